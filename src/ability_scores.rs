@@ -0,0 +1,306 @@
+use std::fmt;
+
+use crate::dice::DiceExpression;
+use crate::roller::Roller;
+use crate::{
+    Ability, AbilityScore, AbilitySet, ABILITIES, EFFECTIVE_ABILITY_SCORE_MAX,
+    EFFECTIVE_ABILITY_SCORE_MIN,
+};
+
+pub const STANDARD_ARRAY: [u8; 6] = [15, 14, 13, 12, 10, 8];
+
+pub const POINT_BUY_BUDGET: u8 = 27;
+pub const POINT_BUY_SCORE_MIN: u8 = 8;
+pub const POINT_BUY_SCORE_MAX: u8 = 15;
+
+#[rustfmt::skip]
+const POINT_BUY_COST_TABLE: [(u8, u8); 8] = [
+    (8, 0), (9, 1), (10, 2), (11, 3), (12, 4), (13, 5), (14, 7), (15, 9),
+];
+
+const ABILITY_SCORE_ROLL: &str = "4d6dl1";
+
+#[derive(Debug, PartialEq)]
+pub enum AbilityScoreError {
+    ScoreOutOfRange(Ability, u8),
+    MissingAbility(Ability),
+    DuplicateAbility(Ability),
+    NotStandardArray(Vec<u8>),
+    PointBuyScoreOutOfRange(Ability, u8),
+    PointBuyOverBudget(u8),
+}
+
+impl fmt::Display for AbilityScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbilityScoreError::ScoreOutOfRange(ability, score) => write!(
+                f,
+                "{:?} score {} is outside {}..={}",
+                ability, score, EFFECTIVE_ABILITY_SCORE_MIN, EFFECTIVE_ABILITY_SCORE_MAX
+            ),
+            AbilityScoreError::MissingAbility(ability) => {
+                write!(f, "no score was assigned to {:?}", ability)
+            }
+            AbilityScoreError::DuplicateAbility(ability) => {
+                write!(f, "{:?} was assigned more than one score", ability)
+            }
+            AbilityScoreError::NotStandardArray(scores) => write!(
+                f,
+                "scores {:?} are not the standard array {:?}",
+                scores, STANDARD_ARRAY
+            ),
+            AbilityScoreError::PointBuyScoreOutOfRange(ability, score) => write!(
+                f,
+                "{:?} score {} can't be bought, point buy scores range from {} to {}",
+                ability, score, POINT_BUY_SCORE_MIN, POINT_BUY_SCORE_MAX
+            ),
+            AbilityScoreError::PointBuyOverBudget(spent) => write!(
+                f,
+                "{} points spent, but only {} are available",
+                spent, POINT_BUY_BUDGET
+            ),
+        }
+    }
+}
+
+impl AbilityScore {
+    pub fn new(ability: Ability, score: u8) -> Result<AbilityScore, AbilityScoreError> {
+        if !(EFFECTIVE_ABILITY_SCORE_MIN..=EFFECTIVE_ABILITY_SCORE_MAX).contains(&score) {
+            return Err(AbilityScoreError::ScoreOutOfRange(ability, score));
+        }
+
+        Ok(AbilityScore {
+            ability,
+            score,
+            modifier: crate::derive_ability_modifier_from_ability_score(score),
+        })
+    }
+}
+
+impl AbilitySet {
+    // Builds a set from one score per ability, in any order.
+    pub fn from_scores(scores: &[(Ability, u8)]) -> Result<AbilitySet, AbilityScoreError> {
+        for (index, (ability, _)) in scores.iter().enumerate() {
+            if scores[..index].iter().any(|(other, _)| other == ability) {
+                return Err(AbilityScoreError::DuplicateAbility(*ability));
+            }
+        }
+
+        let mut ability_scores = vec![];
+        for &ability in ABILITIES.iter() {
+            let score = scores
+                .iter()
+                .find(|(assigned, _)| *assigned == ability)
+                .map(|&(_, score)| score)
+                .ok_or(AbilityScoreError::MissingAbility(ability))?;
+            ability_scores.push(AbilityScore::new(ability, score)?);
+        }
+
+        Ok(AbilitySet([
+            ability_scores[0],
+            ability_scores[1],
+            ability_scores[2],
+            ability_scores[3],
+            ability_scores[4],
+            ability_scores[5],
+        ]))
+    }
+
+    pub fn increase_ability_score(
+        &mut self,
+        ability: Ability,
+        amount: u8,
+    ) -> Result<(), AbilityScoreError> {
+        let ability_score = self
+            .0
+            .iter_mut()
+            .find(|ability_score| ability_score.ability == ability)
+            .expect("Ability score not found");
+        *ability_score = AbilityScore::new(ability, ability_score.score.saturating_add(amount))?;
+        Ok(())
+    }
+
+    pub fn decrease_ability_score(
+        &mut self,
+        ability: Ability,
+        amount: u8,
+    ) -> Result<(), AbilityScoreError> {
+        let ability_score = self
+            .0
+            .iter_mut()
+            .find(|ability_score| ability_score.ability == ability)
+            .expect("Ability score not found");
+        *ability_score = AbilityScore::new(ability, ability_score.score.saturating_sub(amount))?;
+        Ok(())
+    }
+}
+
+pub fn calculate_point_buy_cost(score: u8) -> Option<u8> {
+    POINT_BUY_COST_TABLE
+        .iter()
+        .find(|&&(table_score, _)| table_score == score)
+        .map(|&(_, cost)| cost)
+}
+
+pub fn create_ability_set_from_standard_array(
+    assignment: &[(Ability, u8)],
+) -> Result<AbilitySet, AbilityScoreError> {
+    let mut assigned: Vec<u8> = assignment.iter().map(|&(_, score)| score).collect();
+    let mut expected = STANDARD_ARRAY.to_vec();
+    assigned.sort_unstable();
+    expected.sort_unstable();
+    if assigned != expected {
+        return Err(AbilityScoreError::NotStandardArray(
+            assignment.iter().map(|&(_, score)| score).collect(),
+        ));
+    }
+
+    AbilitySet::from_scores(assignment)
+}
+
+// The six abilities are checked before the costs are added up, so a malformed
+// assignment can't overflow the total.
+pub fn create_ability_set_from_point_buy(
+    assignment: &[(Ability, u8)],
+) -> Result<AbilitySet, AbilityScoreError> {
+    let ability_set = AbilitySet::from_scores(assignment)?;
+    let mut spent = 0;
+    for &(ability, score) in assignment.iter() {
+        spent += calculate_point_buy_cost(score)
+            .ok_or(AbilityScoreError::PointBuyScoreOutOfRange(ability, score))?;
+    }
+
+    if spent > POINT_BUY_BUDGET {
+        return Err(AbilityScoreError::PointBuyOverBudget(spent));
+    }
+
+    Ok(ability_set)
+}
+
+// Rolls six scores, each the total of the highest three of four d6.
+pub fn roll_ability_scores(roller: &mut dyn Roller) -> [u8; 6] {
+    let expression: DiceExpression = ABILITY_SCORE_ROLL
+        .parse()
+        .expect("Ability score roll is a valid dice expression");
+
+    let mut scores = [0; 6];
+    for score in scores.iter_mut() {
+        *score = expression.roll(roller).total as u8;
+    }
+    scores
+}
+
+// Assigns rolled scores to abilities, the first score going to the first ability and so on.
+pub fn create_ability_set_from_rolls(
+    rolled_scores: [u8; 6],
+    ability_order: [Ability; 6],
+) -> Result<AbilitySet, AbilityScoreError> {
+    let assignment: Vec<(Ability, u8)> = ability_order
+        .iter()
+        .copied()
+        .zip(rolled_scores.iter().copied())
+        .collect();
+
+    AbilitySet::from_scores(&assignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roller::ScriptedRoller;
+
+    fn assign(scores: [u8; 6]) -> Vec<(Ability, u8)> {
+        ABILITIES
+            .iter()
+            .copied()
+            .zip(scores.iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn standard_array_assignment() {
+        let ability_set =
+            create_ability_set_from_standard_array(&assign([8, 14, 13, 15, 12, 10])).unwrap();
+        assert_eq!(ability_set[Ability::Intelligence].score, 15);
+        assert_eq!(ability_set[Ability::Intelligence].modifier, 2);
+        assert_eq!(ability_set[Ability::Strength].modifier, -1);
+
+        assert_eq!(
+            create_ability_set_from_standard_array(&assign([15, 15, 13, 12, 10, 8])).err(),
+            Some(AbilityScoreError::NotStandardArray(vec![
+                15, 15, 13, 12, 10, 8
+            ]))
+        );
+    }
+
+    #[test]
+    fn point_buy_validation() {
+        let ability_set =
+            create_ability_set_from_point_buy(&assign([15, 15, 15, 8, 8, 8])).unwrap();
+        assert_eq!(ability_set[Ability::Constitution].score, 15);
+
+        assert_eq!(
+            create_ability_set_from_point_buy(&assign([15, 15, 15, 9, 8, 8])).err(),
+            Some(AbilityScoreError::PointBuyOverBudget(28))
+        );
+        assert_eq!(
+            create_ability_set_from_point_buy(&assign([16, 8, 8, 8, 8, 8])).err(),
+            Some(AbilityScoreError::PointBuyScoreOutOfRange(
+                Ability::Strength,
+                16
+            ))
+        );
+        assert_eq!(
+            create_ability_set_from_point_buy(&[(Ability::Strength, 15); 40]).err(),
+            Some(AbilityScoreError::DuplicateAbility(Ability::Strength))
+        );
+    }
+
+    #[test]
+    fn every_ability_needs_exactly_one_score() {
+        let mut assignment = assign([10, 10, 10, 10, 10, 10]);
+        assignment[5] = (Ability::Strength, 10);
+        assert_eq!(
+            AbilitySet::from_scores(&assignment).err(),
+            Some(AbilityScoreError::DuplicateAbility(Ability::Strength))
+        );
+        assert_eq!(
+            AbilitySet::from_scores(&assignment[..5]).err(),
+            Some(AbilityScoreError::MissingAbility(Ability::Charisma))
+        );
+        assert_eq!(
+            AbilityScore::new(Ability::Wisdom, 31),
+            Err(AbilityScoreError::ScoreOutOfRange(Ability::Wisdom, 31))
+        );
+    }
+
+    #[test]
+    fn rolled_scores_drop_the_lowest_die() {
+        #[rustfmt::skip]
+        let mut roller = ScriptedRoller::new(vec![
+            6, 6, 6, 1,
+            1, 1, 1, 1,
+            3, 4, 5, 2,
+            6, 5, 1, 2,
+            2, 2, 3, 3,
+            4, 4, 4, 4,
+        ]);
+        let rolled_scores = roll_ability_scores(&mut roller);
+        assert_eq!(rolled_scores, [18, 3, 12, 13, 8, 12]);
+
+        let ability_set = create_ability_set_from_rolls(
+            rolled_scores,
+            [
+                Ability::Strength,
+                Ability::Intelligence,
+                Ability::Constitution,
+                Ability::Dexterity,
+                Ability::Charisma,
+                Ability::Wisdom,
+            ],
+        )
+        .unwrap();
+        assert_eq!(ability_set[Ability::Strength].modifier, 4);
+        assert_eq!(ability_set[Ability::Intelligence].modifier, -4);
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calculate_level_from_experience_points, Character, CHARACTER_ADVANCEMENT_TABLE,
+    EFFECTIVE_LEVEL_MAX, EFFECTIVE_LEVEL_MIN,
+};
+
+// How a character earns levels. With milestone leveling the level is set by the
+// DM and experience points are ignored.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub enum Advancement {
+    #[default]
+    ExperiencePoints,
+    Milestone(u32),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AdvancementError {
+    NotMilestone,
+    LevelOutOfRange(u32),
+}
+
+impl fmt::Display for AdvancementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AdvancementError::NotMilestone => {
+                write!(f, "the character levels up with experience points")
+            }
+            AdvancementError::LevelOutOfRange(level) => write!(
+                f,
+                "level {} is outside of {} to {}",
+                level, EFFECTIVE_LEVEL_MIN, EFFECTIVE_LEVEL_MAX
+            ),
+        }
+    }
+}
+
+fn check_level(level: u32) -> Result<(), AdvancementError> {
+    if !(EFFECTIVE_LEVEL_MIN..=EFFECTIVE_LEVEL_MAX).contains(&level) {
+        return Err(AdvancementError::LevelOutOfRange(level));
+    }
+    Ok(())
+}
+
+impl Advancement {
+    // Only set_milestone_level checks the level, so loaded characters are
+    // checked again.
+    pub fn check_milestone_level(&self) -> Result<(), AdvancementError> {
+        match self {
+            Advancement::Milestone(level) => check_level(*level),
+            Advancement::ExperiencePoints => Ok(()),
+        }
+    }
+}
+
+impl Character {
+    // The character keeps the level its experience points had reached.
+    pub fn use_milestone_leveling(&mut self) {
+        if let Advancement::ExperiencePoints = self.advancement {
+            self.advancement = Advancement::Milestone(calculate_level_from_experience_points(
+                self.experience_points,
+            ));
+        }
+    }
+
+    // The experience points move to the start of the milestone level, unless
+    // they already fall within it.
+    pub fn use_experience_points(&mut self) {
+        let level = match self.advancement {
+            Advancement::Milestone(level) => level,
+            Advancement::ExperiencePoints => return,
+        };
+        if calculate_level_from_experience_points(self.experience_points) != level {
+            if let Some(entry) = CHARACTER_ADVANCEMENT_TABLE
+                .iter()
+                .find(|entry| entry.level == level)
+            {
+                self.experience_points = entry.required_experience_points;
+            }
+        }
+        self.advancement = Advancement::ExperiencePoints;
+        self.refresh_derived_statistics();
+    }
+
+    // Class levels follow the next time gain_level is called.
+    pub fn set_milestone_level(&mut self, level: u32) -> Result<(), AdvancementError> {
+        check_level(level)?;
+        match self.advancement {
+            Advancement::Milestone(_) => {
+                self.advancement = Advancement::Milestone(level);
+                self.refresh_derived_statistics();
+                Ok(())
+            }
+            Advancement::ExperiencePoints => Err(AdvancementError::NotMilestone),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::create_sample_character;
+    use crate::ClassType;
+
+    #[test]
+    fn milestone_leveling_ignores_experience_points() {
+        let mut character = create_sample_character();
+        character.experience_points = 6500;
+        assert_eq!(character.get_proficiency_bonus(), 3);
+        assert_eq!(
+            character.set_milestone_level(3),
+            Err(AdvancementError::NotMilestone)
+        );
+
+        character.use_milestone_leveling();
+        assert_eq!(character.advancement, Advancement::Milestone(5));
+        character.set_milestone_level(3).unwrap();
+        character.experience_points = 355000;
+        assert_eq!(character.get_current_level(), 3);
+        assert_eq!(character.get_proficiency_bonus(), 2);
+        character.gain_level(ClassType::Barbarian, &mut ScriptedRoller::new(vec![]));
+        assert_eq!(character.get_class_level(ClassType::Barbarian), 3);
+        assert_eq!(
+            character.set_milestone_level(21),
+            Err(AdvancementError::LevelOutOfRange(21))
+        );
+
+        character.use_experience_points();
+        assert_eq!(character.experience_points, 900);
+        assert_eq!(character.level, 3);
+        assert!(character.find_stale_statistics().is_empty());
+    }
+}
@@ -0,0 +1,427 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::weapon_attacks::WeaponProficiency;
+use crate::{
+    f32, Ability, Armor, ArmorCategory, ArmorClassEffect, ArmorType, Character, ClassType,
+    SpeedEffect, WeaponCategory,
+};
+
+const UNARMORED_BASE_ARMOR_CLASS: i16 = 10;
+const MEDIUM_ARMOR_DEXTERITY_MAX: i8 = 2;
+const STRENGTH_REQUIREMENT_SPEED_PENALTY: i64 = 10;
+
+const UNARMORED_DEFENSE: &str = "Unarmored Defense";
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Shield {
+    pub cost: f32::Coin,
+    pub armor_class_bonus: i16,
+    pub weight: u32,
+    #[serde(default)]
+    pub magic_bonus: i16,
+}
+
+// The rule that sets the armor class before shields and bonuses are added.
+// Only one formula applies at a time, so the best one is chosen.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArmorClassFormula {
+    Unarmored,
+    Armor(ArmorType),
+    UnarmoredDefense(ClassType),
+    // A trait such as Mage Armor that replaces the unarmored formula.
+    Trait(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArmorClassComponent {
+    pub source: String,
+    pub value: i16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArmorClass {
+    pub total: i16,
+    pub formula: ArmorClassFormula,
+    pub components: Vec<ArmorClassComponent>,
+}
+
+// For example "17 = 13 ChainShirt + 2 Dexterity + 2 Shield".
+impl fmt::Display for ArmorClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} =", self.total)?;
+        for (index, component) in self.components.iter().enumerate() {
+            let sign = match (index, component.value < 0) {
+                (0, false) => "",
+                (0, true) => "-",
+                (_, false) => "+ ",
+                (_, true) => "- ",
+            };
+            write!(f, " {}{} {}", sign, component.value.abs(), component.source)?;
+        }
+        Ok(())
+    }
+}
+
+fn component(source: &str, value: i16) -> ArmorClassComponent {
+    ArmorClassComponent {
+        source: String::from(source),
+        value,
+    }
+}
+
+fn total(components: &[ArmorClassComponent]) -> i16 {
+    components.iter().map(|component| component.value).sum()
+}
+
+impl Character {
+    // Shield proficiency is listed with the weapon proficiencies of a class.
+    pub fn has_shield_proficiency(&self) -> bool {
+        self.get_weapon_proficiencies().any(|weapon_proficiency| {
+            *weapon_proficiency == WeaponProficiency::Category(WeaponCategory::Shields)
+        })
+    }
+
+    fn get_ability_component(&self, ability: Ability, max: Option<i8>) -> ArmorClassComponent {
+        let modifier = self.get_ability_modifier(ability);
+        let modifier = max.map_or(modifier, |max| modifier.min(max));
+        component(&format!("{:?}", ability), modifier as i16)
+    }
+
+    // Traits like Medium Armor Master raise the Dexterity cap of medium armor
+    // when the Dexterity score is high enough.
+    fn get_medium_armor_dexterity_max(&self) -> i8 {
+        let dexterity = self.ability_scores[Ability::Dexterity].score;
+        self.traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.armor_class_modifiers.iter())
+            .filter_map(|armor_class_modifier| match armor_class_modifier.value {
+                ArmorClassEffect::MediumArmorDexterityMax {
+                    maximum,
+                    dexterity_minimum,
+                } if dexterity >= dexterity_minimum => Some(maximum),
+                _ => None,
+            })
+            .fold(MEDIUM_ARMOR_DEXTERITY_MAX, i8::max)
+    }
+
+    fn get_armor_formula(&self, armor: &Armor) -> (ArmorClassFormula, Vec<ArmorClassComponent>) {
+        let mut components = vec![component(
+            &format!("{:?}", armor.armor_type),
+            armor.base_armor_class as i16,
+        )];
+        match armor.category {
+            ArmorCategory::LightArmor => {
+                components.push(self.get_ability_component(Ability::Dexterity, None))
+            }
+            ArmorCategory::MediumArmor => components.push(self.get_ability_component(
+                Ability::Dexterity,
+                Some(self.get_medium_armor_dexterity_max()),
+            )),
+            ArmorCategory::HeavyArmor => {}
+        }
+        if armor.magic_bonus != 0 {
+            components.push(component("armor magic bonus", armor.magic_bonus));
+        }
+        (ArmorClassFormula::Armor(armor.armor_type), components)
+    }
+
+    // Barbarians add their Constitution modifier while unarmored, and Monks their
+    // Wisdom modifier as long as they also don't use a shield.
+    fn get_unarmored_formulas(
+        &self,
+        has_shield: bool,
+    ) -> Vec<(ArmorClassFormula, Vec<ArmorClassComponent>)> {
+        let unarmored_base = || {
+            vec![
+                component("unarmored", UNARMORED_BASE_ARMOR_CLASS),
+                self.get_ability_component(Ability::Dexterity, None),
+            ]
+        };
+        let mut formulas = vec![(ArmorClassFormula::Unarmored, unarmored_base())];
+
+        for class_feature in self
+            .class_features
+            .iter()
+            .filter(|class_feature| class_feature.name == UNARMORED_DEFENSE)
+        {
+            let ability = match class_feature.class_type {
+                ClassType::Barbarian => Ability::Constitution,
+                ClassType::Monk if !has_shield => Ability::Wisdom,
+                _ => continue,
+            };
+            let mut components = unarmored_base();
+            components.push(self.get_ability_component(ability, None));
+            formulas.push((
+                ArmorClassFormula::UnarmoredDefense(class_feature.class_type),
+                components,
+            ));
+        }
+
+        for armor_class_modifier in self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.armor_class_modifiers.iter())
+        {
+            if let ArmorClassEffect::Formula { base, abilities } = &armor_class_modifier.value {
+                let mut components = vec![component(&armor_class_modifier.name, *base)];
+                components.extend(
+                    abilities
+                        .iter()
+                        .map(|&ability| self.get_ability_component(ability, None)),
+                );
+                formulas.push((
+                    ArmorClassFormula::Trait(armor_class_modifier.name.clone()),
+                    components,
+                ));
+            }
+        }
+
+        formulas
+    }
+
+    // Armor sets the formula while it's worn. Without armor the best of the
+    // unarmored formulas is used. Shields and bonuses from traits, such as a
+    // ring of protection, are added on top.
+    pub fn get_armor_class(&self, armor: Option<&Armor>, shield: Option<&Shield>) -> ArmorClass {
+        let formulas = match armor {
+            Some(armor) => vec![self.get_armor_formula(armor)],
+            None => self.get_unarmored_formulas(shield.is_some()),
+        };
+        let (formula, mut components) = formulas
+            .into_iter()
+            .max_by_key(|(_, components)| total(components))
+            .expect("The unarmored formula always applies");
+
+        if let Some(shield) = shield {
+            components.push(component("Shield", shield.armor_class_bonus));
+            if shield.magic_bonus != 0 {
+                components.push(component("shield magic bonus", shield.magic_bonus));
+            }
+        }
+        for armor_class_modifier in self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.armor_class_modifiers.iter())
+        {
+            if let ArmorClassEffect::Bonus(bonus) = armor_class_modifier.value {
+                components.push(component(&armor_class_modifier.name, bonus));
+            }
+        }
+
+        ArmorClass {
+            total: total(&components),
+            formula,
+            components,
+        }
+    }
+
+    // Armor with a Strength requirement reduces the wearer's speed by 10 feet
+    // unless their score meets it. Traits like the dwarf's Speed ignore it.
+    pub fn get_armor_speed_penalty(&self, armor: &Armor) -> i64 {
+        let ignores_requirement = self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.speed_modifiers.iter())
+            .any(|speed_modifier| {
+                speed_modifier.value == SpeedEffect::IgnoreArmorStrengthRequirement
+            });
+        if ignores_requirement {
+            return 0;
+        }
+
+        match armor.ability_requirement {
+            Some(requirement)
+                if self.ability_scores[requirement.ability].score < requirement.score =>
+            {
+                STRENGTH_REQUIREMENT_SPEED_PENALTY
+            }
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_progression::ClassFeature;
+    use crate::tests::create_sample_character;
+    use crate::{
+        load_armor_from_file, load_feats_from_file, load_races_from_file, ArmorClassModifier,
+        RaceType, Trait,
+    };
+
+    fn find_armor(armor_type: ArmorType) -> Armor {
+        load_armor_from_file("data/armor.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|armor| armor.armor_type == armor_type)
+            .unwrap()
+    }
+
+    fn shield() -> Shield {
+        Shield {
+            cost: f32::Coin::new::<crate::coin::gold>(10.0),
+            armor_class_bonus: 2,
+            weight: 6,
+            magic_bonus: 0,
+        }
+    }
+
+    fn set_ability_score(character: &mut Character, ability: Ability, score: u8) {
+        let ability_score = character
+            .ability_scores
+            .0
+            .iter_mut()
+            .find(|ability_score| ability_score.ability == ability)
+            .unwrap();
+        ability_score.score = score;
+        character.refresh_derived_statistics();
+    }
+
+    #[test]
+    fn armor_categories_limit_dexterity() {
+        let mut character = create_sample_character();
+        set_ability_score(&mut character, Ability::Dexterity, 18);
+
+        let studded_leather = find_armor(ArmorType::StuddedLeather);
+        assert_eq!(
+            character
+                .get_armor_class(Some(&studded_leather), None)
+                .total,
+            16
+        );
+
+        let mut half_plate = find_armor(ArmorType::HalfPlate);
+        half_plate.magic_bonus = 1;
+        let armor_class = character.get_armor_class(Some(&half_plate), Some(&shield()));
+        assert_eq!(armor_class.total, 20);
+        assert_eq!(
+            armor_class.to_string(),
+            "20 = 15 HalfPlate + 2 Dexterity + 1 armor magic bonus + 2 Shield"
+        );
+
+        let plate = find_armor(ArmorType::Plate);
+        assert_eq!(character.get_armor_class(Some(&plate), None).total, 18);
+        set_ability_score(&mut character, Ability::Strength, 14);
+        assert_eq!(character.get_armor_speed_penalty(&plate), 10);
+        assert_eq!(
+            character.get_armor_speed_penalty(&find_armor(ArmorType::ChainMail)),
+            0
+        );
+    }
+
+    #[test]
+    fn medium_armor_master_raises_the_dexterity_cap() {
+        let mut character = create_sample_character();
+        set_ability_score(&mut character, Ability::Dexterity, 14);
+        let half_plate = find_armor(ArmorType::HalfPlate);
+        assert_eq!(character.get_armor_class(Some(&half_plate), None).total, 17);
+
+        let medium_armor_master = load_feats_from_file("data/feats.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|feat| feat.name == "Medium Armor Master")
+            .unwrap();
+        character.traits.extend(medium_armor_master.traits);
+        assert_eq!(character.get_armor_class(Some(&half_plate), None).total, 17);
+
+        set_ability_score(&mut character, Ability::Dexterity, 16);
+        assert_eq!(
+            character
+                .get_armor_class(Some(&half_plate), None)
+                .to_string(),
+            "18 = 15 HalfPlate + 3 Dexterity"
+        );
+        let studded_leather = find_armor(ArmorType::StuddedLeather);
+        assert_eq!(
+            character
+                .get_armor_class(Some(&studded_leather), None)
+                .total,
+            15
+        );
+    }
+
+    #[test]
+    fn dwarves_ignore_the_strength_requirement_for_speed() {
+        let mut character = create_sample_character();
+        set_ability_score(&mut character, Ability::Strength, 8);
+        let plate = find_armor(ArmorType::Plate);
+        assert_eq!(character.get_armor_speed_penalty(&plate), 10);
+
+        character.race = load_races_from_file("data/races.yaml")
+            .unwrap()
+            .find_race(RaceType::Dwarf)
+            .unwrap();
+        assert_eq!(character.get_armor_speed_penalty(&plate), 0);
+    }
+
+    #[test]
+    fn the_best_unarmored_formula_is_chosen() {
+        let mut character = create_sample_character();
+        set_ability_score(&mut character, Ability::Dexterity, 14);
+        set_ability_score(&mut character, Ability::Constitution, 16);
+        set_ability_score(&mut character, Ability::Wisdom, 12);
+        assert_eq!(
+            character.get_armor_class(None, None).formula,
+            ArmorClassFormula::Unarmored
+        );
+
+        character.class_features.push(ClassFeature {
+            class_type: ClassType::Barbarian,
+            level: 1,
+            name: String::from(UNARMORED_DEFENSE),
+        });
+        let armor_class = character.get_armor_class(None, Some(&shield()));
+        assert_eq!(
+            armor_class.formula,
+            ArmorClassFormula::UnarmoredDefense(ClassType::Barbarian)
+        );
+        assert_eq!(
+            armor_class.to_string(),
+            "17 = 10 unarmored + 2 Dexterity + 3 Constitution + 2 Shield"
+        );
+
+        character.traits.push(Trait {
+            name: String::from("Mage Armor"),
+            description: String::from("The target's base AC becomes 13 + its Dexterity modifier."),
+            armor_class_modifiers: vec![
+                ArmorClassModifier {
+                    name: String::from("Mage Armor"),
+                    value: ArmorClassEffect::Formula {
+                        base: 13,
+                        abilities: vec![Ability::Dexterity],
+                    },
+                },
+                ArmorClassModifier {
+                    name: String::from("Ring of Protection"),
+                    value: ArmorClassEffect::Bonus(1),
+                },
+            ],
+            ..Default::default()
+        });
+        set_ability_score(&mut character, Ability::Constitution, 18);
+        let armor_class = character.get_armor_class(None, None);
+        assert_eq!(
+            armor_class.formula,
+            ArmorClassFormula::UnarmoredDefense(ClassType::Barbarian)
+        );
+        assert_eq!(armor_class.total, 17);
+
+        set_ability_score(&mut character, Ability::Constitution, 10);
+        let armor_class = character.get_armor_class(None, None);
+        assert_eq!(
+            armor_class.formula,
+            ArmorClassFormula::Trait(String::from("Mage Armor"))
+        );
+        assert_eq!(
+            armor_class.to_string(),
+            "16 = 13 Mage Armor + 2 Dexterity + 1 Ring of Protection"
+        );
+    }
+}
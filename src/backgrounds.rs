@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dice::{roll_die, Die};
+use crate::inventory::InventoryEntry;
+use crate::roller::Roller;
+use crate::{f32, Ability, Character, Language, Skill, Trait};
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum BackgroundType {
+    Acolyte,
+    Charlatan,
+    Criminal,
+    Entertainer,
+    FolkHero,
+    GuildArtisan,
+    Hermit,
+    Noble,
+    Outlander,
+    Sage,
+    Sailor,
+    Soldier,
+    Urchin,
+}
+
+// A background picks two personality traits, and one each of ideal, bond and flaw.
+const PERSONALITY_TRAIT_COUNT: usize = 2;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Background {
+    pub background_type: BackgroundType,
+    pub skill_proficiencies: Vec<Skill>,
+    pub tool_proficiencies: Vec<String>,
+    pub language_choices: u8,
+    pub equipment: Vec<InventoryEntry>,
+    pub gold: f32::Coin,
+    pub feature: Trait,
+    pub personality_traits: Vec<String>,
+    pub ideals: Vec<String>,
+    pub bonds: Vec<String>,
+    pub flaws: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Personality {
+    pub personality_traits: Vec<String>,
+    pub ideal: String,
+    pub bond: String,
+    pub flaw: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CharacterBackground {
+    pub background: Background,
+    pub languages: Vec<Language>,
+    pub personality: Personality,
+}
+
+impl Background {
+    // Every table needs an entry to roll, and the personality traits enough
+    // different entries to pick from.
+    pub fn find_table_problem(&self) -> Option<String> {
+        let tables = [
+            ("ideals", &self.ideals),
+            ("bonds", &self.bonds),
+            ("flaws", &self.flaws),
+        ];
+        if let Some((table, _)) = tables.iter().find(|(_, entries)| entries.is_empty()) {
+            return Some(format!(
+                "{:?} has no {} to roll on",
+                self.background_type, table
+            ));
+        }
+
+        let distinct_traits = count_distinct(&self.personality_traits);
+        if distinct_traits < PERSONALITY_TRAIT_COUNT {
+            return Some(format!(
+                "{:?} lists {} different personality traits instead of at least {}",
+                self.background_type, distinct_traits, PERSONALITY_TRAIT_COUNT
+            ));
+        }
+
+        None
+    }
+
+    // Rolls on each of the background's tables, with a die sized to the table.
+    pub fn roll_personality(&self, roller: &mut dyn Roller) -> Personality {
+        let mut personality_traits: Vec<String> = vec![];
+        let trait_count = PERSONALITY_TRAIT_COUNT.min(count_distinct(&self.personality_traits));
+        while personality_traits.len() < trait_count {
+            let personality_trait = roll_on_table(&self.personality_traits, roller);
+            if !personality_traits.contains(&personality_trait) {
+                personality_traits.push(personality_trait);
+            }
+        }
+
+        Personality {
+            personality_traits,
+            ideal: roll_on_table(&self.ideals, roller),
+            bond: roll_on_table(&self.bonds, roller),
+            flaw: roll_on_table(&self.flaws, roller),
+        }
+    }
+}
+
+impl Character {
+    // Tool proficiencies come from the background, like a Criminal's thieves' tools.
+    pub fn has_tool_proficiency(&self, tool: &str) -> bool {
+        self.background.iter().any(|character_background| {
+            character_background
+                .background
+                .tool_proficiencies
+                .iter()
+                .any(|proficiency| proficiency.eq_ignore_ascii_case(tool))
+        })
+    }
+
+    // A check made with a tool adds the proficiency bonus when the character is
+    // proficient with it, whichever ability the check uses.
+    pub fn get_tool_check_modifier(&self, tool: &str, ability: Ability) -> i8 {
+        let proficiency_bonus = if self.has_tool_proficiency(tool) {
+            self.get_proficiency_bonus() as i8
+        } else {
+            0
+        };
+        self.get_ability_modifier(ability) + proficiency_bonus
+    }
+}
+
+fn count_distinct(table: &[String]) -> usize {
+    let mut distinct: Vec<&String> = table.iter().collect();
+    distinct.sort();
+    distinct.dedup();
+    distinct.len()
+}
+
+// An empty table has nothing to roll on, so it gives an empty entry.
+fn roll_on_table(table: &[String], roller: &mut dyn Roller) -> String {
+    if table.is_empty() {
+        return String::new();
+    }
+    let roll = roll_die(Die::with_sides(table.len() as u16), roller);
+    table[roll as usize - 1].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::build_character;
+    use crate::{coin, load_backgrounds_from_file, ClassType, RaceType};
+
+    fn find_background(background_type: BackgroundType) -> Background {
+        load_backgrounds_from_file("data/backgrounds.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|background| background.background_type == background_type)
+            .expect("Background is in the backgrounds data file")
+    }
+
+    #[test]
+    fn load_sage_background() {
+        let sage = find_background(BackgroundType::Sage);
+        assert_eq!(
+            sage.skill_proficiencies,
+            vec![Skill::Arcana, Skill::History]
+        );
+        assert_eq!(sage.language_choices, 2);
+        assert_eq!(sage.gold, f32::Coin::new::<coin::gold>(10.0));
+        assert_eq!(sage.feature.name, "Researcher");
+    }
+
+    #[test]
+    fn every_background_has_data() {
+        let backgrounds = load_backgrounds_from_file("data/backgrounds.yaml").unwrap();
+        let background_types: Vec<BackgroundType> = backgrounds
+            .iter()
+            .map(|background| background.background_type)
+            .collect();
+        assert_eq!(
+            background_types,
+            vec![
+                BackgroundType::Acolyte,
+                BackgroundType::Charlatan,
+                BackgroundType::Criminal,
+                BackgroundType::Entertainer,
+                BackgroundType::FolkHero,
+                BackgroundType::GuildArtisan,
+                BackgroundType::Hermit,
+                BackgroundType::Noble,
+                BackgroundType::Outlander,
+                BackgroundType::Sage,
+                BackgroundType::Sailor,
+                BackgroundType::Soldier,
+                BackgroundType::Urchin,
+            ]
+        );
+    }
+
+    #[test]
+    fn tool_proficiencies_come_from_the_background() {
+        let scores = [
+            (Ability::Strength, 8),
+            (Ability::Dexterity, 15),
+            (Ability::Constitution, 13),
+            (Ability::Intelligence, 12),
+            (Ability::Wisdom, 14),
+            (Ability::Charisma, 10),
+        ];
+        let character = build_character(
+            RaceType::Human,
+            ClassType::Rogue,
+            &scores,
+            BackgroundType::Urchin,
+        );
+
+        assert!(character.has_tool_proficiency("Thieves' tools"));
+        assert!(character.has_tool_proficiency("disguise kit"));
+        assert!(!character.has_tool_proficiency("Herbalism kit"));
+        assert_eq!(
+            character.get_tool_check_modifier("Thieves' tools", Ability::Dexterity),
+            5
+        );
+        assert_eq!(
+            character.get_tool_check_modifier("Herbalism kit", Ability::Wisdom),
+            2
+        );
+    }
+
+    #[test]
+    fn personality_traits_are_distinct() {
+        let soldier = find_background(BackgroundType::Soldier);
+        let mut roller = ScriptedRoller::new(vec![2, 2, 4, 1, 3, 2]);
+        let personality = soldier.roll_personality(&mut roller);
+
+        assert_eq!(
+            personality.personality_traits,
+            vec![
+                soldier.personality_traits[1].clone(),
+                soldier.personality_traits[3].clone()
+            ]
+        );
+        assert_eq!(personality.ideal, soldier.ideals[0]);
+        assert_eq!(personality.bond, soldier.bonds[2]);
+        assert_eq!(personality.flaw, soldier.flaws[1]);
+        assert_eq!(roller.remaining(), 0);
+    }
+
+    #[test]
+    fn tables_without_enough_entries_are_reported() {
+        let mut soldier = find_background(BackgroundType::Soldier);
+        assert_eq!(soldier.find_table_problem(), None);
+
+        let personality_trait = soldier.personality_traits[0].clone();
+        soldier.personality_traits = vec![personality_trait.clone(), personality_trait];
+        assert_eq!(
+            soldier.find_table_problem(),
+            Some(String::from(
+                "Soldier lists 1 different personality traits instead of at least 2"
+            ))
+        );
+        let mut roller = ScriptedRoller::new(vec![2, 1, 1, 1]);
+        let personality = soldier.roll_personality(&mut roller);
+        assert_eq!(personality.personality_traits.len(), 1);
+        assert_eq!(roller.remaining(), 0);
+
+        soldier.bonds.clear();
+        assert_eq!(
+            soldier.find_table_problem(),
+            Some(String::from("Soldier has no bonds to roll on"))
+        );
+        let personality = soldier.roll_personality(&mut ScriptedRoller::new(vec![1, 1, 1]));
+        assert_eq!(personality.bond, "");
+    }
+}
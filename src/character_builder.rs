@@ -0,0 +1,612 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ability_scores::{
+    create_ability_set_from_point_buy, create_ability_set_from_rolls,
+    create_ability_set_from_standard_array, roll_ability_scores, AbilityScoreError,
+};
+use crate::advancement::Advancement;
+use crate::backgrounds::{Background, BackgroundType, CharacterBackground, Personality};
+use crate::dying::DeathSavingThrows;
+use crate::equipment::Equipment;
+use crate::hit_points::HitPoints;
+use crate::inventory::{CoinDenomination, Inventory, InventoryEntry, InventoryError};
+use crate::roller::Roller;
+use crate::{
+    coin, Ability, AbilitySet, Alignment, Character, Class, ClassType, Item, Language, Race,
+    RaceType, SavingThrowSet, SkillSet, ABILITIES, EFFECTIVE_LEVEL_MIN,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CreationStep {
+    Race,
+    Class,
+    AbilityScores,
+    Background,
+    Equipment,
+}
+
+// Steps have to be taken in this order, since later steps depend on earlier ones
+// (racial increases are applied on top of the assigned ability scores).
+const CREATION_STEPS: [CreationStep; 5] = [
+    CreationStep::Race,
+    CreationStep::Class,
+    CreationStep::AbilityScores,
+    CreationStep::Background,
+    CreationStep::Equipment,
+];
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum AbilityScoreMethod {
+    StandardArray,
+    PointBuy,
+    Rolled,
+}
+
+// A record of each decision made while building a character, in the order it was made.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum CreationChoice {
+    Race(RaceType),
+    Class(ClassType),
+    AbilityScores {
+        method: AbilityScoreMethod,
+        scores: Vec<(Ability, u8)>,
+    },
+    Background {
+        background: BackgroundType,
+        languages: Vec<Language>,
+        personality: Personality,
+    },
+    Equipment(Vec<(String, u32)>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CharacterBuilderError {
+    OutOfOrder {
+        step: CreationStep,
+        expected: Option<CreationStep>,
+    },
+    MissingStep(CreationStep),
+    AbilityScore(AbilityScoreError),
+    LanguageChoices {
+        expected: u8,
+        chosen: usize,
+    },
+    KnownLanguage(Language),
+    Inventory(InventoryError),
+}
+
+impl fmt::Display for CharacterBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CharacterBuilderError::OutOfOrder {
+                step,
+                expected: Some(expected),
+            } => write!(f, "can't choose {:?} before {:?}", step, expected),
+            CharacterBuilderError::OutOfOrder {
+                step,
+                expected: None,
+            } => write!(f, "{:?} has already been chosen", step),
+            CharacterBuilderError::MissingStep(step) => {
+                write!(
+                    f,
+                    "the character is incomplete, {:?} hasn't been chosen",
+                    step
+                )
+            }
+            CharacterBuilderError::AbilityScore(error) => write!(f, "{}", error),
+            CharacterBuilderError::LanguageChoices { expected, chosen } => write!(
+                f,
+                "the background grants {} languages, but {} were chosen",
+                expected, chosen
+            ),
+            CharacterBuilderError::KnownLanguage(language) => {
+                write!(f, "{:?} is already known", language)
+            }
+            CharacterBuilderError::Inventory(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<AbilityScoreError> for CharacterBuilderError {
+    fn from(error: AbilityScoreError) -> Self {
+        CharacterBuilderError::AbilityScore(error)
+    }
+}
+
+impl From<InventoryError> for CharacterBuilderError {
+    fn from(error: InventoryError) -> Self {
+        CharacterBuilderError::Inventory(error)
+    }
+}
+
+pub struct CharacterBuilder {
+    name: String,
+    age: u32,
+    alignment: Alignment,
+    race: Option<Race>,
+    class: Option<Class>,
+    ability_scores: Option<AbilitySet>,
+    background: Option<CharacterBackground>,
+    equipment: Option<Vec<InventoryEntry>>,
+    choices: Vec<CreationChoice>,
+}
+
+impl CharacterBuilder {
+    pub fn new(name: &str, age: u32, alignment: Alignment) -> CharacterBuilder {
+        CharacterBuilder {
+            name: String::from(name),
+            age,
+            alignment,
+            race: None,
+            class: None,
+            ability_scores: None,
+            background: None,
+            equipment: None,
+            choices: vec![],
+        }
+    }
+
+    // Every step records exactly one choice, so the number of choices is the number of steps taken.
+    pub fn next_step(&self) -> Option<CreationStep> {
+        CREATION_STEPS.get(self.choices.len()).copied()
+    }
+
+    pub fn choices(&self) -> &[CreationChoice] {
+        &self.choices
+    }
+
+    fn begin_step(&self, step: CreationStep) -> Result<(), CharacterBuilderError> {
+        let expected = self.next_step();
+        if expected != Some(step) {
+            return Err(CharacterBuilderError::OutOfOrder { step, expected });
+        }
+        Ok(())
+    }
+
+    pub fn choose_race(&mut self, race: Race) -> Result<(), CharacterBuilderError> {
+        self.begin_step(CreationStep::Race)?;
+        self.choices.push(CreationChoice::Race(race.race_type));
+        self.race = Some(race);
+        Ok(())
+    }
+
+    pub fn choose_class(&mut self, class: Class) -> Result<(), CharacterBuilderError> {
+        self.begin_step(CreationStep::Class)?;
+        self.choices.push(CreationChoice::Class(class.class_type));
+        self.class = Some(class);
+        Ok(())
+    }
+
+    pub fn assign_standard_array(
+        &mut self,
+        assignment: &[(Ability, u8)],
+    ) -> Result<(), CharacterBuilderError> {
+        self.begin_step(CreationStep::AbilityScores)?;
+        let ability_set = create_ability_set_from_standard_array(assignment)?;
+        self.set_ability_scores(AbilityScoreMethod::StandardArray, ability_set);
+        Ok(())
+    }
+
+    pub fn assign_point_buy(
+        &mut self,
+        assignment: &[(Ability, u8)],
+    ) -> Result<(), CharacterBuilderError> {
+        self.begin_step(CreationStep::AbilityScores)?;
+        let ability_set = create_ability_set_from_point_buy(assignment)?;
+        self.set_ability_scores(AbilityScoreMethod::PointBuy, ability_set);
+        Ok(())
+    }
+
+    pub fn roll_ability_scores(
+        &mut self,
+        ability_order: [Ability; 6],
+        roller: &mut dyn Roller,
+    ) -> Result<(), CharacterBuilderError> {
+        self.begin_step(CreationStep::AbilityScores)?;
+        let ability_set =
+            create_ability_set_from_rolls(roll_ability_scores(roller), ability_order)?;
+        self.set_ability_scores(AbilityScoreMethod::Rolled, ability_set);
+        Ok(())
+    }
+
+    fn set_ability_scores(&mut self, method: AbilityScoreMethod, ability_set: AbilitySet) {
+        self.choices.push(CreationChoice::AbilityScores {
+            method,
+            scores: ABILITIES
+                .iter()
+                .map(|&ability| (ability, ability_set[ability].score))
+                .collect(),
+        });
+        self.ability_scores = Some(ability_set);
+    }
+
+    // Languages granted by a background have to be new to the character.
+    pub fn choose_background(
+        &mut self,
+        background: Background,
+        languages: Vec<Language>,
+        personality: Personality,
+    ) -> Result<(), CharacterBuilderError> {
+        self.begin_step(CreationStep::Background)?;
+        if languages.len() != background.language_choices as usize {
+            return Err(CharacterBuilderError::LanguageChoices {
+                expected: background.language_choices,
+                chosen: languages.len(),
+            });
+        }
+
+        let mut known_languages = self
+            .race
+            .as_ref()
+            .map(|race| race.racial_traits.languages.clone())
+            .unwrap_or_default();
+        for &language in languages.iter() {
+            if known_languages.contains(&language) {
+                return Err(CharacterBuilderError::KnownLanguage(language));
+            }
+            known_languages.push(language);
+        }
+
+        self.choices.push(CreationChoice::Background {
+            background: background.background_type,
+            languages: languages.clone(),
+            personality: personality.clone(),
+        });
+        self.background = Some(CharacterBackground {
+            background,
+            languages,
+            personality,
+        });
+        Ok(())
+    }
+
+    // The items picked from the class's starting equipment options. They go into
+    // the inventory when the character is built.
+    pub fn choose_equipment(
+        &mut self,
+        class_equipment: Vec<InventoryEntry>,
+    ) -> Result<(), CharacterBuilderError> {
+        self.begin_step(CreationStep::Equipment)?;
+        if class_equipment.iter().any(|entry| entry.quantity == 0) {
+            return Err(CharacterBuilderError::Inventory(
+                InventoryError::ZeroQuantity,
+            ));
+        }
+        self.choices.push(CreationChoice::Equipment(
+            class_equipment
+                .iter()
+                .map(|entry| (entry.item.get_name(), entry.quantity))
+                .collect(),
+        ));
+        self.equipment = Some(class_equipment);
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Character, CharacterBuilderError> {
+        let race = self
+            .race
+            .ok_or(CharacterBuilderError::MissingStep(CreationStep::Race))?;
+        let class = self
+            .class
+            .ok_or(CharacterBuilderError::MissingStep(CreationStep::Class))?;
+        let mut ability_scores = self
+            .ability_scores
+            .ok_or(CharacterBuilderError::MissingStep(
+                CreationStep::AbilityScores,
+            ))?;
+
+        let background = self
+            .background
+            .ok_or(CharacterBuilderError::MissingStep(CreationStep::Background))?;
+        let equipment = self
+            .equipment
+            .ok_or(CharacterBuilderError::MissingStep(CreationStep::Equipment))?;
+
+        for increase in race.racial_traits.ability_score_increase.iter() {
+            ability_scores.increase_ability_score(increase.ability, increase.value)?;
+        }
+
+        let saving_throws = SavingThrowSet::from_ability_scores(&ability_scores);
+        let skills = SkillSet::from_proficiencies(&background.background.skill_proficiencies);
+        // The background adds its feature, its own equipment and its starting gold.
+        let feature = background.background.feature.clone();
+        let background_equipment = background.background.equipment.clone();
+        let gold = background.background.gold.get::<coin::gold>().round() as u32;
+
+        let mut character = Character {
+            name: self.name,
+            age: self.age,
+            alignment: self.alignment,
+            size: race.racial_traits.size,
+            speed: race.racial_traits.speed as i64,
+            languages: race.racial_traits.languages.clone(),
+            race,
+            class: vec![class],
+            experience_points: 0,
+            level: EFFECTIVE_LEVEL_MIN,
+            ability_scores,
+            saving_throws,
+            traits: vec![feature],
+            roll_hit_points: false,
+            conditions: vec![],
+            creation_choices: self.choices,
+            background: Some(background),
+            class_features: vec![],
+            hit_points_gained: vec![],
+            hit_points: HitPoints::default(),
+            death_saving_throws: DeathSavingThrows::default(),
+            skills,
+            ability_score_improvements: vec![],
+            skill_choices: vec![],
+            advancement: Advancement::default(),
+            inventory: Inventory::default(),
+            variant_encumbrance: false,
+            equipment: Equipment::default(),
+        };
+        for entry in equipment.into_iter().chain(background_equipment) {
+            character.add_item(entry.item, entry.quantity)?;
+        }
+        if gold > 0 {
+            character.add_coins(CoinDenomination::Gold, gold)?;
+        }
+        character.refresh_derived_statistics();
+        character.start_first_class_level();
+
+        Ok(character)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::D12;
+    use crate::inventory::InventoryItem;
+    use crate::roller::ScriptedRoller;
+    use crate::{
+        load_backgrounds_from_file, load_races_from_file, load_weapons_from_file, ClassFeatures,
+        Size, Skill,
+    };
+
+    fn find_race(race_type: RaceType) -> Race {
+        load_races_from_file("data/races.yaml")
+            .unwrap()
+            .find_race(race_type)
+            .expect("Race is in the races data file")
+    }
+
+    fn find_background(background_type: BackgroundType) -> Background {
+        load_backgrounds_from_file("data/backgrounds.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|background| background.background_type == background_type)
+            .expect("Background is in the backgrounds data file")
+    }
+
+    fn personality() -> Personality {
+        Personality {
+            personality_traits: vec![String::from("I'm always polite and respectful.")],
+            ideal: String::from("Might. In life as in war, the stronger force wins."),
+            bond: String::from("My honor is my life."),
+            flaw: String::from("I obey the law, even if the law causes misery."),
+        }
+    }
+
+    fn barbarian() -> Class {
+        Class {
+            class_type: ClassType::Barbarian,
+            level: 1,
+            levels: vec![],
+            features: ClassFeatures {
+                hit_dice: D12,
+                hit_points_starting: 12,
+                hit_points_from_level: D12,
+                weapon_proficiency_modifiers: vec![],
+                armor_proficiency_modifiers: vec![],
+                saving_throw_proficiencies: vec![],
+            },
+        }
+    }
+
+    fn barbarian_equipment() -> Vec<InventoryEntry> {
+        load_weapons_from_file("data/weapons.yaml")
+            .unwrap()
+            .into_iter()
+            .filter_map(|weapon| match weapon.name.as_str() {
+                "Greataxe" => Some((weapon, 1)),
+                "Javelin" => Some((weapon, 4)),
+                _ => None,
+            })
+            .map(|(weapon, quantity)| InventoryEntry {
+                item: InventoryItem::Weapon(weapon),
+                quantity,
+            })
+            .collect()
+    }
+
+    fn assignment() -> Vec<(Ability, u8)> {
+        vec![
+            (Ability::Strength, 15),
+            (Ability::Dexterity, 12),
+            (Ability::Constitution, 14),
+            (Ability::Intelligence, 8),
+            (Ability::Wisdom, 13),
+            (Ability::Charisma, 10),
+        ]
+    }
+
+    #[test]
+    fn racial_traits_are_applied() {
+        let mut builder = CharacterBuilder::new("Tishros", 80, Alignment::ChaoticNeutral);
+        builder.choose_race(find_race(RaceType::HillDwarf)).unwrap();
+        builder.choose_class(barbarian()).unwrap();
+        builder.assign_standard_array(&assignment()).unwrap();
+        builder
+            .choose_background(
+                find_background(BackgroundType::Soldier),
+                vec![],
+                personality(),
+            )
+            .unwrap();
+        builder.choose_equipment(barbarian_equipment()).unwrap();
+        let character = builder.build().unwrap();
+
+        assert_eq!(character.ability_scores[Ability::Constitution].score, 16);
+        assert_eq!(character.ability_scores[Ability::Constitution].modifier, 3);
+        assert_eq!(character.ability_scores[Ability::Wisdom].score, 14);
+        assert_eq!(character.saving_throws[Ability::Constitution].modifier, 3);
+        assert_eq!(character.speed, 25);
+        assert_eq!(character.size, Size::Medium);
+        assert_eq!(
+            character.languages,
+            vec![Language::Common, Language::Dwarvish]
+        );
+        assert_eq!(character.race.racial_traits.darkvision, Some(60));
+        assert_eq!(
+            character.creation_choices,
+            vec![
+                CreationChoice::Race(RaceType::HillDwarf),
+                CreationChoice::Class(ClassType::Barbarian),
+                CreationChoice::AbilityScores {
+                    method: AbilityScoreMethod::StandardArray,
+                    scores: assignment(),
+                },
+                CreationChoice::Background {
+                    background: BackgroundType::Soldier,
+                    languages: vec![],
+                    personality: personality(),
+                },
+                CreationChoice::Equipment(vec![
+                    (String::from("Javelin"), 4),
+                    (String::from("Greataxe"), 1),
+                ]),
+            ]
+        );
+        assert_eq!(character.inventory.get_quantity("Greataxe"), 1);
+        assert_eq!(character.inventory.get_quantity("Javelin"), 4);
+        assert_eq!(character.inventory.get_quantity("Insignia of rank"), 1);
+        assert_eq!(
+            character.inventory.wealth.get_coins(CoinDenomination::Gold),
+            10
+        );
+        assert_eq!(character.traits[0].name, "Military Rank");
+    }
+
+    #[test]
+    fn racial_trait_modifiers_apply_to_the_built_character() {
+        let mut builder = CharacterBuilder::new("Merric", 30, Alignment::LawfulGood);
+        builder.choose_race(find_race(RaceType::Halfling)).unwrap();
+        builder.choose_class(barbarian()).unwrap();
+        let mut roller = ScriptedRoller::new(vec![3; 24]);
+        builder.roll_ability_scores(ABILITIES, &mut roller).unwrap();
+        assert_eq!(
+            builder.choose_background(
+                find_background(BackgroundType::Sage),
+                vec![Language::Halfling, Language::Elvish],
+                personality(),
+            ),
+            Err(CharacterBuilderError::KnownLanguage(Language::Halfling))
+        );
+        builder
+            .choose_background(
+                find_background(BackgroundType::Sage),
+                vec![Language::Elvish, Language::Draconic],
+                personality(),
+            )
+            .unwrap();
+        assert_eq!(builder.next_step(), Some(CreationStep::Equipment));
+        builder.choose_equipment(vec![]).unwrap();
+        let character = builder.build().unwrap();
+
+        assert_eq!(
+            character.languages,
+            vec![
+                Language::Common,
+                Language::Halfling,
+                Language::Elvish,
+                Language::Draconic
+            ]
+        );
+        assert_eq!(
+            character.get_skill_proficiencies(),
+            vec![Skill::Arcana, Skill::History]
+        );
+        assert_eq!(character.size, Size::Small);
+        assert_eq!(character.inventory.get_quantity("Bottle of black ink"), 1);
+        assert_eq!(character.traits[0].name, "Researcher");
+        assert_eq!(character.ability_scores[Ability::Dexterity].score, 11);
+        assert!(!character.get_roll_rules().is_empty());
+    }
+
+    #[test]
+    fn steps_must_be_completed_in_order() {
+        let mut builder = CharacterBuilder::new("Tishros", 80, Alignment::ChaoticNeutral);
+        assert_eq!(
+            builder.choose_class(barbarian()),
+            Err(CharacterBuilderError::OutOfOrder {
+                step: CreationStep::Class,
+                expected: Some(CreationStep::Race),
+            })
+        );
+
+        builder.choose_race(find_race(RaceType::Dwarf)).unwrap();
+        assert_eq!(
+            builder.choose_race(find_race(RaceType::Elf)),
+            Err(CharacterBuilderError::OutOfOrder {
+                step: CreationStep::Race,
+                expected: Some(CreationStep::Class),
+            })
+        );
+        assert_eq!(builder.next_step(), Some(CreationStep::Class));
+
+        assert_eq!(
+            builder.build().err(),
+            Some(CharacterBuilderError::MissingStep(CreationStep::Class))
+        );
+    }
+
+    #[test]
+    fn point_buy_is_recorded_as_a_choice() {
+        let mut builder = CharacterBuilder::new("Tishros", 80, Alignment::ChaoticNeutral);
+        builder.choose_race(find_race(RaceType::HillDwarf)).unwrap();
+        builder.choose_class(barbarian()).unwrap();
+        builder.assign_point_buy(&assignment()).unwrap();
+        assert_eq!(
+            builder.choices()[2],
+            CreationChoice::AbilityScores {
+                method: AbilityScoreMethod::PointBuy,
+                scores: assignment(),
+            }
+        );
+        assert_eq!(builder.next_step(), Some(CreationStep::Background));
+    }
+
+    #[test]
+    fn equipment_has_to_be_chosen_before_building() {
+        let mut builder = CharacterBuilder::new("Tishros", 80, Alignment::ChaoticNeutral);
+        builder.choose_race(find_race(RaceType::HillDwarf)).unwrap();
+        builder.choose_class(barbarian()).unwrap();
+        builder.assign_standard_array(&assignment()).unwrap();
+        builder
+            .choose_background(
+                find_background(BackgroundType::Soldier),
+                vec![],
+                personality(),
+            )
+            .unwrap();
+        assert_eq!(
+            builder.choose_equipment(vec![InventoryEntry {
+                quantity: 0,
+                ..barbarian_equipment().remove(0)
+            }]),
+            Err(CharacterBuilderError::Inventory(
+                InventoryError::ZeroQuantity
+            ))
+        );
+        assert_eq!(
+            builder.build().err(),
+            Some(CharacterBuilderError::MissingStep(CreationStep::Equipment))
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dice::roll_die;
+use crate::roller::Roller;
+use crate::skills::SkillProficiency;
+use crate::{
+    Character, Class, ClassType, CriticalModifier, CHARACTER_ADVANCEMENT_TABLE, EFFECTIVE_LEVEL_MIN,
+};
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Rages {
+    Limited(u8),
+    Unlimited,
+}
+
+// One row of a class table in the classes data file.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ClassLevel {
+    pub level: u32,
+    pub proficiency_bonus: u16,
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub rages: Option<Rages>,
+    #[serde(default)]
+    pub rage_damage: Option<u8>,
+    #[serde(default)]
+    pub critical_modifiers: Vec<CriticalModifier>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ClassFeature {
+    pub class_type: ClassType,
+    pub level: u32,
+    pub name: String,
+}
+
+// Hit points are recorded without the Constitution modifier, which is added
+// when the hit point maximum is calculated.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct HitPointsGained {
+    pub class_type: ClassType,
+    pub level: u32,
+    pub hit_points: u16,
+    pub rolled: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LevelDownError {
+    FirstLevel,
+}
+
+impl fmt::Display for LevelDownError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelDownError::FirstLevel => {
+                write!(f, "a character can't lose its first class level")
+            }
+        }
+    }
+}
+
+impl Class {
+    pub fn find_level(&self, level: u32) -> Option<&ClassLevel> {
+        self.levels.iter().find(|entry| entry.level == level)
+    }
+
+    // The fixed hit points a character can take instead of rolling the hit die.
+    pub fn get_average_hit_points(&self) -> u16 {
+        self.features.hit_points_from_level.max / 2 + 1
+    }
+
+    // Every level from 1 to 20 has to be listed in order, with the proficiency
+    // bonus of the advancement table.
+    pub fn find_level_table_problem(&self) -> Option<String> {
+        if self.levels.len() != CHARACTER_ADVANCEMENT_TABLE.len() {
+            return Some(format!(
+                "{:?} lists {} levels instead of {}",
+                self.class_type,
+                self.levels.len(),
+                CHARACTER_ADVANCEMENT_TABLE.len()
+            ));
+        }
+
+        for (entry, advancement) in self.levels.iter().zip(CHARACTER_ADVANCEMENT_TABLE.iter()) {
+            if entry.level != advancement.level {
+                return Some(format!(
+                    "{:?} lists level {} where level {} was expected",
+                    self.class_type, entry.level, advancement.level
+                ));
+            }
+            if entry.proficiency_bonus != advancement.proficiency_bonus {
+                return Some(format!(
+                    "{:?} level {} has a proficiency bonus of {} instead of {}",
+                    self.class_type,
+                    entry.level,
+                    entry.proficiency_bonus,
+                    advancement.proficiency_bonus
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+impl Character {
+    pub fn get_class_level_entry(&self, class_type: ClassType) -> Option<&ClassLevel> {
+        let class = self
+            .class
+            .iter()
+            .find(|class| class.class_type == class_type)?;
+        class.find_level(class.level)
+    }
+
+    pub fn has_class_feature(&self, name: &str) -> bool {
+        self.class_features
+            .iter()
+            .any(|class_feature| class_feature.name == name)
+    }
+
+    pub fn get_rages(&self) -> Option<Rages> {
+        self.get_class_level_entry(ClassType::Barbarian)?.rages
+    }
+
+    pub fn get_rage_damage(&self) -> Option<u8> {
+        self.get_class_level_entry(ClassType::Barbarian)?
+            .rage_damage
+    }
+
+    // Attaches the features of the level the class has just reached.
+    pub fn add_class_features_for_level(&mut self, class_type: ClassType) {
+        let class_features: Vec<ClassFeature> = match self.get_class_level_entry(class_type) {
+            Some(entry) => entry
+                .features
+                .iter()
+                .map(|name| ClassFeature {
+                    class_type,
+                    level: entry.level,
+                    name: name.clone(),
+                })
+                .collect(),
+            None => return,
+        };
+        self.class_features.extend(class_features);
+    }
+
+    // The first level of the first class takes the full starting hit points.
+    pub fn start_first_class_level(&mut self) {
+        let class = match self.class.first() {
+            Some(class) => class,
+            None => return,
+        };
+        let class_type = class.class_type;
+        self.hit_points_gained.push(HitPointsGained {
+            class_type,
+            level: EFFECTIVE_LEVEL_MIN,
+            hit_points: class.features.hit_points_starting,
+            rolled: false,
+        });
+        self.add_class_features_for_level(class_type);
+    }
+
+    // Every later class level rolls the hit die, or takes its average when the
+    // character doesn't roll for hit points.
+    pub fn advance_class_level(&mut self, class_type: ClassType, roller: &mut dyn Roller) {
+        let class = match self
+            .class
+            .iter()
+            .find(|class| class.class_type == class_type)
+        {
+            Some(class) => class,
+            None => return,
+        };
+        let hit_points = if self.roll_hit_points {
+            roll_die(class.features.hit_points_from_level, roller)
+        } else {
+            class.get_average_hit_points()
+        };
+        self.hit_points_gained.push(HitPointsGained {
+            class_type,
+            level: class.level,
+            hit_points,
+            rolled: self.roll_hit_points,
+        });
+        self.add_class_features_for_level(class_type);
+    }
+
+    // Undoes the most recent class level, using the recorded hit points as the
+    // level history: the features, hit points, Ability Score Improvements and
+    // skill choices of that level are taken away. Spell slots follow from the class levels, so
+    // they drop on their own. Returns the hit points of the removed level.
+    pub fn remove_last_class_level(&mut self) -> Result<HitPointsGained, LevelDownError> {
+        if self.hit_points_gained.len() <= 1 {
+            return Err(LevelDownError::FirstLevel);
+        }
+        let was_conscious = self.get_current_hit_points() > 0;
+        let lost = self
+            .hit_points_gained
+            .pop()
+            .expect("A character with more than one level has a last level");
+        let granted_at_lost_level = |class_type: ClassType, level: u32| {
+            class_type == lost.class_type && level == lost.level
+        };
+
+        self.class_features.retain(|class_feature| {
+            !granted_at_lost_level(class_feature.class_type, class_feature.level)
+        });
+
+        let (lost_improvements, kept_improvements) = self
+            .ability_score_improvements
+            .drain(..)
+            .partition(|improvement| {
+                granted_at_lost_level(improvement.class_type, improvement.level)
+            });
+        self.ability_score_improvements = kept_improvements;
+        for improvement in lost_improvements.iter() {
+            self.revert_ability_score_improvement(improvement);
+        }
+
+        let (lost_skills, kept_skills) = self.skill_choices.drain(..).partition(|skill_choice| {
+            granted_at_lost_level(skill_choice.class_type, skill_choice.level)
+        });
+        self.skill_choices = kept_skills;
+        for skill_choice in lost_skills.iter() {
+            self.set_skill_proficiency(skill_choice.skill, SkillProficiency::None);
+        }
+
+        if let Some(class) = self
+            .class
+            .iter_mut()
+            .find(|class| class.class_type == lost.class_type)
+        {
+            class.level = class.level.saturating_sub(1);
+        }
+        self.class.retain(|class| class.level > 0);
+
+        let class_level = self.get_class_level(lost.class_type);
+        for hit_dice_spent in self.hit_points.hit_dice_spent.iter_mut() {
+            if hit_dice_spent.class_type == lost.class_type {
+                hit_dice_spent.spent = hit_dice_spent.spent.min(class_level);
+            }
+        }
+        self.hit_points
+            .hit_dice_spent
+            .retain(|hit_dice_spent| hit_dice_spent.spent > 0);
+
+        self.refresh_derived_statistics();
+        self.apply_hit_point_maximum(was_conscious);
+        Ok(lost)
+    }
+
+    // Level drain: the character loses its most recent class level and drops to
+    // the fewest experience points of the level it's left with.
+    pub fn lose_level(&mut self) -> Result<HitPointsGained, LevelDownError> {
+        let lost = self.remove_last_class_level()?;
+        let class_levels = self.get_total_class_levels();
+        if let Some(entry) = CHARACTER_ADVANCEMENT_TABLE
+            .iter()
+            .find(|entry| entry.level == class_levels)
+        {
+            self.experience_points = entry.required_experience_points;
+        }
+        self.refresh_derived_statistics();
+        Ok(lost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgrounds::BackgroundType;
+    use crate::feats::AbilityScoreImprovementChoice;
+    use crate::hit_points::HitDiceSpent;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::build_character;
+    use crate::{load_classes_from_file, Ability, RaceType, Skill, ABILITIES};
+
+    fn build_half_orc(class_type: ClassType) -> Character {
+        build_character(
+            RaceType::HalfOrc,
+            class_type,
+            &[
+                (Ability::Strength, 15),
+                (Ability::Dexterity, 13),
+                (Ability::Constitution, 14),
+                (Ability::Intelligence, 8),
+                (Ability::Wisdom, 12),
+                (Ability::Charisma, 10),
+            ],
+            BackgroundType::Criminal,
+        )
+    }
+
+    #[test]
+    fn leveling_adds_features_and_records_hit_points() {
+        let mut character = build_half_orc(ClassType::Barbarian);
+        assert!(character.has_class_feature("Rage"));
+        assert_eq!(character.get_rages(), Some(Rages::Limited(2)));
+
+        character.experience_points = 2700;
+        character.roll_hit_points = true;
+        let mut roller = ScriptedRoller::new(vec![9, 3, 12]);
+        character.gain_level(ClassType::Barbarian, &mut roller);
+        assert_eq!(roller.remaining(), 0);
+
+        assert_eq!(character.get_class_level(ClassType::Barbarian), 4);
+        assert!(character.has_class_feature("Reckless Attack"));
+        assert!(character.has_class_feature("Ability Score Improvement"));
+        assert_eq!(character.get_rages(), Some(Rages::Limited(3)));
+        assert_eq!(character.get_rage_damage(), Some(2));
+        let hit_points: Vec<u16> = character
+            .hit_points_gained
+            .iter()
+            .map(|gained| gained.hit_points)
+            .collect();
+        assert_eq!(hit_points, vec![12, 9, 3, 12]);
+    }
+
+    #[test]
+    fn pending_levels_go_to_the_chosen_class() {
+        let mut character = build_half_orc(ClassType::Barbarian);
+        character.experience_points = 300;
+        let rogue = load_classes_from_file("data/classes.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|class| class.class_type == ClassType::Rogue)
+            .unwrap();
+        character
+            .multiclass_into(
+                rogue,
+                vec![Skill::Acrobatics],
+                &mut ScriptedRoller::new(vec![]),
+            )
+            .unwrap();
+
+        character.experience_points = 2700;
+        character.gain_level(ClassType::Rogue, &mut ScriptedRoller::new(vec![]));
+        assert_eq!(character.get_class_level(ClassType::Barbarian), 1);
+        assert_eq!(character.get_class_level(ClassType::Rogue), 3);
+        assert!(character.has_class_feature("Cunning Action"));
+    }
+
+    #[test]
+    fn losing_levels_removes_what_they_granted() {
+        let mut character = build_half_orc(ClassType::Fighter);
+        character.experience_points = 6500;
+        character.gain_level(ClassType::Fighter, &mut ScriptedRoller::new(vec![]));
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::IncreaseTwo(
+                Ability::Strength,
+                Ability::Constitution,
+            ))
+            .unwrap();
+        character.gain_level(ClassType::Fighter, &mut ScriptedRoller::new(vec![]));
+        character.hit_points.hit_dice_spent = vec![HitDiceSpent {
+            class_type: ClassType::Fighter,
+            spent: 5,
+        }];
+        assert_eq!(character.get_class_level(ClassType::Fighter), 5);
+        assert!(character.has_class_feature("Extra Attack"));
+        assert_eq!(character.ability_scores[Ability::Strength].score, 18);
+
+        character.experience_points = 900;
+        character.gain_level(ClassType::Fighter, &mut ScriptedRoller::new(vec![]));
+        assert_eq!(character.get_class_level(ClassType::Fighter), 3);
+        assert_eq!(character.hit_points_gained.len(), 3);
+        assert!(!character.has_class_feature("Extra Attack"));
+        assert!(!character.has_class_feature("Ability Score Improvement"));
+        assert!(character.ability_score_improvements.is_empty());
+        assert_eq!(character.ability_scores[Ability::Strength].score, 17);
+        assert_eq!(character.ability_scores[Ability::Constitution].score, 15);
+        assert_eq!(character.get_hit_dice_remaining(ClassType::Fighter), 0);
+        assert!(character.find_stale_statistics().is_empty());
+
+        let lost = character.lose_level().unwrap();
+        assert_eq!(lost.level, 3);
+        assert_eq!(character.experience_points, 300);
+        assert_eq!(character.level, 2);
+        assert_eq!(character.get_max_hit_points(), 10 + 6 + 2 * 2);
+
+        character.lose_level().unwrap();
+        assert_eq!(character.lose_level(), Err(LevelDownError::FirstLevel));
+        assert_eq!(character.get_class_level(ClassType::Fighter), 1);
+    }
+
+    #[test]
+    fn losing_a_multiclass_level_removes_its_skill() {
+        let mut character = build_half_orc(ClassType::Barbarian);
+        character.experience_points = 300;
+        let rogue = load_classes_from_file("data/classes.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|class| class.class_type == ClassType::Rogue)
+            .unwrap();
+        character
+            .multiclass_into(
+                rogue,
+                vec![Skill::Acrobatics],
+                &mut ScriptedRoller::new(vec![]),
+            )
+            .unwrap();
+        assert_eq!(
+            character.get_skill_proficiency(Skill::Acrobatics, None),
+            SkillProficiency::Proficient
+        );
+
+        character.lose_level().unwrap();
+        assert_eq!(character.get_class_level(ClassType::Rogue), 0);
+        assert_eq!(
+            character.get_skill_proficiency(Skill::Acrobatics, None),
+            SkillProficiency::None
+        );
+        assert!(character.skill_choices.is_empty());
+    }
+
+    #[test]
+    fn every_class_can_level_to_twenty() {
+        let classes = load_classes_from_file("data/classes.yaml").unwrap();
+        for class_type in classes.iter().map(|class| class.class_type) {
+            let mut character = build_half_orc(class_type);
+            character.experience_points = 355000;
+            character.gain_level(class_type, &mut ScriptedRoller::new(vec![]));
+            while !character
+                .get_pending_ability_score_improvements()
+                .is_empty()
+            {
+                let lowest = ABILITIES
+                    .iter()
+                    .copied()
+                    .min_by_key(|&ability| character.ability_scores[ability].score)
+                    .unwrap();
+                character
+                    .resolve_ability_score_improvement(AbilityScoreImprovementChoice::IncreaseOne(
+                        lowest,
+                    ))
+                    .unwrap();
+                character.gain_level(class_type, &mut ScriptedRoller::new(vec![]));
+            }
+
+            assert_eq!(character.get_class_level(class_type), 20);
+            assert_eq!(character.hit_points_gained.len(), 20);
+            assert_eq!(
+                character.get_class_level_entry(class_type).unwrap().level,
+                20
+            );
+            assert!(character.has_class_feature("Ability Score Improvement"));
+        }
+    }
+
+    #[test]
+    fn every_class_has_a_complete_level_table() {
+        let classes = load_classes_from_file("data/classes.yaml").unwrap();
+        assert_eq!(classes.len(), 12);
+
+        let barbarian = &classes[0];
+        assert_eq!(barbarian.class_type, ClassType::Barbarian);
+        assert_eq!(barbarian.get_average_hit_points(), 7);
+        let level_nine = barbarian.find_level(9).unwrap();
+        assert_eq!(level_nine.features, vec!["Brutal Critical (1 die)"]);
+        assert_eq!(level_nine.rages, Some(Rages::Limited(4)));
+        assert_eq!(level_nine.rage_damage, Some(3));
+        assert_eq!(
+            barbarian.find_level(20).unwrap().rages,
+            Some(Rages::Unlimited)
+        );
+
+        let wizard = classes.last().unwrap();
+        assert_eq!(wizard.class_type, ClassType::Wizard);
+        assert_eq!(
+            wizard.find_level(18).unwrap().features,
+            vec!["Spell Mastery"]
+        );
+        assert_eq!(wizard.find_level(1).unwrap().rages, None);
+    }
+
+    #[test]
+    fn reject_level_tables_with_a_wrong_proficiency_bonus() {
+        let mut classes = load_classes_from_file("data/classes.yaml").unwrap();
+        let fighter = &mut classes[4];
+        fighter.levels[4].proficiency_bonus = 2;
+        assert_eq!(
+            fighter.find_level_table_problem(),
+            Some(String::from(
+                "Fighter level 5 has a proficiency bonus of 2 instead of 3"
+            ))
+        );
+
+        fighter.levels.remove(4);
+        assert_eq!(
+            fighter.find_level_table_problem(),
+            Some(String::from("Fighter lists 19 levels instead of 20"))
+        );
+    }
+}
@@ -0,0 +1,489 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::d20::{D20Test, RollMode};
+use crate::{Ability, Character};
+
+pub const EXHAUSTION_LEVEL_MAX: u8 = 6;
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Condition {
+    Blinded,
+    Charmed,
+    Deafened,
+    Frightened,
+    Grappled,
+    Incapacitated,
+    Invisible,
+    Paralyzed,
+    Petrified,
+    Poisoned,
+    Prone,
+    Restrained,
+    Stunned,
+    Unconscious,
+    Stable,
+    Exhaustion(u8),
+}
+
+impl Condition {
+    // Paralyzed, petrified, stunned and unconscious creatures are also incapacitated.
+    pub fn includes(&self, condition: Condition) -> bool {
+        *self == condition
+            || (condition == Condition::Incapacitated
+                && matches!(
+                    self,
+                    Condition::Paralyzed
+                        | Condition::Petrified
+                        | Condition::Stunned
+                        | Condition::Unconscious
+                ))
+    }
+
+    pub fn roll_mode_for(&self, d20_test: D20Test) -> Option<RollMode> {
+        match (self, d20_test) {
+            (Condition::Blinded, D20Test::AttackRoll) => Some(RollMode::Disadvantage),
+            (Condition::Frightened, D20Test::AttackRoll | D20Test::AbilityCheck(_)) => {
+                Some(RollMode::Disadvantage)
+            }
+            (Condition::Invisible, D20Test::AttackRoll) => Some(RollMode::Advantage),
+            (Condition::Poisoned, D20Test::AttackRoll | D20Test::AbilityCheck(_)) => {
+                Some(RollMode::Disadvantage)
+            }
+            (Condition::Prone, D20Test::AttackRoll) => Some(RollMode::Disadvantage),
+            (Condition::Restrained, D20Test::AttackRoll) => Some(RollMode::Disadvantage),
+            (Condition::Restrained, D20Test::SavingThrow(Ability::Dexterity)) => {
+                Some(RollMode::Disadvantage)
+            }
+            (Condition::Exhaustion(level), D20Test::AbilityCheck(_)) if *level >= 1 => {
+                Some(RollMode::Disadvantage)
+            }
+            (Condition::Exhaustion(level), D20Test::AttackRoll | D20Test::SavingThrow(_))
+                if *level >= 3 =>
+            {
+                Some(RollMode::Disadvantage)
+            }
+            _ => None,
+        }
+    }
+
+    // The roll mode of attacks made against a creature with this condition.
+    pub fn roll_mode_against(&self, within_five_feet: bool) -> Option<RollMode> {
+        match self {
+            Condition::Blinded
+            | Condition::Paralyzed
+            | Condition::Petrified
+            | Condition::Restrained
+            | Condition::Stunned
+            | Condition::Unconscious => Some(RollMode::Advantage),
+            Condition::Invisible => Some(RollMode::Disadvantage),
+            Condition::Prone if within_five_feet => Some(RollMode::Advantage),
+            Condition::Prone => Some(RollMode::Disadvantage),
+            _ => None,
+        }
+    }
+
+    pub fn fails_automatically(&self, d20_test: D20Test) -> bool {
+        matches!(
+            (self, d20_test),
+            (
+                Condition::Paralyzed
+                    | Condition::Petrified
+                    | Condition::Stunned
+                    | Condition::Unconscious,
+                D20Test::SavingThrow(Ability::Strength | Ability::Dexterity),
+            )
+        )
+    }
+
+    pub fn sets_speed_to_zero(&self) -> bool {
+        match self {
+            Condition::Grappled
+            | Condition::Paralyzed
+            | Condition::Petrified
+            | Condition::Restrained
+            | Condition::Stunned
+            | Condition::Unconscious => true,
+            Condition::Exhaustion(level) => *level >= 5,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConditionError {
+    ExhaustionLevel(u8),
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConditionError::ExhaustionLevel(level) => write!(
+                f,
+                "exhaustion level {} is outside 1 to {}",
+                level, EXHAUSTION_LEVEL_MAX
+            ),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ConditionDuration {
+    UntilRemoved,
+    Rounds(u16),
+}
+
+// A condition along with the effect that caused it, such as a spell or a
+// creature's name, so that it can be removed when that effect ends.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ActiveCondition {
+    pub condition: Condition,
+    pub source: String,
+    pub duration: ConditionDuration,
+}
+
+impl Character {
+    pub fn has_condition(&self, condition: Condition) -> bool {
+        self.conditions
+            .iter()
+            .any(|active_condition| active_condition.condition.includes(condition))
+    }
+
+    // Applying a condition the source already caused refreshes its duration.
+    // Exhaustion only has levels 1 to 6.
+    pub fn add_condition(
+        &mut self,
+        condition: Condition,
+        source: &str,
+        duration: ConditionDuration,
+    ) -> Result<(), ConditionError> {
+        if let Condition::Exhaustion(level) = condition {
+            if !(1..=EXHAUSTION_LEVEL_MAX).contains(&level) {
+                return Err(ConditionError::ExhaustionLevel(level));
+            }
+        }
+        self.insert_condition(condition, source, duration);
+        Ok(())
+    }
+
+    // Falling unconscious also knocks a creature prone.
+    pub(crate) fn insert_condition(
+        &mut self,
+        condition: Condition,
+        source: &str,
+        duration: ConditionDuration,
+    ) {
+        self.conditions.retain(|active_condition| {
+            active_condition.condition != condition || active_condition.source != source
+        });
+        self.conditions.push(ActiveCondition {
+            condition,
+            source: String::from(source),
+            duration,
+        });
+
+        if condition == Condition::Unconscious && !self.has_condition(Condition::Prone) {
+            self.insert_condition(Condition::Prone, source, ConditionDuration::UntilRemoved);
+        }
+    }
+
+    pub fn remove_condition(&mut self, condition: Condition) {
+        self.conditions
+            .retain(|active_condition| active_condition.condition != condition);
+    }
+
+    pub fn remove_conditions_from_source(&mut self, source: &str) {
+        self.conditions
+            .retain(|active_condition| active_condition.source != source);
+    }
+
+    // Counts down conditions that last a number of rounds, and removes the ones
+    // that have run out.
+    pub fn advance_round(&mut self) -> Vec<ActiveCondition> {
+        for active_condition in self.conditions.iter_mut() {
+            if let ConditionDuration::Rounds(rounds) = &mut active_condition.duration {
+                *rounds = rounds.saturating_sub(1);
+            }
+        }
+
+        let (expired, remaining) = self.conditions.drain(..).partition(|active_condition| {
+            active_condition.duration == ConditionDuration::Rounds(0)
+        });
+        self.conditions = remaining;
+        expired
+    }
+
+    pub fn get_exhaustion_level(&self) -> u8 {
+        self.conditions
+            .iter()
+            .filter_map(|active_condition| match active_condition.condition {
+                Condition::Exhaustion(level) => Some(level),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Exhaustion levels stack up to the sixth, which is death. A negative
+    // change, such as from a long rest, removes levels.
+    pub fn change_exhaustion_level(&mut self, change: i8, source: &str) -> u8 {
+        let was_conscious = self.get_current_hit_points() > 0;
+        let level = (self.get_exhaustion_level() as i16 + change as i16)
+            .clamp(0, EXHAUSTION_LEVEL_MAX as i16) as u8;
+        self.conditions.retain(|active_condition| {
+            !matches!(active_condition.condition, Condition::Exhaustion(_))
+        });
+        if level > 0 {
+            self.conditions.push(ActiveCondition {
+                condition: Condition::Exhaustion(level),
+                source: String::from(source),
+                duration: ConditionDuration::UntilRemoved,
+            });
+        }
+        if level == EXHAUSTION_LEVEL_MAX && !self.death_saving_throws.dead {
+            self.die();
+        }
+        self.apply_hit_point_maximum(was_conscious);
+        level
+    }
+
+    pub fn get_roll_mode_for_attacks_against(&self, within_five_feet: bool) -> RollMode {
+        RollMode::from_sources(self.conditions.iter().filter_map(|active_condition| {
+            active_condition
+                .condition
+                .roll_mode_against(within_five_feet)
+        }))
+    }
+
+    // Any attack that hits a paralyzed or unconscious creature from within 5
+    // feet is a critical hit.
+    pub fn attacks_against_are_critical(&self, within_five_feet: bool) -> bool {
+        within_five_feet
+            && (self.has_condition(Condition::Paralyzed)
+                || self.has_condition(Condition::Unconscious))
+    }
+
+    pub fn fails_automatically(&self, d20_test: D20Test) -> bool {
+        self.conditions
+            .iter()
+            .any(|active_condition| active_condition.condition.fails_automatically(d20_test))
+    }
+
+    // The walking speed after conditions, with exhaustion of the second level
+    // and above halving it.
+    pub fn get_current_speed(&self) -> i64 {
+        if self
+            .conditions
+            .iter()
+            .any(|active_condition| active_condition.condition.sets_speed_to_zero())
+        {
+            return 0;
+        }
+
+        match self.get_exhaustion_level() {
+            level if level >= 2 => self.get_speed() / 2,
+            _ => self.get_speed(),
+        }
+    }
+
+    pub fn can_take_actions(&self) -> bool {
+        !self.has_condition(Condition::Incapacitated)
+    }
+
+    pub fn can_take_reactions(&self) -> bool {
+        !self.has_condition(Condition::Incapacitated)
+    }
+
+    // A charmed creature can't attack the one who charmed it.
+    pub fn can_attack(&self, target: &str) -> bool {
+        self.can_take_actions()
+            && !self.conditions.iter().any(|active_condition| {
+                active_condition.condition == Condition::Charmed
+                    && active_condition.source == target
+            })
+    }
+
+    // A frightened creature can't willingly move closer to the source of its fear.
+    pub fn can_move_closer_to(&self, source: &str) -> bool {
+        self.get_current_speed() > 0
+            && !self.conditions.iter().any(|active_condition| {
+                active_condition.condition == Condition::Frightened
+                    && active_condition.source == source
+            })
+    }
+
+    // Blinded creatures fail checks that require sight, and deafened ones those
+    // that require hearing.
+    pub fn can_see(&self) -> bool {
+        !self.has_condition(Condition::Blinded) && !self.has_condition(Condition::Unconscious)
+    }
+
+    pub fn can_hear(&self) -> bool {
+        !self.has_condition(Condition::Deafened) && !self.has_condition(Condition::Unconscious)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::create_sample_character;
+    use crate::EntityRoll;
+
+    #[test]
+    fn conditions_change_rolls_and_attacks_against() {
+        let mut character = create_sample_character();
+        character
+            .add_condition(
+                Condition::Poisoned,
+                "Giant Spider",
+                ConditionDuration::Rounds(2),
+            )
+            .unwrap();
+        character
+            .add_condition(Condition::Prone, "Shove", ConditionDuration::UntilRemoved)
+            .unwrap();
+
+        assert_eq!(
+            character.get_roll_mode(D20Test::AttackRoll, RollMode::Advantage),
+            RollMode::Normal
+        );
+        assert_eq!(
+            character.get_roll_mode(D20Test::AbilityCheck(Ability::Wisdom), RollMode::Normal),
+            RollMode::Disadvantage
+        );
+        assert_eq!(
+            character.get_roll_mode_for_attacks_against(true),
+            RollMode::Advantage
+        );
+        assert_eq!(
+            character.get_roll_mode_for_attacks_against(false),
+            RollMode::Disadvantage
+        );
+
+        character
+            .add_condition(
+                Condition::Stunned,
+                "Stunning Strike",
+                ConditionDuration::Rounds(1),
+            )
+            .unwrap();
+        let saving_throw = character.roll_saving_throw(
+            Ability::Dexterity,
+            RollMode::Normal,
+            &mut ScriptedRoller::new(vec![20]),
+        );
+        assert!(saving_throw.automatic_failure);
+        assert!(!saving_throw.succeeds(10));
+        assert!(!character.can_take_actions());
+        assert_eq!(character.get_current_speed(), 0);
+
+        let expired: Vec<Condition> = character
+            .advance_round()
+            .iter()
+            .map(|active_condition| active_condition.condition)
+            .collect();
+        assert_eq!(expired, vec![Condition::Stunned]);
+        assert!(character.can_take_actions());
+        assert_eq!(character.get_current_speed(), character.get_speed());
+
+        character.advance_round();
+        assert!(!character.has_condition(Condition::Poisoned));
+        assert!(character.has_condition(Condition::Prone));
+    }
+
+    #[test]
+    fn sources_limit_and_end_conditions() {
+        let mut character = create_sample_character();
+        character
+            .add_condition(
+                Condition::Charmed,
+                "Vampire",
+                ConditionDuration::UntilRemoved,
+            )
+            .unwrap();
+        character
+            .add_condition(
+                Condition::Frightened,
+                "Dragon",
+                ConditionDuration::UntilRemoved,
+            )
+            .unwrap();
+
+        assert!(!character.can_attack("Vampire"));
+        assert!(character.can_attack("Dragon"));
+        assert!(!character.can_move_closer_to("Dragon"));
+
+        character.remove_conditions_from_source("Vampire");
+        assert!(character.can_attack("Vampire"));
+        assert!(character.can_take_reactions());
+        assert!(character.can_see() && character.can_hear());
+
+        character
+            .add_condition(
+                Condition::Unconscious,
+                "Sleep",
+                ConditionDuration::Rounds(10),
+            )
+            .unwrap();
+        assert!(character.has_condition(Condition::Incapacitated));
+        assert!(character.has_condition(Condition::Prone));
+        assert!(!character.can_take_reactions());
+        assert!(!character.can_see() && !character.can_hear());
+        assert!(character.attacks_against_are_critical(true));
+        assert!(!character.attacks_against_are_critical(false));
+    }
+
+    #[test]
+    fn exhaustion_levels_stack_until_death() {
+        let mut character = create_sample_character();
+        assert_eq!(character.change_exhaustion_level(2, "Forced March"), 2);
+        assert_eq!(
+            character.get_roll_mode(D20Test::AbilityCheck(Ability::Strength), RollMode::Normal),
+            RollMode::Disadvantage
+        );
+        assert_eq!(
+            character.get_roll_mode(D20Test::AttackRoll, RollMode::Normal),
+            RollMode::Normal
+        );
+        assert_eq!(character.get_current_speed(), character.get_speed() / 2);
+
+        assert_eq!(character.change_exhaustion_level(2, "Starvation"), 4);
+        assert_eq!(
+            character.get_roll_mode(D20Test::SavingThrow(Ability::Wisdom), RollMode::Normal),
+            RollMode::Disadvantage
+        );
+        assert_eq!(character.change_exhaustion_level(-1, "Long Rest"), 3);
+        assert_eq!(character.change_exhaustion_level(i8::MAX, "Starvation"), 6);
+        assert!(character.death_saving_throws.dead);
+        assert!(character.has_condition(Condition::Unconscious));
+        assert!(character.has_condition(Condition::Prone));
+        assert_eq!(character.change_exhaustion_level(i8::MIN, "Long Rest"), 0);
+    }
+
+    #[test]
+    fn exhaustion_levels_outside_the_table_are_rejected() {
+        let mut character = create_sample_character();
+        for level in [0, EXHAUSTION_LEVEL_MAX + 1, 200] {
+            assert_eq!(
+                character.add_condition(
+                    Condition::Exhaustion(level),
+                    "Curse",
+                    ConditionDuration::UntilRemoved
+                ),
+                Err(ConditionError::ExhaustionLevel(level))
+            );
+        }
+        assert_eq!(character.get_exhaustion_level(), 0);
+
+        character
+            .add_condition(
+                Condition::Exhaustion(EXHAUSTION_LEVEL_MAX),
+                "Curse",
+                ConditionDuration::UntilRemoved,
+            )
+            .unwrap();
+        assert_eq!(character.change_exhaustion_level(-1, "Long Rest"), 5);
+    }
+}
@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dice::D20;
+use crate::roll_rules::{DieAdjustment, RollRules, RollTarget};
+use crate::roller::Roller;
+use crate::Ability;
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub enum RollMode {
+    #[default]
+    Normal,
+    Advantage,
+    Disadvantage,
+}
+
+impl RollMode {
+    // If circumstances cause a roll to have both advantage and disadvantage, you are
+    // considered to have neither of them, and you roll one d20. This is true even if
+    // multiple circumstances impose disadvantage and only one grants advantage or vice versa.
+    pub fn from_sources<I: IntoIterator<Item = RollMode>>(sources: I) -> RollMode {
+        let mut has_advantage = false;
+        let mut has_disadvantage = false;
+        for source in sources {
+            match source {
+                RollMode::Advantage => has_advantage = true,
+                RollMode::Disadvantage => has_disadvantage = true,
+                RollMode::Normal => {}
+            }
+        }
+
+        match (has_advantage, has_disadvantage) {
+            (true, false) => RollMode::Advantage,
+            (false, true) => RollMode::Disadvantage,
+            _ => RollMode::Normal,
+        }
+    }
+}
+
+// The kinds of d20 rolls that advantage and disadvantage can apply to.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum D20Test {
+    AbilityCheck(Ability),
+    SavingThrow(Ability),
+    AttackRoll,
+    DeathSavingThrow,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct D20Roll {
+    pub mode: RollMode,
+    pub rolls: Vec<u16>,
+    pub kept: usize,
+    pub modifier: i16,
+    pub total: i16,
+    // Roll rules that changed one of the d20s, keyed by the index into `rolls`.
+    pub adjustments: Vec<(usize, DieAdjustment)>,
+    // Conditions such as being stunned make some saving throws fail whatever the roll.
+    pub automatic_failure: bool,
+}
+
+impl D20Roll {
+    pub fn natural(&self) -> u16 {
+        self.rolls[self.kept]
+    }
+
+    pub fn is_natural_twenty(&self) -> bool {
+        self.natural() == D20.max
+    }
+
+    pub fn is_natural_one(&self) -> bool {
+        self.natural() == D20.min
+    }
+
+    pub fn succeeds(&self, difficulty_class: i16) -> bool {
+        !self.automatic_failure && self.total >= difficulty_class
+    }
+}
+
+pub const DEFAULT_CRITICAL_THRESHOLD: u16 = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttackRoll {
+    pub d20: D20Roll,
+    pub critical: bool,
+    pub fumble: bool,
+}
+
+impl AttackRoll {
+    // If the d20 roll for an attack is a 1, the attack misses regardless of any modifiers
+    // or the target's AC. If the d20 roll is a 20 (or within an expanded critical range),
+    // the attack hits regardless of any modifiers or the target's AC.
+    pub fn hits(&self, target_armor_class: i16) -> bool {
+        if self.fumble {
+            return false;
+        }
+        self.critical || self.d20.total >= target_armor_class
+    }
+}
+
+pub fn roll_attack(
+    attack_bonus: i16,
+    roll_mode: RollMode,
+    critical_threshold: u16,
+    roll_rules: &RollRules,
+    roller: &mut dyn Roller,
+) -> AttackRoll {
+    let d20 = roll_d20_with_rules(attack_bonus, roll_mode, roll_rules, roller);
+    AttackRoll {
+        critical: d20.natural() >= critical_threshold,
+        fumble: d20.is_natural_one(),
+        d20,
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum DeathSaveOutcome {
+    Success,
+    Failure,
+    // A natural 20 regains 1 hit point.
+    RegainHitPoint,
+    // A natural 1 counts as two failures.
+    DoubleFailure,
+}
+
+pub const DEATH_SAVING_THROW_DIFFICULTY_CLASS: i16 = 10;
+
+pub fn get_death_saving_throw_outcome(d20: &D20Roll) -> DeathSaveOutcome {
+    if d20.is_natural_twenty() {
+        DeathSaveOutcome::RegainHitPoint
+    } else if d20.is_natural_one() {
+        DeathSaveOutcome::DoubleFailure
+    } else if d20.total >= DEATH_SAVING_THROW_DIFFICULTY_CLASS {
+        DeathSaveOutcome::Success
+    } else {
+        DeathSaveOutcome::Failure
+    }
+}
+
+pub fn roll_d20(modifier: i16, mode: RollMode, roller: &mut dyn Roller) -> D20Roll {
+    roll_d20_with_rules(modifier, mode, &RollRules::new(), roller)
+}
+
+pub fn roll_d20_with_rules(
+    modifier: i16,
+    mode: RollMode,
+    roll_rules: &RollRules,
+    roller: &mut dyn Roller,
+) -> D20Roll {
+    let dice_count = match mode {
+        RollMode::Normal => 1,
+        RollMode::Advantage | RollMode::Disadvantage => 2,
+    };
+
+    let mut rolls = vec![];
+    let mut adjustments = vec![];
+    for index in 0..dice_count {
+        let (value, die_adjustments) = roll_rules.roll(D20, RollTarget::D20, roller);
+        rolls.push(value);
+        adjustments.extend(
+            die_adjustments
+                .into_iter()
+                .map(|adjustment| (index, adjustment)),
+        );
+    }
+
+    let kept = match mode {
+        RollMode::Advantage if rolls[1] > rolls[0] => 1,
+        RollMode::Disadvantage if rolls[1] < rolls[0] => 1,
+        _ => 0,
+    };
+
+    D20Roll {
+        mode,
+        total: rolls[kept] as i16 + modifier,
+        rolls,
+        kept,
+        modifier,
+        adjustments,
+        automatic_failure: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roller::ScriptedRoller;
+
+    #[test]
+    fn advantage_and_disadvantage_cancel_out() {
+        assert_eq!(RollMode::from_sources(vec![]), RollMode::Normal);
+        assert_eq!(
+            RollMode::from_sources(vec![RollMode::Normal, RollMode::Advantage]),
+            RollMode::Advantage
+        );
+        assert_eq!(
+            RollMode::from_sources(vec![
+                RollMode::Disadvantage,
+                RollMode::Disadvantage,
+                RollMode::Advantage
+            ]),
+            RollMode::Normal
+        );
+    }
+
+    #[test]
+    fn roll_d20_keeps_the_right_die() {
+        let mut roller = ScriptedRoller::new(vec![4, 17, 4, 17, 9]);
+
+        let advantage = roll_d20(2, RollMode::Advantage, &mut roller);
+        assert_eq!(advantage.rolls, vec![4, 17]);
+        assert_eq!(advantage.natural(), 17);
+        assert_eq!(advantage.total, 19);
+
+        let disadvantage = roll_d20(-1, RollMode::Disadvantage, &mut roller);
+        assert_eq!(disadvantage.natural(), 4);
+        assert_eq!(disadvantage.kept, 0);
+        assert_eq!(disadvantage.total, 3);
+
+        let normal = roll_d20(0, RollMode::Normal, &mut roller);
+        assert_eq!(normal.rolls, vec![9]);
+        assert_eq!(roller.remaining(), 0);
+    }
+
+    #[test]
+    fn natural_rolls_decide_attacks() {
+        let mut roller = ScriptedRoller::new(vec![20, 1, 19, 19]);
+
+        let critical = roll_attack(
+            -5,
+            RollMode::Normal,
+            DEFAULT_CRITICAL_THRESHOLD,
+            &RollRules::new(),
+            &mut roller,
+        );
+        assert!(critical.critical);
+        assert!(critical.hits(30));
+
+        let fumble = roll_attack(
+            50,
+            RollMode::Normal,
+            DEFAULT_CRITICAL_THRESHOLD,
+            &RollRules::new(),
+            &mut roller,
+        );
+        assert!(fumble.fumble);
+        assert!(!fumble.hits(1));
+
+        let improved_critical =
+            roll_attack(0, RollMode::Normal, 19, &RollRules::new(), &mut roller);
+        assert!(improved_critical.critical);
+        let normal_hit = roll_attack(
+            0,
+            RollMode::Normal,
+            DEFAULT_CRITICAL_THRESHOLD,
+            &RollRules::new(),
+            &mut roller,
+        );
+        assert!(!normal_hit.critical);
+        assert!(!normal_hit.hits(20));
+    }
+
+    #[test]
+    fn death_saving_throw_outcomes() {
+        let mut roller = ScriptedRoller::new(vec![20, 1, 10, 9]);
+        let outcomes: Vec<DeathSaveOutcome> = (0..4)
+            .map(|_| get_death_saving_throw_outcome(&roll_d20(0, RollMode::Normal, &mut roller)))
+            .collect();
+
+        assert_eq!(
+            outcomes,
+            vec![
+                DeathSaveOutcome::RegainHitPoint,
+                DeathSaveOutcome::DoubleFailure,
+                DeathSaveOutcome::Success,
+                DeathSaveOutcome::Failure,
+            ]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt;
+
+use crate::{
+    derive_ability_modifier_from_ability_score, Ability, AbilitySet, Character, Language,
+    SavingThrowScore, SavingThrowSet, Size, Skill, SpeedEffect, ABILITIES,
+};
+
+// Values that are stored on a character for readability, but are derived from
+// its ability scores, experience points, class, race and background.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DerivedStatistic {
+    AbilityModifier(Ability),
+    SavingThrowScore(Ability),
+    SavingThrowModifier(Ability),
+    SkillModifier(Skill),
+    Level,
+    Size,
+    Speed,
+    Languages,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaleStatistic {
+    pub statistic: DerivedStatistic,
+    pub stored: String,
+    pub derived: String,
+}
+
+impl fmt::Display for StaleStatistic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} is stored as {} but should be {}",
+            self.statistic, self.stored, self.derived
+        )
+    }
+}
+
+impl SavingThrowSet {
+    // Saving throws without any proficiency, which only depend on the ability scores.
+    pub fn from_ability_scores(ability_scores: &AbilitySet) -> SavingThrowSet {
+        SavingThrowSet(ABILITIES.map(|ability| SavingThrowScore {
+            saving_throw: ability,
+            score: ability_scores[ability].score,
+            modifier: derive_ability_modifier_from_ability_score(ability_scores[ability].score),
+        }))
+    }
+}
+
+impl Character {
+    pub fn get_ability_modifier(&self, ability: Ability) -> i8 {
+        derive_ability_modifier_from_ability_score(self.ability_scores[ability].score)
+    }
+
+    // Only the first class taken grants saving throw proficiencies.
+    pub fn is_proficient_in_saving_throw(&self, ability: Ability) -> bool {
+        self.class
+            .first()
+            .is_some_and(|class| class.features.saving_throw_proficiencies.contains(&ability))
+    }
+
+    pub fn get_saving_throw_modifier(&self, ability: Ability) -> i8 {
+        let proficiency_bonus = if self.is_proficient_in_saving_throw(ability) {
+            self.get_proficiency_bonus() as i8
+        } else {
+            0
+        };
+        self.get_ability_modifier(ability) + proficiency_bonus
+    }
+
+    pub fn get_size(&self) -> Size {
+        self.race.racial_traits.size
+    }
+
+    pub fn get_speed(&self) -> i64 {
+        let armor_speed_penalty = self
+            .get_worn_armor()
+            .map_or(0, |armor| self.get_armor_speed_penalty(armor));
+        let speed_bonus: i64 = self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.speed_modifiers.iter())
+            .filter_map(|speed_modifier| match speed_modifier.value {
+                SpeedEffect::Bonus(bonus) => Some(bonus),
+                SpeedEffect::IgnoreArmorStrengthRequirement => None,
+            })
+            .sum();
+        (self.race.racial_traits.speed as i64 + speed_bonus
+            - self.get_encumbrance_speed_penalty()
+            - armor_speed_penalty)
+            .max(0)
+    }
+
+    // Initiative is a Dexterity check, plus bonuses from traits like Alert.
+    pub fn get_initiative_modifier(&self) -> i8 {
+        self.get_ability_modifier(Ability::Dexterity)
+            + self
+                .traits
+                .iter()
+                .chain(self.race.racial_traits.traits.iter())
+                .flat_map(|character_trait| character_trait.initiative_modifiers.iter())
+                .map(|initiative_modifier| initiative_modifier.value)
+                .sum::<i8>()
+    }
+
+    pub fn get_languages(&self) -> Vec<Language> {
+        let mut languages = self.race.racial_traits.languages.clone();
+        if let Some(background) = &self.background {
+            languages.extend(background.languages.iter().copied());
+        }
+        languages
+    }
+
+    fn derive_saving_throw(&self, ability: Ability) -> SavingThrowScore {
+        SavingThrowScore {
+            saving_throw: ability,
+            score: self.ability_scores[ability].score,
+            modifier: self.get_saving_throw_modifier(ability),
+        }
+    }
+
+    // Brings every stored statistic back in line with the values it's derived from.
+    pub fn refresh_derived_statistics(&mut self) {
+        for ability_score in self.ability_scores.0.iter_mut() {
+            ability_score.modifier =
+                derive_ability_modifier_from_ability_score(ability_score.score);
+        }
+        self.saving_throws =
+            SavingThrowSet(ABILITIES.map(|ability| self.derive_saving_throw(ability)));
+        self.level = self.get_current_level();
+        self.size = self.get_size();
+        self.speed = self.get_speed();
+        self.languages = self.get_languages();
+        self.refresh_skill_modifiers();
+    }
+
+    pub fn find_stale_statistics(&self) -> Vec<StaleStatistic> {
+        let mut stale_statistics = vec![];
+        let mut compare = |statistic: DerivedStatistic, stored: String, derived: String| {
+            if stored != derived {
+                stale_statistics.push(StaleStatistic {
+                    statistic,
+                    stored,
+                    derived,
+                });
+            }
+        };
+
+        for &ability in ABILITIES.iter() {
+            compare(
+                DerivedStatistic::AbilityModifier(ability),
+                self.ability_scores[ability].modifier.to_string(),
+                self.get_ability_modifier(ability).to_string(),
+            );
+
+            let saving_throw = self.derive_saving_throw(ability);
+            compare(
+                DerivedStatistic::SavingThrowScore(ability),
+                self.saving_throws[ability].score.to_string(),
+                saving_throw.score.to_string(),
+            );
+            compare(
+                DerivedStatistic::SavingThrowModifier(ability),
+                self.saving_throws[ability].modifier.to_string(),
+                saving_throw.modifier.to_string(),
+            );
+        }
+
+        for skill_score in self.skills.0.iter() {
+            compare(
+                DerivedStatistic::SkillModifier(skill_score.skill),
+                skill_score.modifier.to_string(),
+                self.get_skill_modifier(skill_score.skill, None).to_string(),
+            );
+        }
+
+        compare(
+            DerivedStatistic::Level,
+            self.level.to_string(),
+            self.get_current_level().to_string(),
+        );
+        compare(
+            DerivedStatistic::Size,
+            format!("{:?}", self.size),
+            format!("{:?}", self.get_size()),
+        );
+        compare(
+            DerivedStatistic::Speed,
+            self.speed.to_string(),
+            self.get_speed().to_string(),
+        );
+        compare(
+            DerivedStatistic::Languages,
+            format!("{:?}", self.languages),
+            format!("{:?}", self.get_languages()),
+        );
+
+        stale_statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgrounds::BackgroundType;
+    use crate::tests::build_character;
+    use crate::{ClassType, RaceType};
+
+    fn build_barbarian() -> Character {
+        build_character(
+            RaceType::Dwarf,
+            ClassType::Barbarian,
+            &[
+                (Ability::Strength, 15),
+                (Ability::Dexterity, 12),
+                (Ability::Constitution, 14),
+                (Ability::Intelligence, 8),
+                (Ability::Wisdom, 13),
+                (Ability::Charisma, 10),
+            ],
+            BackgroundType::Soldier,
+        )
+    }
+
+    #[test]
+    fn saving_throws_include_class_proficiency() {
+        let character = build_barbarian();
+        assert_eq!(character.get_saving_throw_modifier(Ability::Strength), 4);
+        assert_eq!(character.get_saving_throw_modifier(Ability::Dexterity), 1);
+        assert_eq!(character.saving_throws[Ability::Constitution].modifier, 5);
+        assert!(character.find_stale_statistics().is_empty());
+    }
+
+    #[test]
+    fn stale_statistics_are_reported_and_refreshed() {
+        let mut character = build_barbarian();
+        character.ability_scores.0[0].score = 18;
+        character.experience_points = 900;
+        character.speed = 30;
+
+        let stale_statistics: Vec<DerivedStatistic> = character
+            .find_stale_statistics()
+            .iter()
+            .map(|stale_statistic| stale_statistic.statistic)
+            .collect();
+        assert_eq!(
+            stale_statistics,
+            vec![
+                DerivedStatistic::AbilityModifier(Ability::Strength),
+                DerivedStatistic::SavingThrowScore(Ability::Strength),
+                DerivedStatistic::SavingThrowModifier(Ability::Strength),
+                DerivedStatistic::SkillModifier(Skill::Athletics),
+                DerivedStatistic::Level,
+                DerivedStatistic::Speed,
+            ]
+        );
+        assert_eq!(
+            character.find_stale_statistics()[5].to_string(),
+            "Speed is stored as 30 but should be 25"
+        );
+
+        character.refresh_derived_statistics();
+        assert!(character.find_stale_statistics().is_empty());
+        assert_eq!(character.level, 3);
+        assert_eq!(character.ability_scores[Ability::Strength].modifier, 4);
+        assert_eq!(character.saving_throws[Ability::Strength].modifier, 6);
+    }
+}
@@ -0,0 +1,789 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::roll_rules::{DamageSource, DieAdjustment, RollRules, RollTarget};
+use crate::roller::Roller;
+use crate::DamageType;
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Die {
+    pub min: u16,
+    pub max: u16,
+}
+
+pub const D4: Die = Die { min: 1, max: 4 };
+pub const D6: Die = Die { min: 1, max: 6 };
+pub const D8: Die = Die { min: 1, max: 8 };
+pub const D10: Die = Die { min: 1, max: 10 };
+pub const D12: Die = Die { min: 1, max: 12 };
+pub const D20: Die = Die { min: 1, max: 20 };
+pub const D100: Die = Die { min: 1, max: 100 };
+
+impl Die {
+    pub fn with_sides(sides: u16) -> Die {
+        Die { min: 1, max: sides }
+    }
+
+    pub fn sides(&self) -> u16 {
+        self.max - self.min + 1
+    }
+}
+
+impl fmt::Display for Die {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.min == 1 {
+            write!(f, "d{}", self.max)
+        } else {
+            write!(f, "d[{}..{}]", self.min, self.max)
+        }
+    }
+}
+
+pub fn roll_die(die: Die, roller: &mut dyn Roller) -> u16 {
+    roller.roll(die)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KeepRule {
+    KeepHighest(u16),
+    KeepLowest(u16),
+    DropHighest(u16),
+    DropLowest(u16),
+}
+
+impl KeepRule {
+    // Returns which of the rolled values survive the rule, in roll order.
+    pub fn kept(&self, values: &[u16]) -> Vec<bool> {
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by_key(|&index| values[index]);
+
+        let count = values.len();
+        let (keep_from, keep_to) = match *self {
+            KeepRule::KeepHighest(n) => (count.saturating_sub(n as usize), count),
+            KeepRule::KeepLowest(n) => (0, (n as usize).min(count)),
+            KeepRule::DropHighest(n) => (0, count.saturating_sub(n as usize)),
+            KeepRule::DropLowest(n) => ((n as usize).min(count), count),
+        };
+
+        let mut kept = vec![false; count];
+        for &index in order[keep_from..keep_to].iter() {
+            kept[index] = true;
+        }
+        kept
+    }
+}
+
+impl fmt::Display for KeepRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeepRule::KeepHighest(n) => write!(f, "kh{}", n),
+            KeepRule::KeepLowest(n) => write!(f, "kl{}", n),
+            KeepRule::DropHighest(n) => write!(f, "dh{}", n),
+            KeepRule::DropLowest(n) => write!(f, "dl{}", n),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DiceGroup {
+    pub count: u16,
+    pub die: Die,
+    pub keep: Option<KeepRule>,
+}
+
+impl DiceGroup {
+    pub fn kept_count(&self) -> u16 {
+        match self.keep {
+            None => self.count,
+            Some(KeepRule::KeepHighest(n)) | Some(KeepRule::KeepLowest(n)) => n.min(self.count),
+            Some(KeepRule::DropHighest(n)) | Some(KeepRule::DropLowest(n)) => {
+                self.count.saturating_sub(n)
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TermValue {
+    Dice(DiceGroup),
+    Flat(i32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DiceTerm {
+    pub negative: bool,
+    pub value: TermValue,
+    pub damage_type: Option<DamageType>,
+}
+
+// A parsed dice expression such as "2d6+3", "4d6kh3", "1d8+1d6 fire" or "1d12 (or 7)".
+// The optional fixed value is the average a player may take instead of rolling.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceExpression {
+    pub terms: Vec<DiceTerm>,
+    pub fixed_value: Option<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DieRoll {
+    pub die: Die,
+    pub value: u16,
+    pub kept: bool,
+    pub adjustments: Vec<DieAdjustment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermRoll {
+    pub term: DiceTerm,
+    pub dice: Vec<DieRoll>,
+    pub subtotal: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceRoll {
+    pub total: i32,
+    pub terms: Vec<TermRoll>,
+}
+
+impl DiceRoll {
+    pub fn total_for_damage_type(&self, damage_type: DamageType) -> i32 {
+        self.terms
+            .iter()
+            .filter(|term_roll| term_roll.term.damage_type == Some(damage_type))
+            .map(|term_roll| term_roll.subtotal)
+            .sum()
+    }
+}
+
+impl DiceExpression {
+    pub fn roll(&self, roller: &mut dyn Roller) -> DiceRoll {
+        self.roll_terms(&RollRules::new(), None, roller)
+    }
+
+    // Rolls the expression as damage so that damage roll rules apply to each die.
+    pub fn roll_damage(
+        &self,
+        roll_rules: &RollRules,
+        source: DamageSource,
+        roller: &mut dyn Roller,
+    ) -> DiceRoll {
+        self.roll_terms(roll_rules, Some(source), roller)
+    }
+
+    fn roll_terms(
+        &self,
+        roll_rules: &RollRules,
+        damage_source: Option<DamageSource>,
+        roller: &mut dyn Roller,
+    ) -> DiceRoll {
+        let mut terms = Vec::new();
+        let mut total = 0;
+
+        for term in self.terms.iter() {
+            let roll_target = match damage_source {
+                Some(source) => RollTarget::Damage(term.damage_type, source),
+                None => RollTarget::Other,
+            };
+            let (dice, magnitude) = match term.value {
+                TermValue::Flat(value) => (vec![], value),
+                TermValue::Dice(group) => roll_dice_group(group, roll_rules, roll_target, roller),
+            };
+
+            let subtotal = if term.negative { -magnitude } else { magnitude };
+            total = i32::saturating_add(total, subtotal);
+            terms.push(TermRoll {
+                term: *term,
+                dice,
+                subtotal,
+            });
+        }
+
+        DiceRoll { total, terms }
+    }
+
+    pub fn min(&self) -> i32 {
+        self.terms
+            .iter()
+            .map(|term| {
+                if term.negative {
+                    -term_max(term.value)
+                } else {
+                    term_min(term.value)
+                }
+            })
+            .fold(0, i32::saturating_add)
+    }
+
+    pub fn max(&self) -> i32 {
+        self.terms
+            .iter()
+            .map(|term| {
+                if term.negative {
+                    -term_min(term.value)
+                } else {
+                    term_max(term.value)
+                }
+            })
+            .fold(0, i32::saturating_add)
+    }
+
+    // When you score a critical hit, you roll all of the attack's damage dice twice and
+    // add them together. Extra dice (e.g. Brutal Critical) repeat the first damage die.
+    // Die counts are capped at u16::MAX rather than overflowing.
+    pub fn critical(&self, extra_dice: u16) -> DiceExpression {
+        let mut terms: Vec<DiceTerm> = self
+            .terms
+            .iter()
+            .map(|term| match term.value {
+                TermValue::Dice(group) => DiceTerm {
+                    value: TermValue::Dice(DiceGroup {
+                        count: group.count.saturating_mul(2),
+                        die: group.die,
+                        keep: group.keep.map(|keep| match keep {
+                            KeepRule::KeepHighest(n) => KeepRule::KeepHighest(n.saturating_mul(2)),
+                            KeepRule::KeepLowest(n) => KeepRule::KeepLowest(n.saturating_mul(2)),
+                            KeepRule::DropHighest(n) => KeepRule::DropHighest(n.saturating_mul(2)),
+                            KeepRule::DropLowest(n) => KeepRule::DropLowest(n.saturating_mul(2)),
+                        }),
+                    }),
+                    ..*term
+                },
+                TermValue::Flat(_) => *term,
+            })
+            .collect();
+
+        let first_damage_die = self
+            .terms
+            .iter()
+            .find(|term| !term.negative && matches!(term.value, TermValue::Dice(_)));
+        if let (Some(term), true) = (first_damage_die, extra_dice > 0) {
+            if let TermValue::Dice(group) = term.value {
+                terms.push(DiceTerm {
+                    negative: false,
+                    value: TermValue::Dice(DiceGroup {
+                        count: extra_dice,
+                        die: group.die,
+                        keep: None,
+                    }),
+                    damage_type: term.damage_type,
+                });
+            }
+        }
+
+        DiceExpression {
+            terms,
+            fixed_value: None,
+        }
+    }
+
+    // The sum of the constant terms, such as the 4 in "1d20 + 5 - 1".
+    pub fn flat_modifier(&self) -> i32 {
+        self.terms
+            .iter()
+            .map(|term| match term.value {
+                TermValue::Flat(value) if term.negative => -value,
+                TermValue::Flat(value) => value,
+                TermValue::Dice(_) => 0,
+            })
+            .sum()
+    }
+}
+
+fn roll_dice_group(
+    group: DiceGroup,
+    roll_rules: &RollRules,
+    roll_target: RollTarget,
+    roller: &mut dyn Roller,
+) -> (Vec<DieRoll>, i32) {
+    let rolls: Vec<(u16, Vec<DieAdjustment>)> = (0..group.count)
+        .map(|_| roll_rules.roll(group.die, roll_target, roller))
+        .collect();
+    let values: Vec<u16> = rolls.iter().map(|(value, _)| *value).collect();
+    let kept = match group.keep {
+        Some(keep) => keep.kept(&values),
+        None => vec![true; values.len()],
+    };
+
+    let dice: Vec<DieRoll> = rolls
+        .into_iter()
+        .zip(kept.iter())
+        .map(|((value, adjustments), &kept)| DieRoll {
+            die: group.die,
+            value,
+            kept,
+            adjustments,
+        })
+        .collect();
+    let sum = dice
+        .iter()
+        .filter(|die_roll| die_roll.kept)
+        .map(|die_roll| die_roll.value as i32)
+        .fold(0, i32::saturating_add);
+
+    (dice, sum)
+}
+
+fn term_min(value: TermValue) -> i32 {
+    match value {
+        TermValue::Flat(value) => value,
+        TermValue::Dice(group) => dice_total(group.kept_count(), group.die.min),
+    }
+}
+
+fn term_max(value: TermValue) -> i32 {
+    match value {
+        TermValue::Flat(value) => value,
+        TermValue::Dice(group) => dice_total(group.kept_count(), group.die.max),
+    }
+}
+
+// The parser accepts up to 65535d65535, whose maximum doesn't fit in an i32,
+// so totals are capped at i32::MAX rather than overflowing.
+fn dice_total(count: u16, face: u16) -> i32 {
+    (count as u32 * face as u32).min(i32::MAX as u32) as i32
+}
+
+impl From<Die> for DiceExpression {
+    fn from(die: Die) -> Self {
+        DiceExpression {
+            terms: vec![DiceTerm {
+                negative: false,
+                value: TermValue::Dice(DiceGroup {
+                    count: 1,
+                    die,
+                    keep: None,
+                }),
+                damage_type: None,
+            }],
+            fixed_value: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DiceParseError {
+    Empty,
+    UnexpectedCharacter(char, usize),
+    UnexpectedEnd,
+    InvalidNumber(String),
+    ZeroSidedDie,
+    InvalidKeepCount(u16, u16),
+    UnknownDamageType(String),
+}
+
+impl fmt::Display for DiceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiceParseError::Empty => write!(f, "dice expression is empty"),
+            DiceParseError::UnexpectedCharacter(character, position) => write!(
+                f,
+                "unexpected character '{}' at position {}",
+                character, position
+            ),
+            DiceParseError::UnexpectedEnd => write!(f, "dice expression ended unexpectedly"),
+            DiceParseError::InvalidNumber(number) => write!(f, "invalid number '{}'", number),
+            DiceParseError::ZeroSidedDie => write!(f, "a die must have at least one side"),
+            DiceParseError::InvalidKeepCount(keep, count) => {
+                write!(f, "cannot keep or drop {} dice out of {}", keep, count)
+            }
+            DiceParseError::UnknownDamageType(name) => {
+                write!(f, "unknown damage type '{}'", name)
+            }
+        }
+    }
+}
+
+struct DiceParser {
+    characters: Vec<char>,
+    position: usize,
+}
+
+impl DiceParser {
+    fn new(source: &str) -> DiceParser {
+        DiceParser {
+            characters: source.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(character) = self.peek() {
+            if !character.is_whitespace() {
+                break;
+            }
+            self.position += 1;
+        }
+    }
+
+    fn unexpected(&self) -> DiceParseError {
+        match self.peek() {
+            Some(character) => DiceParseError::UnexpectedCharacter(character, self.position),
+            None => DiceParseError::UnexpectedEnd,
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), DiceParseError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn number(&mut self) -> Option<Result<u16, DiceParseError>> {
+        let start = self.position;
+        while let Some(character) = self.peek() {
+            if !character.is_ascii_digit() {
+                break;
+            }
+            self.position += 1;
+        }
+
+        if start == self.position {
+            return None;
+        }
+
+        let digits: String = self.characters[start..self.position].iter().collect();
+        Some(
+            digits
+                .parse::<u16>()
+                .map_err(|_| DiceParseError::InvalidNumber(digits)),
+        )
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.position;
+        while let Some(character) = self.peek() {
+            if !character.is_ascii_alphabetic() {
+                break;
+            }
+            self.position += 1;
+        }
+        self.characters[start..self.position].iter().collect()
+    }
+
+    fn parse(mut self) -> Result<DiceExpression, DiceParseError> {
+        self.skip_whitespace();
+        if self.peek().is_none() {
+            return Err(DiceParseError::Empty);
+        }
+
+        let mut terms = vec![self.term(false)?];
+        let mut fixed_value = None;
+
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('+') => {
+                    self.position += 1;
+                    terms.push(self.term(false)?);
+                }
+                Some('-') => {
+                    self.position += 1;
+                    terms.push(self.term(true)?);
+                }
+                Some('(') => {
+                    self.position += 1;
+                    self.skip_whitespace();
+                    if self.word().to_lowercase() != "or" {
+                        return Err(self.unexpected());
+                    }
+                    self.skip_whitespace();
+                    let value = self.number().ok_or_else(|| self.unexpected())??;
+                    self.expect(')')?;
+                    fixed_value = Some(value as i32);
+                }
+                Some(_) => return Err(self.unexpected()),
+                None => break,
+            }
+
+            if fixed_value.is_some() {
+                self.skip_whitespace();
+                if self.peek().is_some() {
+                    return Err(self.unexpected());
+                }
+                break;
+            }
+        }
+
+        Ok(DiceExpression { terms, fixed_value })
+    }
+
+    fn term(&mut self, negative: bool) -> Result<DiceTerm, DiceParseError> {
+        self.skip_whitespace();
+        let count = self.number().transpose()?;
+
+        let value = match self.peek() {
+            Some('d') | Some('D') => {
+                self.position += 1;
+                let count = count.unwrap_or(1);
+                let sides = self.number().ok_or_else(|| self.unexpected())??;
+                if sides == 0 {
+                    return Err(DiceParseError::ZeroSidedDie);
+                }
+                let keep = self.keep_rule(count)?;
+                TermValue::Dice(DiceGroup {
+                    count,
+                    die: Die::with_sides(sides),
+                    keep,
+                })
+            }
+            _ => match count {
+                Some(value) => TermValue::Flat(value as i32),
+                None => return Err(self.unexpected()),
+            },
+        };
+
+        let damage_type = self.damage_type()?;
+
+        Ok(DiceTerm {
+            negative,
+            value,
+            damage_type,
+        })
+    }
+
+    fn keep_rule(&mut self, count: u16) -> Result<Option<KeepRule>, DiceParseError> {
+        let start = self.position;
+        let (rule, length): (fn(u16) -> KeepRule, usize) =
+            match (self.peek(), self.characters.get(self.position + 1)) {
+                (Some('k'), Some('h')) => (KeepRule::KeepHighest, 2),
+                (Some('k'), Some('l')) => (KeepRule::KeepLowest, 2),
+                (Some('d'), Some('h')) => (KeepRule::DropHighest, 2),
+                (Some('d'), Some('l')) => (KeepRule::DropLowest, 2),
+                (Some('k'), Some(next)) if next.is_ascii_digit() => (KeepRule::KeepHighest, 1),
+                _ => return Ok(None),
+            };
+
+        self.position += length;
+        match self.number() {
+            Some(number) => {
+                let number = number?;
+                if number > count {
+                    return Err(DiceParseError::InvalidKeepCount(number, count));
+                }
+                Ok(Some(rule(number)))
+            }
+            None => {
+                // Not a keep rule after all, e.g. the start of a damage type word.
+                self.position = start;
+                Ok(None)
+            }
+        }
+    }
+
+    fn damage_type(&mut self) -> Result<Option<DamageType>, DiceParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(character) if character.is_ascii_alphabetic() => {
+                let start = self.position;
+                let word = self.word();
+                if word.to_lowercase() == "or" {
+                    self.position = start;
+                    return Ok(None);
+                }
+                word.parse::<DamageType>()
+                    .map(Some)
+                    .map_err(|_| DiceParseError::UnknownDamageType(word))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl FromStr for DiceExpression {
+    type Err = DiceParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        DiceParser::new(source).parse()
+    }
+}
+
+impl fmt::Display for DiceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, term) in self.terms.iter().enumerate() {
+            if term.negative {
+                write!(f, "{}", if index == 0 { "-" } else { " - " })?;
+            } else if index > 0 {
+                write!(f, " + ")?;
+            }
+
+            match term.value {
+                TermValue::Flat(value) => write!(f, "{}", value)?,
+                TermValue::Dice(group) => {
+                    write!(f, "{}{}", group.count, group.die)?;
+                    if let Some(keep) = group.keep {
+                        write!(f, "{}", keep)?;
+                    }
+                }
+            }
+
+            if let Some(damage_type) = term.damage_type {
+                write!(f, " {}", format!("{:?}", damage_type).to_lowercase())?;
+            }
+        }
+
+        if let Some(fixed_value) = self.fixed_value {
+            write!(f, " (or {})", fixed_value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for DiceExpression {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for DiceExpression {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        source.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roller::{ScriptedRoller, SeededRoller};
+
+    #[test]
+    fn parse_dice_expressions() {
+        let expression: DiceExpression = "2d6+3".parse().unwrap();
+        assert_eq!(expression.min(), 5);
+        assert_eq!(expression.max(), 15);
+        assert_eq!(expression.to_string(), "2d6 + 3");
+
+        let expression: DiceExpression = "4d6kh3".parse().unwrap();
+        assert_eq!(expression.min(), 3);
+        assert_eq!(expression.max(), 18);
+
+        let expression: DiceExpression = "1d8 + 1d6 fire".parse().unwrap();
+        assert_eq!(expression.terms[0].damage_type, None);
+        assert_eq!(expression.terms[1].damage_type, Some(DamageType::Fire));
+
+        let expression: DiceExpression = "1d12 (or 7)".parse().unwrap();
+        assert_eq!(expression.fixed_value, Some(7));
+        assert_eq!(expression.to_string(), "1d12 (or 7)");
+
+        let expression: DiceExpression = "d20 - 1".parse().unwrap();
+        assert_eq!(expression.min(), 0);
+        assert_eq!(expression.max(), 19);
+
+        let expression: DiceExpression = "1d4 + 1d8 + 1d10 + 1d100".parse().unwrap();
+        let dice: Vec<Die> = expression
+            .terms
+            .iter()
+            .filter_map(|term| match term.value {
+                TermValue::Dice(group) => Some(group.die),
+                TermValue::Flat(_) => None,
+            })
+            .collect();
+        assert_eq!(dice, vec![D4, D8, D10, D100]);
+    }
+
+    #[test]
+    fn flat_modifier_ignores_the_dice() {
+        let expression: DiceExpression = "1d20 + 5 - 1".parse().unwrap();
+        assert_eq!(expression.flat_modifier(), 4);
+
+        let expression: DiceExpression = "5 - 1d20".parse().unwrap();
+        assert_eq!(expression.flat_modifier(), 5);
+    }
+
+    #[test]
+    fn reject_invalid_dice_expressions() {
+        assert_eq!("".parse::<DiceExpression>(), Err(DiceParseError::Empty));
+        assert_eq!(
+            "1d0".parse::<DiceExpression>(),
+            Err(DiceParseError::ZeroSidedDie)
+        );
+        assert_eq!(
+            "2d6kh3".parse::<DiceExpression>(),
+            Err(DiceParseError::InvalidKeepCount(3, 2))
+        );
+        assert_eq!(
+            "1d6 frost".parse::<DiceExpression>(),
+            Err(DiceParseError::UnknownDamageType(String::from("frost")))
+        );
+        assert_eq!(
+            "2d6 +".parse::<DiceExpression>(),
+            Err(DiceParseError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn roll_keeps_and_drops_dice() {
+        let expression: DiceExpression = "4d6dl1 + 2".parse().unwrap();
+        let mut roller = ScriptedRoller::new(vec![3, 1, 6, 4]);
+        let roll = expression.roll(&mut roller);
+
+        let kept: Vec<bool> = roll.terms[0]
+            .dice
+            .iter()
+            .map(|die_roll| die_roll.kept)
+            .collect();
+        assert_eq!(kept, vec![true, false, true, true]);
+        assert_eq!(roll.terms[0].subtotal, 13);
+        assert_eq!(roll.terms[1].subtotal, 2);
+        assert_eq!(roll.total, 15);
+    }
+
+    #[test]
+    fn roll_totals_by_damage_type() {
+        let expression: DiceExpression = "1d8 slashing + 2d6 fire - 1 slashing".parse().unwrap();
+        let mut roller = ScriptedRoller::new(vec![5, 2, 6]);
+        let roll = expression.roll(&mut roller);
+
+        assert_eq!(roll.total, 12);
+        assert_eq!(roll.total_for_damage_type(DamageType::Slashing), 4);
+        assert_eq!(roll.total_for_damage_type(DamageType::Fire), 8);
+    }
+
+    #[test]
+    fn critical_doubles_dice_but_not_modifiers() {
+        let expression: DiceExpression = "1d12 + 1d6 fire + 3".parse().unwrap();
+
+        assert_eq!(expression.critical(0).to_string(), "2d12 + 2d6 fire + 3");
+        assert_eq!(
+            expression.critical(2).to_string(),
+            "2d12 + 2d6 fire + 3 + 2d12"
+        );
+
+        let huge: DiceExpression = "40000d6kh30000".parse().unwrap();
+        assert_eq!(huge.critical(0).to_string(), "65535d6kh60000");
+    }
+
+    #[test]
+    fn huge_dice_totals_saturate() {
+        let expression: DiceExpression = "65535d65535".parse().unwrap();
+        assert_eq!(expression.min(), 65535);
+        assert_eq!(expression.max(), i32::MAX);
+
+        let difference: DiceExpression = "1 - 65535d65535".parse().unwrap();
+        assert_eq!(difference.min(), 1 - i32::MAX);
+
+        let roll = expression.roll(&mut ScriptedRoller::new(vec![65535; 65535]));
+        assert_eq!(roll.total, i32::MAX);
+    }
+
+    #[test]
+    fn seeded_rolls_are_reproducible() {
+        let expression: DiceExpression = "8d6 + 4d10kh2".parse().unwrap();
+        let first = expression.roll(&mut SeededRoller::new(7));
+        let second = expression.roll(&mut SeededRoller::new(7));
+
+        assert_eq!(first, second);
+        assert!(first.total >= expression.min() && first.total <= expression.max());
+    }
+}
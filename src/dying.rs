@@ -0,0 +1,333 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::conditions::ConditionDuration;
+use crate::d20::{D20Roll, DeathSaveOutcome, RollMode};
+use crate::roller::Roller;
+use crate::{Character, Condition, EntityRoll, Skill};
+
+const DEATH_SAVING_THROWS_TO_STABILIZE: u8 = 3;
+const DEATH_SAVING_THROWS_TO_DIE: u8 = 3;
+const STABILIZE_DIFFICULTY_CLASS: i16 = 10;
+const DYING_CONDITION_SOURCE: &str = "0 hit points";
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct DeathSavingThrows {
+    pub successes: u8,
+    pub failures: u8,
+    pub dead: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DyingState {
+    Conscious,
+    Dying { successes: u8, failures: u8 },
+    Stable,
+    Dead,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DamageOutcome {
+    Conscious,
+    // Temporary hit points absorbed all the damage taken at 0 hit points.
+    Unharmed,
+    KnockedUnconscious,
+    // Damage taken at 0 hit points counts as a failed death saving throw, or
+    // two on a critical hit.
+    DeathSavingThrowsFailed(u8),
+    Killed,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DyingError {
+    NotDying,
+    Dead,
+}
+
+impl fmt::Display for DyingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DyingError::NotDying => write!(f, "the character isn't dying"),
+            DyingError::Dead => write!(f, "the character is dead"),
+        }
+    }
+}
+
+impl Character {
+    pub fn get_dying_state(&self) -> DyingState {
+        if self.death_saving_throws.dead {
+            DyingState::Dead
+        } else if self.get_current_hit_points() > 0 {
+            DyingState::Conscious
+        } else if self.has_condition(Condition::Stable) {
+            DyingState::Stable
+        } else {
+            DyingState::Dying {
+                successes: self.death_saving_throws.successes,
+                failures: self.death_saving_throws.failures,
+            }
+        }
+    }
+
+    // Applies damage with the rules for dropping to 0 hit points: damage that
+    // leaves as much left over as the hit point maximum kills outright.
+    pub fn suffer_damage(&mut self, damage: u16, critical: bool) -> DamageOutcome {
+        if self.death_saving_throws.dead {
+            return DamageOutcome::Killed;
+        }
+
+        let max_hit_points = self.get_max_hit_points();
+        let was_conscious = self.get_current_hit_points() > 0;
+        let damage_taken = self.take_damage(damage);
+        if self.get_current_hit_points() > 0 {
+            return DamageOutcome::Conscious;
+        }
+
+        if damage_taken.excess_damage >= max_hit_points {
+            self.die();
+            return DamageOutcome::Killed;
+        }
+
+        if was_conscious {
+            self.fall_unconscious();
+            return DamageOutcome::KnockedUnconscious;
+        }
+
+        if damage_taken.excess_damage == 0 {
+            return DamageOutcome::Unharmed;
+        }
+
+        let failures = if critical { 2 } else { 1 };
+        self.remove_condition(Condition::Stable);
+        if self.fail_death_saving_throws(failures) {
+            return DamageOutcome::Killed;
+        }
+        DamageOutcome::DeathSavingThrowsFailed(failures)
+    }
+
+    pub fn make_death_saving_throw(
+        &mut self,
+        roller: &mut dyn Roller,
+    ) -> Result<DeathSaveOutcome, DyingError> {
+        match self.get_dying_state() {
+            DyingState::Dying { .. } => {}
+            DyingState::Dead => return Err(DyingError::Dead),
+            DyingState::Conscious | DyingState::Stable => return Err(DyingError::NotDying),
+        }
+
+        let (_, outcome) = self.roll_death_saving_throw(roller);
+        match outcome {
+            DeathSaveOutcome::Success => {
+                self.death_saving_throws.successes += 1;
+                if self.death_saving_throws.successes >= DEATH_SAVING_THROWS_TO_STABILIZE {
+                    self.become_stable();
+                }
+            }
+            DeathSaveOutcome::Failure => {
+                self.fail_death_saving_throws(1);
+            }
+            DeathSaveOutcome::DoubleFailure => {
+                self.fail_death_saving_throws(2);
+            }
+            DeathSaveOutcome::RegainHitPoint => {
+                self.heal(1);
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    // The healer makes a DC 10 Wisdom (Medicine) check to stabilize a dying
+    // creature. Returns the check, whether or not it succeeded.
+    pub fn stabilize(
+        &self,
+        target: &mut Character,
+        roller: &mut dyn Roller,
+    ) -> Result<D20Roll, DyingError> {
+        match target.get_dying_state() {
+            DyingState::Dying { .. } => {}
+            DyingState::Dead => return Err(DyingError::Dead),
+            DyingState::Conscious | DyingState::Stable => return Err(DyingError::NotDying),
+        }
+
+        let medicine_check = self.roll_skill_check(Skill::Medicine, None, RollMode::Normal, roller);
+        if medicine_check.succeeds(STABILIZE_DIFFICULTY_CLASS) {
+            target.become_stable();
+        }
+
+        Ok(medicine_check)
+    }
+
+    // A lower hit point maximum, such as after losing a level or from the fourth
+    // level of exhaustion, can leave less than the damage already taken. The
+    // character then drops to 0 hit points the same way as from damage.
+    pub(crate) fn apply_hit_point_maximum(&mut self, was_conscious: bool) {
+        let max_hit_points = self.get_max_hit_points();
+        if self.hit_points.damage_taken < max_hit_points {
+            return;
+        }
+
+        self.hit_points.damage_taken = max_hit_points;
+        if was_conscious && !self.death_saving_throws.dead {
+            self.fall_unconscious();
+        }
+    }
+
+    fn fall_unconscious(&mut self) {
+        self.death_saving_throws = DeathSavingThrows::default();
+        self.insert_condition(
+            Condition::Unconscious,
+            DYING_CONDITION_SOURCE,
+            ConditionDuration::UntilRemoved,
+        );
+    }
+
+    // Any healing brings a character at 0 hit points back to consciousness,
+    // though it stays prone.
+    pub(crate) fn regain_consciousness(&mut self) {
+        self.death_saving_throws = DeathSavingThrows::default();
+        self.remove_condition(Condition::Unconscious);
+        self.remove_condition(Condition::Stable);
+    }
+
+    fn become_stable(&mut self) {
+        self.death_saving_throws = DeathSavingThrows::default();
+        self.insert_condition(
+            Condition::Stable,
+            DYING_CONDITION_SOURCE,
+            ConditionDuration::UntilRemoved,
+        );
+    }
+
+    // Returns whether the failures killed the character.
+    fn fail_death_saving_throws(&mut self, failures: u8) -> bool {
+        self.death_saving_throws.failures += failures;
+        if self.death_saving_throws.failures >= DEATH_SAVING_THROWS_TO_DIE {
+            self.die();
+        }
+        self.death_saving_throws.dead
+    }
+
+    pub(crate) fn die(&mut self) {
+        self.death_saving_throws.dead = true;
+        self.remove_condition(Condition::Stable);
+        self.insert_condition(
+            Condition::Unconscious,
+            DYING_CONDITION_SOURCE,
+            ConditionDuration::UntilRemoved,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgrounds::BackgroundType;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::build_character;
+    use crate::{Ability, ClassType, RaceType};
+
+    fn build_acolyte_cleric() -> Character {
+        build_character(
+            RaceType::Human,
+            ClassType::Cleric,
+            &[
+                (Ability::Strength, 13),
+                (Ability::Dexterity, 8),
+                (Ability::Constitution, 14),
+                (Ability::Intelligence, 10),
+                (Ability::Wisdom, 15),
+                (Ability::Charisma, 12),
+            ],
+            BackgroundType::Acolyte,
+        )
+    }
+
+    #[test]
+    fn dropping_to_zero_and_rolling_death_saving_throws() {
+        let mut character = build_acolyte_cleric();
+        assert_eq!(character.get_max_hit_points(), 10);
+
+        assert_eq!(
+            character.suffer_damage(12, false),
+            DamageOutcome::KnockedUnconscious
+        );
+        assert!(character.has_condition(Condition::Unconscious));
+        assert!(character.has_condition(Condition::Prone));
+
+        let mut roller = ScriptedRoller::new(vec![12, 1, 20]);
+        assert_eq!(
+            character.make_death_saving_throw(&mut roller),
+            Ok(DeathSaveOutcome::Success)
+        );
+        assert_eq!(
+            character.make_death_saving_throw(&mut roller),
+            Ok(DeathSaveOutcome::DoubleFailure)
+        );
+        assert_eq!(
+            character.get_dying_state(),
+            DyingState::Dying {
+                successes: 1,
+                failures: 2
+            }
+        );
+        assert_eq!(
+            character.make_death_saving_throw(&mut roller),
+            Ok(DeathSaveOutcome::RegainHitPoint)
+        );
+        assert_eq!(character.get_current_hit_points(), 1);
+        assert_eq!(character.get_dying_state(), DyingState::Conscious);
+        assert!(!character.has_condition(Condition::Unconscious));
+        assert!(character.has_condition(Condition::Prone));
+    }
+
+    #[test]
+    fn damage_at_zero_hit_points_and_massive_damage() {
+        let mut character = build_acolyte_cleric();
+        character.suffer_damage(10, false);
+        assert_eq!(
+            character.suffer_damage(3, true),
+            DamageOutcome::DeathSavingThrowsFailed(2)
+        );
+        assert_eq!(character.suffer_damage(3, false), DamageOutcome::Killed);
+        assert_eq!(character.get_dying_state(), DyingState::Dead);
+        assert_eq!(
+            character.make_death_saving_throw(&mut ScriptedRoller::new(vec![])),
+            Err(DyingError::Dead)
+        );
+
+        let mut character = build_acolyte_cleric();
+        assert_eq!(character.suffer_damage(20, false), DamageOutcome::Killed);
+    }
+
+    #[test]
+    fn medicine_check_stabilizes_a_dying_character() {
+        let healer = build_acolyte_cleric();
+        let mut character = build_acolyte_cleric();
+        let mut roller = ScriptedRoller::new(vec![7]);
+        assert_eq!(
+            healer.stabilize(&mut character, &mut roller),
+            Err(DyingError::NotDying)
+        );
+
+        character.suffer_damage(10, false);
+        let medicine_check = healer.stabilize(&mut character, &mut roller).unwrap();
+        assert_eq!(medicine_check.total, 10);
+        assert_eq!(character.get_dying_state(), DyingState::Stable);
+        assert!(character.has_condition(Condition::Stable));
+        assert_eq!(
+            character.make_death_saving_throw(&mut roller),
+            Err(DyingError::NotDying)
+        );
+
+        character.suffer_damage(1, false);
+        assert_eq!(
+            character.get_dying_state(),
+            DyingState::Dying {
+                successes: 0,
+                failures: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,577 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::armor_class::{ArmorClass, Shield};
+use crate::inventory::InventoryItem;
+use crate::{Armor, ArmorCategory, ArmorEffect, Character, Item, Weapon, WeaponProperty};
+
+const ATTUNEMENT_LIMIT: usize = 3;
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum EquipmentSlot {
+    BodyArmor,
+    Shield,
+    MainHand,
+    OffHand,
+    Worn,
+}
+
+// Equipped items are referenced by name and stay in the inventory, so that
+// they're only weighed once. Carried items have unique names, and magic armor
+// and shields carry their bonus in the name.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Equipment {
+    pub body_armor: Option<String>,
+    pub shield: Option<String>,
+    pub main_hand: Option<String>,
+    pub off_hand: Option<String>,
+    pub worn: Vec<String>,
+    pub attuned: Vec<String>,
+}
+
+// Drawing or sheathing a weapon is part of moving or acting, a shield takes an
+// action, and armor takes minutes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EquipTime {
+    ObjectInteraction,
+    Action,
+    Minutes(u32),
+}
+
+impl ArmorCategory {
+    pub fn get_don_time(&self) -> EquipTime {
+        match self {
+            ArmorCategory::LightArmor => EquipTime::Minutes(1),
+            ArmorCategory::MediumArmor => EquipTime::Minutes(5),
+            ArmorCategory::HeavyArmor => EquipTime::Minutes(10),
+        }
+    }
+
+    pub fn get_doff_time(&self) -> EquipTime {
+        match self {
+            ArmorCategory::LightArmor | ArmorCategory::MediumArmor => EquipTime::Minutes(1),
+            ArmorCategory::HeavyArmor => EquipTime::Minutes(5),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EquipmentError {
+    NotCarried(String),
+    NotEquipped(String),
+    WrongSlot { name: String, slot: EquipmentSlot },
+    SlotOccupied(EquipmentSlot),
+    HandsFull,
+    NotAttunable(String),
+    AlreadyAttuned(String),
+    NotAttuned(String),
+    AttunementLimit,
+}
+
+impl fmt::Display for EquipmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EquipmentError::NotCarried(name) => write!(f, "no unequipped {} is carried", name),
+            EquipmentError::NotEquipped(name) => write!(f, "no {} is equipped", name),
+            EquipmentError::WrongSlot { name, slot } => {
+                write!(f, "a {} can't be equipped as {:?}", name, slot)
+            }
+            EquipmentError::SlotOccupied(slot) => write!(f, "the {:?} slot is in use", slot),
+            EquipmentError::HandsFull => write!(f, "both hands are in use"),
+            EquipmentError::NotAttunable(name) => {
+                write!(f, "the {} doesn't require attunement", name)
+            }
+            EquipmentError::AlreadyAttuned(name) => {
+                write!(f, "the character is already attuned to the {}", name)
+            }
+            EquipmentError::NotAttuned(name) => {
+                write!(f, "the character isn't attuned to the {}", name)
+            }
+            EquipmentError::AttunementLimit => write!(
+                f,
+                "a character can't be attuned to more than {} items",
+                ATTUNEMENT_LIMIT
+            ),
+        }
+    }
+}
+
+fn is_two_handed(item: &InventoryItem) -> bool {
+    match item {
+        InventoryItem::Weapon(weapon) => weapon.has_property(&WeaponProperty::TwoHanded),
+        _ => false,
+    }
+}
+
+impl Character {
+    fn find_carried_item(&self, name: &str) -> Option<&InventoryItem> {
+        self.inventory
+            .items
+            .iter()
+            .map(|entry| &entry.item)
+            .find(|item| item.get_name() == name)
+    }
+
+    // A two-handed weapon is recorded in both hands but only counts once.
+    fn is_wielding_two_handed(&self) -> bool {
+        match (&self.equipment.main_hand, &self.equipment.off_hand) {
+            (Some(main_hand), Some(off_hand)) if main_hand == off_hand => {
+                self.find_carried_item(main_hand).is_some_and(is_two_handed)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get_equipped_count(&self, name: &str) -> u32 {
+        let equipment = &self.equipment;
+        let off_hand = if self.is_wielding_two_handed() {
+            None
+        } else {
+            equipment.off_hand.as_ref()
+        };
+        equipment
+            .body_armor
+            .iter()
+            .chain(equipment.shield.iter())
+            .chain(equipment.main_hand.iter())
+            .chain(off_hand)
+            .chain(equipment.worn.iter())
+            .filter(|equipped| equipped.as_str() == name)
+            .count() as u32
+    }
+
+    pub fn get_worn_armor(&self) -> Option<&Armor> {
+        match self.find_carried_item(self.equipment.body_armor.as_ref()?)? {
+            InventoryItem::Armor(armor) => Some(armor),
+            _ => None,
+        }
+    }
+
+    pub fn get_equipped_shield(&self) -> Option<&Shield> {
+        match self.find_carried_item(self.equipment.shield.as_ref()?)? {
+            InventoryItem::Shield(shield) => Some(shield),
+            _ => None,
+        }
+    }
+
+    pub fn get_wielded_weapon(&self, slot: EquipmentSlot) -> Option<&Weapon> {
+        let name = match slot {
+            EquipmentSlot::MainHand => self.equipment.main_hand.as_ref()?,
+            EquipmentSlot::OffHand => self.equipment.off_hand.as_ref()?,
+            _ => return None,
+        };
+        match self.find_carried_item(name)? {
+            InventoryItem::Weapon(weapon) => Some(weapon),
+            _ => None,
+        }
+    }
+
+    pub fn get_equipped_armor_class(&self) -> ArmorClass {
+        self.get_armor_class(self.get_worn_armor(), self.get_equipped_shield())
+    }
+
+    // Traits like Medium Armor Master remove the disadvantage for a category.
+    pub fn has_armor_stealth_disadvantage(&self) -> bool {
+        let Some(armor) = self.get_worn_armor() else {
+            return false;
+        };
+        let ignored = self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.armor_modifiers.iter())
+            .any(|armor_modifier| {
+                armor_modifier.value == ArmorEffect::NoStealthDisadvantage(armor.category)
+            });
+        armor.has_stealth_disadvantage && !ignored
+    }
+
+    fn check_hands_free(
+        &self,
+        slot: EquipmentSlot,
+        two_handed: bool,
+    ) -> Result<(), EquipmentError> {
+        let equipment = &self.equipment;
+        let main_hand_free = equipment.main_hand.is_none();
+        let off_hand_free = equipment.off_hand.is_none() && equipment.shield.is_none();
+        let hands_free = match slot {
+            _ if two_handed => main_hand_free && off_hand_free,
+            EquipmentSlot::MainHand => main_hand_free,
+            _ => off_hand_free,
+        };
+        if hands_free {
+            Ok(())
+        } else if two_handed || slot == EquipmentSlot::Shield || self.is_wielding_two_handed() {
+            Err(EquipmentError::HandsFull)
+        } else {
+            Err(EquipmentError::SlotOccupied(slot))
+        }
+    }
+
+    // Only one suit of armor can be worn, a shield takes the off hand, and a
+    // two-handed weapon takes both hands. Returns how long equipping takes.
+    pub fn equip(&mut self, name: &str, slot: EquipmentSlot) -> Result<EquipTime, EquipmentError> {
+        let item = self
+            .find_carried_item(name)
+            .ok_or_else(|| EquipmentError::NotCarried(String::from(name)))?;
+        let carried = self.inventory.get_quantity(name);
+        if self.get_equipped_count(name) >= carried {
+            return Err(EquipmentError::NotCarried(String::from(name)));
+        }
+
+        let equip_time = match (item, slot) {
+            (InventoryItem::Armor(armor), EquipmentSlot::BodyArmor) => {
+                if self.equipment.body_armor.is_some() {
+                    return Err(EquipmentError::SlotOccupied(slot));
+                }
+                armor.category.get_don_time()
+            }
+            (InventoryItem::Shield(_), EquipmentSlot::Shield) => {
+                self.check_hands_free(slot, false)?;
+                EquipTime::Action
+            }
+            (InventoryItem::Weapon(_), EquipmentSlot::MainHand)
+            | (InventoryItem::Weapon(_), EquipmentSlot::OffHand) => {
+                self.check_hands_free(slot, is_two_handed(item))?;
+                EquipTime::ObjectInteraction
+            }
+            (InventoryItem::Gear(_), EquipmentSlot::Worn) => EquipTime::Action,
+            _ => {
+                return Err(EquipmentError::WrongSlot {
+                    name: String::from(name),
+                    slot,
+                })
+            }
+        };
+        let two_handed = is_two_handed(item);
+
+        let name = String::from(name);
+        let equipment = &mut self.equipment;
+        match slot {
+            EquipmentSlot::BodyArmor => equipment.body_armor = Some(name),
+            EquipmentSlot::Shield => equipment.shield = Some(name),
+            EquipmentSlot::MainHand | EquipmentSlot::OffHand if two_handed => {
+                equipment.main_hand = Some(name.clone());
+                equipment.off_hand = Some(name);
+            }
+            EquipmentSlot::MainHand => equipment.main_hand = Some(name),
+            EquipmentSlot::OffHand => equipment.off_hand = Some(name),
+            EquipmentSlot::Worn => equipment.worn.push(name),
+        }
+        self.refresh_derived_statistics();
+        Ok(equip_time)
+    }
+
+    // Armor is taken off first, then the shield, the weapons and worn items.
+    pub fn unequip(&mut self, name: &str) -> Result<EquipTime, EquipmentError> {
+        let equip_time = if self.equipment.body_armor.as_deref() == Some(name) {
+            let doff_time = self
+                .get_worn_armor()
+                .map_or(EquipTime::Action, |armor| armor.category.get_doff_time());
+            self.equipment.body_armor = None;
+            doff_time
+        } else if self.equipment.shield.as_deref() == Some(name) {
+            self.equipment.shield = None;
+            EquipTime::Action
+        } else if self.is_wielding_two_handed() && self.equipment.main_hand.as_deref() == Some(name)
+        {
+            self.equipment.main_hand = None;
+            self.equipment.off_hand = None;
+            EquipTime::ObjectInteraction
+        } else if self.equipment.main_hand.as_deref() == Some(name) {
+            self.equipment.main_hand = None;
+            EquipTime::ObjectInteraction
+        } else if self.equipment.off_hand.as_deref() == Some(name) {
+            self.equipment.off_hand = None;
+            EquipTime::ObjectInteraction
+        } else if let Some(index) = self.equipment.worn.iter().position(|worn| worn == name) {
+            self.equipment.worn.remove(index);
+            EquipTime::Action
+        } else {
+            return Err(EquipmentError::NotEquipped(String::from(name)));
+        };
+        self.refresh_derived_statistics();
+        Ok(equip_time)
+    }
+
+    pub fn is_attuned_to(&self, name: &str) -> bool {
+        self.equipment.attuned.iter().any(|attuned| attuned == name)
+    }
+
+    // A character can be attuned to at most three magic items at a time.
+    pub fn attune(&mut self, name: &str) -> Result<(), EquipmentError> {
+        let item = self
+            .find_carried_item(name)
+            .ok_or_else(|| EquipmentError::NotCarried(String::from(name)))?;
+        if !item.requires_attunement() {
+            return Err(EquipmentError::NotAttunable(String::from(name)));
+        }
+        if self.is_attuned_to(name) {
+            return Err(EquipmentError::AlreadyAttuned(String::from(name)));
+        }
+        if self.equipment.attuned.len() >= ATTUNEMENT_LIMIT {
+            return Err(EquipmentError::AttunementLimit);
+        }
+        self.equipment.attuned.push(String::from(name));
+        Ok(())
+    }
+
+    pub fn end_attunement(&mut self, name: &str) -> Result<(), EquipmentError> {
+        let index = self
+            .equipment
+            .attuned
+            .iter()
+            .position(|attuned| attuned == name)
+            .ok_or_else(|| EquipmentError::NotAttuned(String::from(name)))?;
+        self.equipment.attuned.remove(index);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::d20::RollMode;
+    use crate::inventory::{Gear, InventoryError};
+    use crate::roller::ScriptedRoller;
+    use crate::tests::create_sample_character;
+    use crate::{
+        coin, f32, load_armor_from_file, load_feats_from_file, load_weapons_from_file, ArmorType,
+        Skill,
+    };
+
+    fn carry_weapon(character: &mut Character, name: &str, quantity: u32) {
+        let weapon = load_weapons_from_file("data/weapons.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|weapon| weapon.name == name)
+            .unwrap();
+        character
+            .add_item(InventoryItem::Weapon(weapon), quantity)
+            .unwrap();
+    }
+
+    fn shield() -> InventoryItem {
+        magic_shield(0)
+    }
+
+    fn magic_shield(magic_bonus: i16) -> InventoryItem {
+        InventoryItem::Shield(Shield {
+            cost: f32::Coin::new::<coin::gold>(10.0),
+            armor_class_bonus: 2,
+            weight: 6,
+            magic_bonus,
+        })
+    }
+
+    fn magic_ring(name: &str) -> InventoryItem {
+        InventoryItem::Gear(Gear {
+            name: String::from(name),
+            cost: f32::Coin::new::<coin::gold>(500.0),
+            weight: 0.0,
+            requires_attunement: true,
+        })
+    }
+
+    #[test]
+    fn hands_hold_weapons_and_shields() {
+        let mut character = create_sample_character();
+        carry_weapon(&mut character, "Dagger", 2);
+        carry_weapon(&mut character, "Greataxe", 1);
+        character.add_item(shield(), 1).unwrap();
+
+        assert_eq!(
+            character.equip("Greataxe", EquipmentSlot::MainHand),
+            Ok(EquipTime::ObjectInteraction)
+        );
+        assert_eq!(
+            character.equip("Dagger", EquipmentSlot::OffHand),
+            Err(EquipmentError::HandsFull)
+        );
+        assert_eq!(
+            character.equip("Shield", EquipmentSlot::Shield),
+            Err(EquipmentError::HandsFull)
+        );
+        assert_eq!(character.get_equipped_count("Greataxe"), 1);
+        assert!(character.unequip("Greataxe").is_ok());
+        assert_eq!(character.equipment, Equipment::default());
+
+        character.equip("Dagger", EquipmentSlot::MainHand).unwrap();
+        character.equip("Dagger", EquipmentSlot::OffHand).unwrap();
+        assert_eq!(
+            character
+                .get_wielded_weapon(EquipmentSlot::OffHand)
+                .unwrap()
+                .name,
+            "Dagger"
+        );
+        assert_eq!(
+            character.equip("Dagger", EquipmentSlot::MainHand),
+            Err(EquipmentError::NotCarried(String::from("Dagger")))
+        );
+        assert_eq!(
+            character.remove_item("Dagger", 1),
+            Err(InventoryError::Equipped(String::from("Dagger")))
+        );
+
+        character.unequip("Dagger").unwrap();
+        assert_eq!(character.equipment.off_hand, Some(String::from("Dagger")));
+        assert_eq!(
+            character.equip("Shield", EquipmentSlot::Shield),
+            Err(EquipmentError::HandsFull)
+        );
+        character.unequip("Dagger").unwrap();
+        assert_eq!(
+            character.equip("Shield", EquipmentSlot::Shield),
+            Ok(EquipTime::Action)
+        );
+        assert_eq!(
+            character.equip("Dagger", EquipmentSlot::Worn),
+            Err(EquipmentError::WrongSlot {
+                name: String::from("Dagger"),
+                slot: EquipmentSlot::Worn
+            })
+        );
+    }
+
+    #[test]
+    fn worn_armor_sets_armor_class_speed_and_stealth() {
+        let mut character = create_sample_character();
+        let armors = load_armor_from_file("data/armor.yaml").unwrap();
+        for armor in armors.into_iter().filter(|armor| {
+            armor.armor_type == ArmorType::Plate || armor.armor_type == ArmorType::ChainShirt
+        }) {
+            character.add_item(InventoryItem::Armor(armor), 1).unwrap();
+        }
+        let unarmored_speed = character.speed;
+        character.add_item(shield(), 1).unwrap();
+
+        assert_eq!(
+            character.equip("Plate", EquipmentSlot::BodyArmor),
+            Ok(EquipTime::Minutes(10))
+        );
+        assert_eq!(
+            character.equip("ChainShirt", EquipmentSlot::BodyArmor),
+            Err(EquipmentError::SlotOccupied(EquipmentSlot::BodyArmor))
+        );
+        character.equip("Shield", EquipmentSlot::Shield).unwrap();
+        assert_eq!(character.get_equipped_armor_class().total, 20);
+        assert!(character.has_armor_stealth_disadvantage());
+        let stealth = character.roll_skill_check(
+            Skill::Stealth,
+            None,
+            RollMode::Normal,
+            &mut ScriptedRoller::new(vec![15, 4]),
+        );
+        assert_eq!(stealth.mode, RollMode::Disadvantage);
+        assert_eq!(character.speed, unarmored_speed - 10);
+
+        assert_eq!(character.unequip("Plate"), Ok(EquipTime::Minutes(5)));
+        assert_eq!(character.speed, unarmored_speed);
+        assert_eq!(
+            character.unequip("Plate"),
+            Err(EquipmentError::NotEquipped(String::from("Plate")))
+        );
+    }
+
+    #[test]
+    fn armor_class_comes_from_the_equipped_shield() {
+        let mut character = create_sample_character();
+        character.add_item(shield(), 1).unwrap();
+        character.add_item(magic_shield(1), 1).unwrap();
+        let unshielded = character.get_equipped_armor_class().total;
+
+        character.equip("Shield +1", EquipmentSlot::Shield).unwrap();
+        assert_eq!(character.get_equipped_shield().unwrap().magic_bonus, 1);
+        assert_eq!(character.get_equipped_armor_class().total, unshielded + 3);
+        assert!(character.remove_item("Shield", 1).is_ok());
+        assert_eq!(
+            character.remove_item("Shield +1", 1),
+            Err(InventoryError::Equipped(String::from("Shield +1")))
+        );
+
+        character.unequip("Shield +1").unwrap();
+        character.add_item(shield(), 1).unwrap();
+        character.equip("Shield", EquipmentSlot::Shield).unwrap();
+        assert_eq!(character.get_equipped_armor_class().total, unshielded + 2);
+    }
+
+    #[test]
+    fn medium_armor_master_hides_in_medium_armor() {
+        let mut character = create_sample_character();
+        let armors = load_armor_from_file("data/armor.yaml").unwrap();
+        for armor in armors.into_iter().filter(|armor| {
+            armor.armor_type == ArmorType::Plate || armor.armor_type == ArmorType::HalfPlate
+        }) {
+            character.add_item(InventoryItem::Armor(armor), 1).unwrap();
+        }
+        character
+            .equip("HalfPlate", EquipmentSlot::BodyArmor)
+            .unwrap();
+        assert!(character.has_armor_stealth_disadvantage());
+
+        let medium_armor_master = load_feats_from_file("data/feats.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|feat| feat.name == "Medium Armor Master")
+            .unwrap();
+        character.traits.extend(medium_armor_master.traits);
+        assert!(!character.has_armor_stealth_disadvantage());
+        let stealth = character.roll_skill_check(
+            Skill::Stealth,
+            None,
+            RollMode::Normal,
+            &mut ScriptedRoller::new(vec![15]),
+        );
+        assert_eq!(stealth.mode, RollMode::Normal);
+
+        character.unequip("HalfPlate").unwrap();
+        character.equip("Plate", EquipmentSlot::BodyArmor).unwrap();
+        assert!(character.has_armor_stealth_disadvantage());
+    }
+
+    #[test]
+    fn attunement_is_limited_to_three_items() {
+        let mut character = create_sample_character();
+        for name in [
+            "Ring of Protection",
+            "Cloak of Elvenkind",
+            "Amulet of Health",
+            "Ring of Warmth",
+        ] {
+            character.add_item(magic_ring(name), 1).unwrap();
+        }
+        carry_weapon(&mut character, "Dagger", 1);
+
+        character.attune("Ring of Protection").unwrap();
+        assert_eq!(
+            character.attune("Ring of Protection"),
+            Err(EquipmentError::AlreadyAttuned(String::from(
+                "Ring of Protection"
+            )))
+        );
+        character.attune("Cloak of Elvenkind").unwrap();
+        character.attune("Amulet of Health").unwrap();
+        assert_eq!(
+            character.attune("Ring of Warmth"),
+            Err(EquipmentError::AttunementLimit)
+        );
+        assert_eq!(
+            character.attune("Dagger"),
+            Err(EquipmentError::NotAttunable(String::from("Dagger")))
+        );
+
+        character.end_attunement("Amulet of Health").unwrap();
+        character.attune("Ring of Warmth").unwrap();
+        assert!(character.is_attuned_to("Ring of Warmth"));
+        assert_eq!(
+            character.end_attunement("Amulet of Health"),
+            Err(EquipmentError::NotAttuned(String::from("Amulet of Health")))
+        );
+        assert_eq!(
+            character.equip("Ring of Warmth", EquipmentSlot::Worn),
+            Ok(EquipTime::Action)
+        );
+    }
+}
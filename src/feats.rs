@@ -0,0 +1,463 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::class_progression::ClassFeature;
+use crate::{Ability, AbilityScoreIncrease, ArmorCategory, Character, ClassType, Trait};
+
+pub const ABILITY_SCORE_IMPROVEMENT: &str = "Ability Score Improvement";
+// Neither an Ability Score Improvement nor a feat can raise a score above 20.
+pub const ABILITY_SCORE_IMPROVEMENT_MAX: u8 = 20;
+const ABILITY_SCORE_IMPROVEMENT_SINGLE: u8 = 2;
+const ABILITY_SCORE_IMPROVEMENT_SPLIT: u8 = 1;
+
+const SPELLCASTING_FEATURES: [&str; 2] = ["Spellcasting", "Pact Magic"];
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum FeatPrerequisite {
+    // Met when any of the abilities reaches the minimum.
+    AbilityScore {
+        abilities: Vec<Ability>,
+        minimum: u8,
+    },
+    ArmorProficiency(ArmorCategory),
+    Spellcasting,
+}
+
+impl FeatPrerequisite {
+    pub fn is_met_by(&self, character: &Character) -> bool {
+        match self {
+            FeatPrerequisite::AbilityScore { abilities, minimum } => abilities
+                .iter()
+                .any(|&ability| character.ability_scores[ability].score >= *minimum),
+            FeatPrerequisite::ArmorProficiency(category) => {
+                character.has_armor_proficiency(*category)
+            }
+            FeatPrerequisite::Spellcasting => SPELLCASTING_FEATURES
+                .iter()
+                .any(|feature| character.has_class_feature(feature)),
+        }
+    }
+}
+
+impl fmt::Display for FeatPrerequisite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeatPrerequisite::AbilityScore { abilities, minimum } => {
+                let abilities: Vec<String> = abilities
+                    .iter()
+                    .map(|ability| format!("{:?}", ability))
+                    .collect();
+                write!(f, "{} {} or higher", abilities.join(" or "), minimum)
+            }
+            FeatPrerequisite::ArmorProficiency(category) => {
+                write!(f, "proficiency with {:?}", category)
+            }
+            FeatPrerequisite::Spellcasting => write!(f, "the ability to cast at least one spell"),
+        }
+    }
+}
+
+// A feat's effects are traits, so they use the same modifiers as racial traits.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Feat {
+    pub name: String,
+    #[serde(default)]
+    pub prerequisites: Vec<FeatPrerequisite>,
+    #[serde(default)]
+    pub ability_score_increase: Vec<AbilityScoreIncrease>,
+    pub traits: Vec<Trait>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum AbilityScoreImprovementChoice {
+    IncreaseOne(Ability),
+    IncreaseTwo(Ability, Ability),
+    Feat(Feat),
+}
+
+// Records which class level granted the improvement, so that every
+// Ability Score Improvement feature is resolved exactly once.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AbilityScoreImprovement {
+    pub class_type: ClassType,
+    pub level: u32,
+    pub choice: AbilityScoreImprovementChoice,
+    // The increases that were actually applied, after the cap, so that losing
+    // the level can undo them.
+    #[serde(default)]
+    pub applied_increases: Vec<AbilityScoreIncrease>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AbilityScoreImprovementError {
+    NonePending,
+    SameAbilityTwice(Ability),
+    AboveMaximum(Ability, u8),
+    FeatAlreadyTaken(String),
+    PrerequisiteNotMet {
+        feat: String,
+        prerequisite: FeatPrerequisite,
+    },
+}
+
+impl fmt::Display for AbilityScoreImprovementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbilityScoreImprovementError::NonePending => {
+                write!(
+                    f,
+                    "the character has no Ability Score Improvement to resolve"
+                )
+            }
+            AbilityScoreImprovementError::SameAbilityTwice(ability) => write!(
+                f,
+                "{:?} was chosen twice, increase it by 2 instead",
+                ability
+            ),
+            AbilityScoreImprovementError::AboveMaximum(ability, score) => write!(
+                f,
+                "{:?} would increase to {}, above the maximum of {}",
+                ability, score, ABILITY_SCORE_IMPROVEMENT_MAX
+            ),
+            AbilityScoreImprovementError::FeatAlreadyTaken(feat) => {
+                write!(f, "the character already has the {} feat", feat)
+            }
+            AbilityScoreImprovementError::PrerequisiteNotMet { feat, prerequisite } => {
+                write!(f, "the {} feat requires {}", feat, prerequisite)
+            }
+        }
+    }
+}
+
+impl Character {
+    pub fn get_pending_ability_score_improvements(&self) -> Vec<&ClassFeature> {
+        self.class_features
+            .iter()
+            .filter(|class_feature| class_feature.name == ABILITY_SCORE_IMPROVEMENT)
+            .filter(|class_feature| {
+                !self.ability_score_improvements.iter().any(|improvement| {
+                    improvement.class_type == class_feature.class_type
+                        && improvement.level == class_feature.level
+                })
+            })
+            .collect()
+    }
+
+    pub fn has_feat(&self, name: &str) -> bool {
+        self.ability_score_improvements
+            .iter()
+            .any(|improvement| match &improvement.choice {
+                AbilityScoreImprovementChoice::Feat(feat) => feat.name == name,
+                _ => false,
+            })
+    }
+
+    // Resolves the earliest Ability Score Improvement the character hasn't
+    // used yet, either by raising ability scores or by taking a feat.
+    pub fn resolve_ability_score_improvement(
+        &mut self,
+        choice: AbilityScoreImprovementChoice,
+    ) -> Result<(), AbilityScoreImprovementError> {
+        let (class_type, level) = match self.get_pending_ability_score_improvements().first() {
+            Some(class_feature) => (class_feature.class_type, class_feature.level),
+            None => return Err(AbilityScoreImprovementError::NonePending),
+        };
+
+        let applied_increases = match &choice {
+            AbilityScoreImprovementChoice::IncreaseOne(ability) => {
+                self.check_ability_score_improvement(*ability, ABILITY_SCORE_IMPROVEMENT_SINGLE)?;
+                vec![self.apply_ability_score_increase(*ability, ABILITY_SCORE_IMPROVEMENT_SINGLE)]
+            }
+            AbilityScoreImprovementChoice::IncreaseTwo(first, second) => {
+                if first == second {
+                    return Err(AbilityScoreImprovementError::SameAbilityTwice(*first));
+                }
+                self.check_ability_score_improvement(*first, ABILITY_SCORE_IMPROVEMENT_SPLIT)?;
+                self.check_ability_score_improvement(*second, ABILITY_SCORE_IMPROVEMENT_SPLIT)?;
+                vec![
+                    self.apply_ability_score_increase(*first, ABILITY_SCORE_IMPROVEMENT_SPLIT),
+                    self.apply_ability_score_increase(*second, ABILITY_SCORE_IMPROVEMENT_SPLIT),
+                ]
+            }
+            AbilityScoreImprovementChoice::Feat(feat) => {
+                self.check_feat(feat)?;
+                self.traits.extend(feat.traits.iter().cloned());
+                feat.ability_score_increase
+                    .iter()
+                    .map(|increase| {
+                        self.apply_ability_score_increase(increase.ability, increase.value)
+                    })
+                    .collect()
+            }
+        };
+
+        self.ability_score_improvements
+            .push(AbilityScoreImprovement {
+                class_type,
+                level,
+                choice,
+                applied_increases,
+            });
+        self.refresh_derived_statistics();
+        Ok(())
+    }
+
+    fn check_ability_score_improvement(
+        &self,
+        ability: Ability,
+        amount: u8,
+    ) -> Result<(), AbilityScoreImprovementError> {
+        let score = self.ability_scores[ability].score + amount;
+        if score > ABILITY_SCORE_IMPROVEMENT_MAX {
+            return Err(AbilityScoreImprovementError::AboveMaximum(ability, score));
+        }
+
+        Ok(())
+    }
+
+    fn check_feat(&self, feat: &Feat) -> Result<(), AbilityScoreImprovementError> {
+        if self.has_feat(&feat.name) {
+            return Err(AbilityScoreImprovementError::FeatAlreadyTaken(
+                feat.name.clone(),
+            ));
+        }
+
+        match feat
+            .prerequisites
+            .iter()
+            .find(|prerequisite| !prerequisite.is_met_by(self))
+        {
+            Some(prerequisite) => Err(AbilityScoreImprovementError::PrerequisiteNotMet {
+                feat: feat.name.clone(),
+                prerequisite: prerequisite.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // Feats raise scores "to a maximum of 20", so their increases stop at the cap.
+    fn apply_ability_score_increase(
+        &mut self,
+        ability: Ability,
+        amount: u8,
+    ) -> AbilityScoreIncrease {
+        let score = self.ability_scores[ability].score;
+        let value = amount.min(ABILITY_SCORE_IMPROVEMENT_MAX.saturating_sub(score));
+        self.ability_scores
+            .increase_ability_score(ability, value)
+            .expect("Ability Score Improvements stay below the ability score maximum");
+        AbilityScoreIncrease { ability, value }
+    }
+
+    // Undoes an improvement when the level that granted it is lost, taking away
+    // the ability score increases and the feat's traits.
+    pub(crate) fn revert_ability_score_improvement(
+        &mut self,
+        improvement: &AbilityScoreImprovement,
+    ) {
+        for increase in improvement.applied_increases.iter() {
+            self.ability_scores
+                .decrease_ability_score(increase.ability, increase.value)
+                .expect("Ability Score Improvements are undone from the score they raised");
+        }
+
+        if let AbilityScoreImprovementChoice::Feat(feat) = &improvement.choice {
+            for feat_trait in feat.traits.iter() {
+                if let Some(index) = self
+                    .traits
+                    .iter()
+                    .position(|character_trait| character_trait.name == feat_trait.name)
+                {
+                    self.traits.remove(index);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgrounds::BackgroundType;
+    use crate::multiclassing::MulticlassError;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::build_character;
+    use crate::{load_feats_from_file, RaceType};
+
+    fn build_fighter(experience_points: u64) -> Character {
+        let mut character = build_character(
+            RaceType::Human,
+            ClassType::Fighter,
+            &[
+                (Ability::Strength, 15),
+                (Ability::Dexterity, 13),
+                (Ability::Constitution, 14),
+                (Ability::Intelligence, 8),
+                (Ability::Wisdom, 12),
+                (Ability::Charisma, 10),
+            ],
+            BackgroundType::Soldier,
+        );
+        character.experience_points = experience_points;
+        character
+    }
+
+    fn find_feat(name: &str) -> Feat {
+        load_feats_from_file("data/feats.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|feat| feat.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn level_up_waits_for_the_ability_score_improvement() {
+        let mut character = build_fighter(6500);
+        let mut roller = ScriptedRoller::new(vec![]);
+        character.gain_level(ClassType::Fighter, &mut roller);
+        assert_eq!(character.get_class_level(ClassType::Fighter), 4);
+        assert_eq!(
+            character.level_up_in(ClassType::Fighter, &mut roller),
+            Err(MulticlassError::AbilityScoreImprovementPending {
+                class_type: ClassType::Fighter,
+                level: 4
+            })
+        );
+
+        // The human's +1 to every ability makes Strength 16 and Intelligence 9.
+        assert_eq!(
+            character.resolve_ability_score_improvement(
+                AbilityScoreImprovementChoice::IncreaseTwo(Ability::Strength, Ability::Strength)
+            ),
+            Err(AbilityScoreImprovementError::SameAbilityTwice(
+                Ability::Strength
+            ))
+        );
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::IncreaseTwo(
+                Ability::Strength,
+                Ability::Intelligence,
+            ))
+            .unwrap();
+        assert_eq!(character.ability_scores[Ability::Strength].score, 17);
+        assert_eq!(character.ability_scores[Ability::Intelligence].modifier, 0);
+        assert!(character.find_stale_statistics().is_empty());
+        assert_eq!(
+            character.resolve_ability_score_improvement(
+                AbilityScoreImprovementChoice::IncreaseOne(Ability::Strength)
+            ),
+            Err(AbilityScoreImprovementError::NonePending)
+        );
+
+        character.gain_level(ClassType::Fighter, &mut roller);
+        assert_eq!(character.get_class_level(ClassType::Fighter), 5);
+    }
+
+    #[test]
+    fn ability_score_improvements_stop_at_twenty() {
+        let mut character = build_fighter(2700);
+        character.gain_level(ClassType::Fighter, &mut ScriptedRoller::new(vec![]));
+        character
+            .ability_scores
+            .increase_ability_score(Ability::Strength, 3)
+            .unwrap();
+
+        let error = character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::IncreaseOne(
+                Ability::Strength,
+            ))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            AbilityScoreImprovementError::AboveMaximum(Ability::Strength, 21)
+        );
+        assert_eq!(
+            error.to_string(),
+            "Strength would increase to 21, above the maximum of 20"
+        );
+
+        // A feat's increase is capped instead of refused.
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::Feat(find_feat(
+                "Heavy Armor Master",
+            )))
+            .unwrap();
+        assert_eq!(character.ability_scores[Ability::Strength].score, 20);
+        assert!(character.has_feat("Heavy Armor Master"));
+    }
+
+    #[test]
+    fn feats_check_prerequisites_and_apply_modifiers() {
+        let mut character = build_fighter(2700);
+        character.gain_level(ClassType::Fighter, &mut ScriptedRoller::new(vec![]));
+
+        let error = character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::Feat(find_feat(
+                "War Caster",
+            )))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the War Caster feat requires the ability to cast at least one spell"
+        );
+
+        let max_hit_points = character.get_max_hit_points();
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::Feat(find_feat(
+                "Tough",
+            )))
+            .unwrap();
+        assert_eq!(character.get_max_hit_points(), max_hit_points + 8);
+
+        assert_eq!(
+            FeatPrerequisite::AbilityScore {
+                abilities: vec![Ability::Intelligence, Ability::Wisdom],
+                minimum: 13
+            }
+            .to_string(),
+            "Intelligence or Wisdom 13 or higher"
+        );
+    }
+
+    #[test]
+    fn feats_change_speed_initiative_and_hit_dice() {
+        let mut character = build_fighter(14000);
+        let mut roller = ScriptedRoller::new(vec![]);
+        character.gain_level(ClassType::Fighter, &mut roller);
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::Feat(find_feat(
+                "Mobile",
+            )))
+            .unwrap();
+        assert_eq!(character.get_speed(), 40);
+        assert!(character.find_stale_statistics().is_empty());
+
+        character.gain_level(ClassType::Fighter, &mut roller);
+        assert_eq!(character.get_initiative_modifier(), 2);
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::Feat(find_feat(
+                "Alert",
+            )))
+            .unwrap();
+        assert_eq!(character.get_initiative_modifier(), 7);
+
+        character.experience_points = 34000;
+        character.gain_level(ClassType::Fighter, &mut roller);
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::Feat(find_feat(
+                "Durable",
+            )))
+            .unwrap();
+        // Durable raises Constitution to 16, so a hit die heals at least 2 * 3.
+        character.take_damage(20);
+        assert_eq!(
+            character.spend_hit_die(ClassType::Fighter, &mut ScriptedRoller::new(vec![1])),
+            Ok(6)
+        );
+        assert_eq!(
+            character.spend_hit_die(ClassType::Fighter, &mut ScriptedRoller::new(vec![9])),
+            Ok(12)
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dice::roll_die;
+use crate::roller::Roller;
+use crate::{Ability, Character, ClassType, HitPointEffect};
+
+const EXHAUSTION_HALVES_HIT_POINT_MAXIMUM: u8 = 4;
+
+// Current hit points are stored as the damage taken, so that changes to the
+// maximum (such as a new Constitution score) carry over to the current value.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct HitPoints {
+    pub damage_taken: u16,
+    pub temporary: u16,
+    pub hit_dice_spent: Vec<HitDiceSpent>,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct HitDiceSpent {
+    pub class_type: ClassType,
+    pub spent: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DamageTaken {
+    pub temporary_hit_points_lost: u16,
+    pub hit_points_lost: u16,
+    // Damage left over after the character dropped to 0 hit points.
+    pub excess_damage: u16,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HitPointError {
+    MissingClass(ClassType),
+    NoHitDiceRemaining(ClassType),
+}
+
+impl fmt::Display for HitPointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HitPointError::MissingClass(class_type) => {
+                write!(f, "the character has no {:?} hit dice", class_type)
+            }
+            HitPointError::NoHitDiceRemaining(class_type) => {
+                write!(f, "every {:?} hit die has already been spent", class_type)
+            }
+        }
+    }
+}
+
+impl Character {
+    // Every level adds its hit points plus the Constitution modifier, and always
+    // at least 1. Traits like Dwarven Toughness add a fixed amount per level, and
+    // the fourth level of exhaustion halves the total.
+    pub fn get_max_hit_points(&self) -> u16 {
+        let constitution_modifier = self.get_ability_modifier(Ability::Constitution) as i32;
+        let from_levels: i32 = self
+            .hit_points_gained
+            .iter()
+            .map(|gained| (gained.hit_points as i32 + constitution_modifier).max(1))
+            .sum();
+        let per_level_bonus: u16 = self
+            .get_hit_point_effects()
+            .filter_map(|effect| match effect {
+                HitPointEffect::PerLevel(bonus) => Some(bonus),
+                HitPointEffect::HitDieMinimum { .. } => None,
+            })
+            .sum();
+
+        let max_hit_points =
+            from_levels as u16 + per_level_bonus * self.hit_points_gained.len() as u16;
+        if self.get_exhaustion_level() >= EXHAUSTION_HALVES_HIT_POINT_MAXIMUM {
+            return max_hit_points / 2;
+        }
+        max_hit_points
+    }
+
+    pub fn get_current_hit_points(&self) -> u16 {
+        self.get_max_hit_points()
+            .saturating_sub(self.hit_points.damage_taken)
+    }
+
+    pub fn get_temporary_hit_points(&self) -> u16 {
+        self.hit_points.temporary
+    }
+
+    // Temporary hit points absorb damage first.
+    pub fn take_damage(&mut self, damage: u16) -> DamageTaken {
+        let temporary_hit_points_lost = damage.min(self.hit_points.temporary);
+        self.hit_points.temporary -= temporary_hit_points_lost;
+
+        let remaining_damage = damage - temporary_hit_points_lost;
+        let hit_points_lost = remaining_damage.min(self.get_current_hit_points());
+        self.hit_points.damage_taken += hit_points_lost;
+
+        DamageTaken {
+            temporary_hit_points_lost,
+            hit_points_lost,
+            excess_damage: remaining_damage - hit_points_lost,
+        }
+    }
+
+    // Returns the hit points regained, which can't go above the maximum. The dead
+    // can't be healed.
+    pub fn heal(&mut self, hit_points: u16) -> u16 {
+        if self.death_saving_throws.dead {
+            return 0;
+        }
+
+        let was_at_zero = self.get_current_hit_points() == 0;
+        let healed = hit_points.min(self.hit_points.damage_taken);
+        self.hit_points.damage_taken -= healed;
+        if was_at_zero && healed > 0 {
+            self.regain_consciousness();
+        }
+        healed
+    }
+
+    // Temporary hit points don't stack, so the character keeps whichever pool
+    // is larger. Returns whether the new temporary hit points were kept.
+    pub fn gain_temporary_hit_points(&mut self, hit_points: u16) -> bool {
+        if hit_points <= self.hit_points.temporary {
+            return false;
+        }
+
+        self.hit_points.temporary = hit_points;
+        true
+    }
+
+    // Characters have one hit die per class level.
+    pub fn get_hit_dice_remaining(&self, class_type: ClassType) -> u32 {
+        let spent = self
+            .hit_points
+            .hit_dice_spent
+            .iter()
+            .find(|hit_dice_spent| hit_dice_spent.class_type == class_type)
+            .map_or(0, |hit_dice_spent| hit_dice_spent.spent);
+
+        self.get_class_level(class_type).saturating_sub(spent)
+    }
+
+    fn get_hit_point_effects(&self) -> impl Iterator<Item = HitPointEffect> + '_ {
+        self.traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.hit_point_modifiers.iter())
+            .map(|hit_point_modifier| hit_point_modifier.value)
+    }
+
+    // Spends a hit die during a short rest, healing the roll plus the
+    // Constitution modifier, or at least the minimum from traits like Durable.
+    // Returns the hit points regained.
+    pub fn spend_hit_die(
+        &mut self,
+        class_type: ClassType,
+        roller: &mut dyn Roller,
+    ) -> Result<u16, HitPointError> {
+        let hit_dice = self
+            .class
+            .iter()
+            .find(|class| class.class_type == class_type)
+            .ok_or(HitPointError::MissingClass(class_type))?
+            .features
+            .hit_dice;
+        if self.get_hit_dice_remaining(class_type) == 0 {
+            return Err(HitPointError::NoHitDiceRemaining(class_type));
+        }
+
+        match self
+            .hit_points
+            .hit_dice_spent
+            .iter_mut()
+            .find(|hit_dice_spent| hit_dice_spent.class_type == class_type)
+        {
+            Some(hit_dice_spent) => hit_dice_spent.spent += 1,
+            None => self.hit_points.hit_dice_spent.push(HitDiceSpent {
+                class_type,
+                spent: 1,
+            }),
+        }
+
+        let roll = roll_die(hit_dice, roller) as i32;
+        let constitution_modifier = self.get_ability_modifier(Ability::Constitution) as i32;
+        let minimum = self
+            .get_hit_point_effects()
+            .filter_map(|effect| match effect {
+                HitPointEffect::HitDieMinimum {
+                    constitution_multiplier,
+                    minimum,
+                } => Some(
+                    (constitution_multiplier as i32 * constitution_modifier).max(minimum as i32),
+                ),
+                HitPointEffect::PerLevel(_) => None,
+            })
+            .fold(0, i32::max);
+        Ok(self.heal((roll + constitution_modifier).max(minimum) as u16))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgrounds::BackgroundType;
+    use crate::dying::DyingState;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::build_character;
+    use crate::{Condition, RaceType};
+
+    fn build_hill_dwarf_fighter() -> Character {
+        build_character(
+            RaceType::HillDwarf,
+            ClassType::Fighter,
+            &[
+                (Ability::Strength, 15),
+                (Ability::Dexterity, 10),
+                (Ability::Constitution, 14),
+                (Ability::Intelligence, 8),
+                (Ability::Wisdom, 12),
+                (Ability::Charisma, 13),
+            ],
+            BackgroundType::Soldier,
+        )
+    }
+
+    #[test]
+    fn maximum_follows_constitution_and_toughness() {
+        let mut character = build_hill_dwarf_fighter();
+        // 10 for the first level, 3 for a Constitution of 16 and 1 for Dwarven Toughness.
+        assert_eq!(character.get_max_hit_points(), 14);
+
+        character.experience_points = 900;
+        character.roll_hit_points = true;
+        character.gain_level(ClassType::Fighter, &mut ScriptedRoller::new(vec![1, 8]));
+        assert_eq!(character.get_max_hit_points(), 14 + 5 + 12);
+
+        character.take_damage(5);
+        character
+            .ability_scores
+            .increase_ability_score(Ability::Constitution, 2)
+            .unwrap();
+        character.refresh_derived_statistics();
+        assert_eq!(character.get_max_hit_points(), 34);
+        assert_eq!(character.get_current_hit_points(), 29);
+    }
+
+    #[test]
+    fn a_lower_maximum_can_drop_the_character_to_zero() {
+        let mut character = build_hill_dwarf_fighter();
+        character.experience_points = 900;
+        character.roll_hit_points = true;
+        character.gain_level(ClassType::Fighter, &mut ScriptedRoller::new(vec![1, 8]));
+        character.take_damage(20);
+
+        character.change_exhaustion_level(4, "Forced march");
+        assert_eq!(character.get_max_hit_points(), 15);
+        assert_eq!(character.hit_points.damage_taken, 15);
+        assert!(character.has_condition(Condition::Unconscious));
+        assert_eq!(
+            character.get_dying_state(),
+            DyingState::Dying {
+                successes: 0,
+                failures: 0
+            }
+        );
+
+        assert_eq!(character.heal(1), 1);
+        character.change_exhaustion_level(-4, "Long rest");
+        assert_eq!(character.get_current_hit_points(), 17);
+        character.lose_level().unwrap();
+        assert_eq!(character.get_current_hit_points(), 5);
+        assert_eq!(character.get_dying_state(), DyingState::Conscious);
+        character.lose_level().unwrap();
+        assert_eq!(character.get_current_hit_points(), 0);
+        assert!(character.has_condition(Condition::Unconscious));
+    }
+
+    #[test]
+    fn temporary_hit_points_absorb_damage_and_do_not_stack() {
+        let mut character = build_hill_dwarf_fighter();
+        assert!(character.gain_temporary_hit_points(5));
+        assert!(!character.gain_temporary_hit_points(3));
+        assert_eq!(character.get_temporary_hit_points(), 5);
+
+        assert_eq!(
+            character.take_damage(8),
+            DamageTaken {
+                temporary_hit_points_lost: 5,
+                hit_points_lost: 3,
+                excess_damage: 0,
+            }
+        );
+        assert_eq!(character.get_current_hit_points(), 11);
+        assert_eq!(
+            character.take_damage(20),
+            DamageTaken {
+                temporary_hit_points_lost: 0,
+                hit_points_lost: 11,
+                excess_damage: 9,
+            }
+        );
+        assert_eq!(character.heal(30), 14);
+        assert_eq!(character.get_current_hit_points(), 14);
+    }
+
+    #[test]
+    fn short_rests_spend_hit_dice() {
+        let mut character = build_hill_dwarf_fighter();
+        character.take_damage(12);
+
+        let mut roller = ScriptedRoller::new(vec![6]);
+        assert_eq!(
+            character.spend_hit_die(ClassType::Fighter, &mut roller),
+            Ok(9)
+        );
+        assert_eq!(character.get_current_hit_points(), 11);
+        assert_eq!(character.get_hit_dice_remaining(ClassType::Fighter), 0);
+        assert_eq!(
+            character.spend_hit_die(ClassType::Fighter, &mut roller),
+            Err(HitPointError::NoHitDiceRemaining(ClassType::Fighter))
+        );
+        assert_eq!(
+            character.spend_hit_die(ClassType::Wizard, &mut roller),
+            Err(HitPointError::MissingClass(ClassType::Wizard))
+        );
+    }
+}
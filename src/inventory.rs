@@ -0,0 +1,618 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::armor_class::Shield;
+use crate::{coin, f32, Ability, Armor, Character, Item, Size, Wealth, Weapon};
+
+const CARRYING_CAPACITY_PER_STRENGTH: f32 = 15.0;
+const ENCUMBERED_PER_STRENGTH: f32 = 5.0;
+const HEAVILY_ENCUMBERED_PER_STRENGTH: f32 = 10.0;
+const ENCUMBERED_SPEED_PENALTY: i64 = 10;
+const HEAVILY_ENCUMBERED_SPEED_PENALTY: i64 = 20;
+const COINS_PER_POUND: f32 = 50.0;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Gear {
+    pub name: String,
+    pub cost: f32::Coin,
+    pub weight: f32,
+    #[serde(default)]
+    pub requires_attunement: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum InventoryItem {
+    Weapon(Weapon),
+    Armor(Armor),
+    Shield(Shield),
+    Gear(Gear),
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct InventoryEntry {
+    pub item: InventoryItem,
+    pub quantity: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Inventory {
+    pub items: Vec<InventoryEntry>,
+    #[serde(default)]
+    pub wealth: Wealth,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum CoinDenomination {
+    Copper,
+    Silver,
+    Electrum,
+    Gold,
+    Platinum,
+}
+
+// With the variant rules, carrying more than 5 times the Strength score makes a
+// character encumbered, and more than 10 times heavily encumbered.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Encumbrance {
+    Unencumbered,
+    Encumbered,
+    HeavilyEncumbered,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InventoryError {
+    ZeroQuantity,
+    OverCapacity {
+        weight: f32,
+        capacity: f32,
+    },
+    NotCarried(String),
+    NotEnoughCarried {
+        name: String,
+        carried: u32,
+    },
+    TooManyCarried {
+        name: String,
+        carried: u32,
+    },
+    NotEnoughCoins {
+        denomination: CoinDenomination,
+        carried: u32,
+    },
+    Equipped(String),
+    NameTaken(String),
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InventoryError::ZeroQuantity => write!(f, "the quantity has to be at least 1"),
+            InventoryError::OverCapacity { weight, capacity } => write!(
+                f,
+                "carrying {} lb. would exceed the carrying capacity of {} lb.",
+                weight, capacity
+            ),
+            InventoryError::NotCarried(name) => write!(f, "no {} is carried", name),
+            InventoryError::NotEnoughCarried { name, carried } => {
+                write!(f, "only {} {} carried", carried, name)
+            }
+            InventoryError::TooManyCarried { name, carried } => {
+                write!(
+                    f,
+                    "{} {} carried, which is as many as can be counted",
+                    carried, name
+                )
+            }
+            InventoryError::NotEnoughCoins {
+                denomination,
+                carried,
+            } => write!(f, "only {} {:?} coins carried", carried, denomination),
+            InventoryError::Equipped(name) => {
+                write!(f, "the {} has to be unequipped first", name)
+            }
+            InventoryError::NameTaken(name) => {
+                write!(f, "a different item named {} is already carried", name)
+            }
+        }
+    }
+}
+
+impl Item for Weapon {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_weight(&self) -> f32 {
+        self.weight
+    }
+
+    fn get_cost(&self) -> f32::Coin {
+        self.cost
+    }
+}
+
+// Magic armor and shields are named with their bonus, so that they can be told
+// apart from mundane ones.
+fn with_magic_bonus(name: String, magic_bonus: i16) -> String {
+    if magic_bonus == 0 {
+        name
+    } else {
+        format!("{} {:+}", name, magic_bonus)
+    }
+}
+
+impl Item for Armor {
+    fn get_name(&self) -> String {
+        with_magic_bonus(format!("{:?}", self.armor_type), self.magic_bonus)
+    }
+
+    fn get_weight(&self) -> f32 {
+        self.weight as f32
+    }
+
+    fn get_cost(&self) -> f32::Coin {
+        self.cost
+    }
+}
+
+impl Item for Shield {
+    fn get_name(&self) -> String {
+        with_magic_bonus(String::from("Shield"), self.magic_bonus)
+    }
+
+    fn get_weight(&self) -> f32 {
+        self.weight as f32
+    }
+
+    fn get_cost(&self) -> f32::Coin {
+        self.cost
+    }
+}
+
+impl Item for Gear {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_weight(&self) -> f32 {
+        self.weight
+    }
+
+    fn get_cost(&self) -> f32::Coin {
+        self.cost
+    }
+
+    fn requires_attunement(&self) -> bool {
+        self.requires_attunement
+    }
+}
+
+impl Item for InventoryItem {
+    fn get_name(&self) -> String {
+        match self {
+            InventoryItem::Weapon(weapon) => weapon.get_name(),
+            InventoryItem::Armor(armor) => armor.get_name(),
+            InventoryItem::Shield(shield) => shield.get_name(),
+            InventoryItem::Gear(gear) => gear.get_name(),
+        }
+    }
+
+    fn get_weight(&self) -> f32 {
+        match self {
+            InventoryItem::Weapon(weapon) => weapon.get_weight(),
+            InventoryItem::Armor(armor) => armor.get_weight(),
+            InventoryItem::Shield(shield) => shield.get_weight(),
+            InventoryItem::Gear(gear) => gear.get_weight(),
+        }
+    }
+
+    fn get_cost(&self) -> f32::Coin {
+        match self {
+            InventoryItem::Weapon(weapon) => weapon.get_cost(),
+            InventoryItem::Armor(armor) => armor.get_cost(),
+            InventoryItem::Shield(shield) => shield.get_cost(),
+            InventoryItem::Gear(gear) => gear.get_cost(),
+        }
+    }
+
+    fn requires_attunement(&self) -> bool {
+        match self {
+            InventoryItem::Weapon(weapon) => weapon.requires_attunement(),
+            InventoryItem::Armor(armor) => armor.requires_attunement(),
+            InventoryItem::Shield(shield) => shield.requires_attunement(),
+            InventoryItem::Gear(gear) => gear.requires_attunement(),
+        }
+    }
+}
+
+impl Default for Wealth {
+    fn default() -> Wealth {
+        Wealth {
+            copper: f32::Coin::new::<coin::copper>(0.0),
+            silver: f32::Coin::new::<coin::silver>(0.0),
+            electrum: f32::Coin::new::<coin::electrum>(0.0),
+            gold: f32::Coin::new::<coin::gold>(0.0),
+            platinum: f32::Coin::new::<coin::platinum>(0.0),
+        }
+    }
+}
+
+impl Wealth {
+    pub fn get_coins(&self, denomination: CoinDenomination) -> u32 {
+        let coins = match denomination {
+            CoinDenomination::Copper => self.copper.get::<coin::copper>(),
+            CoinDenomination::Silver => self.silver.get::<coin::silver>(),
+            CoinDenomination::Electrum => self.electrum.get::<coin::electrum>(),
+            CoinDenomination::Gold => self.gold.get::<coin::gold>(),
+            CoinDenomination::Platinum => self.platinum.get::<coin::platinum>(),
+        };
+        coins.round() as u32
+    }
+
+    fn set_coins(&mut self, denomination: CoinDenomination, coins: u32) {
+        let coins = coins as f32;
+        match denomination {
+            CoinDenomination::Copper => self.copper = f32::Coin::new::<coin::copper>(coins),
+            CoinDenomination::Silver => self.silver = f32::Coin::new::<coin::silver>(coins),
+            CoinDenomination::Electrum => self.electrum = f32::Coin::new::<coin::electrum>(coins),
+            CoinDenomination::Gold => self.gold = f32::Coin::new::<coin::gold>(coins),
+            CoinDenomination::Platinum => self.platinum = f32::Coin::new::<coin::platinum>(coins),
+        }
+    }
+
+    pub fn get_coin_count(&self) -> u32 {
+        [
+            CoinDenomination::Copper,
+            CoinDenomination::Silver,
+            CoinDenomination::Electrum,
+            CoinDenomination::Gold,
+            CoinDenomination::Platinum,
+        ]
+        .iter()
+        .map(|&denomination| self.get_coins(denomination))
+        .sum()
+    }
+}
+
+impl Inventory {
+    // Fifty coins weigh a pound.
+    pub fn get_weight(&self) -> f32 {
+        let items: f32 = self
+            .items
+            .iter()
+            .map(|entry| entry.item.get_weight() * entry.quantity as f32)
+            .sum();
+        items + self.wealth.get_coin_count() as f32 / COINS_PER_POUND
+    }
+
+    pub fn get_quantity(&self, name: &str) -> u32 {
+        self.items
+            .iter()
+            .filter(|entry| entry.item.get_name() == name)
+            .map(|entry| entry.quantity)
+            .sum()
+    }
+}
+
+impl Size {
+    // Larger creatures can carry more: each size above Medium doubles the
+    // capacity.
+    pub fn get_carrying_capacity_multiplier(&self) -> f32 {
+        match self {
+            Size::Small | Size::Medium => 1.0,
+            Size::Large => 2.0,
+            Size::Huge => 4.0,
+        }
+    }
+}
+
+impl Character {
+    fn get_weight_limit(&self, per_strength: f32) -> f32 {
+        self.ability_scores[Ability::Strength].score as f32
+            * per_strength
+            * self.get_size().get_carrying_capacity_multiplier()
+    }
+
+    pub fn get_carrying_capacity(&self) -> f32 {
+        self.get_weight_limit(CARRYING_CAPACITY_PER_STRENGTH)
+    }
+
+    pub fn get_carried_weight(&self) -> f32 {
+        self.inventory.get_weight()
+    }
+
+    // Without the variant rules a character is never encumbered below their
+    // carrying capacity.
+    pub fn get_encumbrance(&self) -> Encumbrance {
+        if !self.variant_encumbrance {
+            return Encumbrance::Unencumbered;
+        }
+        let weight = self.get_carried_weight();
+        if weight > self.get_weight_limit(HEAVILY_ENCUMBERED_PER_STRENGTH) {
+            Encumbrance::HeavilyEncumbered
+        } else if weight > self.get_weight_limit(ENCUMBERED_PER_STRENGTH) {
+            Encumbrance::Encumbered
+        } else {
+            Encumbrance::Unencumbered
+        }
+    }
+
+    pub fn get_encumbrance_speed_penalty(&self) -> i64 {
+        match self.get_encumbrance() {
+            Encumbrance::Unencumbered => 0,
+            Encumbrance::Encumbered => ENCUMBERED_SPEED_PENALTY,
+            Encumbrance::HeavilyEncumbered => HEAVILY_ENCUMBERED_SPEED_PENALTY,
+        }
+    }
+
+    fn check_capacity(&self, added_weight: f32) -> Result<(), InventoryError> {
+        let weight = self.get_carried_weight() + added_weight;
+        let capacity = self.get_carrying_capacity();
+        if weight > capacity {
+            return Err(InventoryError::OverCapacity { weight, capacity });
+        }
+        Ok(())
+    }
+
+    // Identical items are stacked into a single entry. Items are equipped and
+    // removed by name, so a different item can't share the name of a carried
+    // one. Weightless items never reach the carrying capacity, so the stack
+    // size is checked as well.
+    pub fn add_item(&mut self, item: InventoryItem, quantity: u32) -> Result<(), InventoryError> {
+        if quantity == 0 {
+            return Err(InventoryError::ZeroQuantity);
+        }
+        let name = item.get_name();
+        let entry = self
+            .inventory
+            .items
+            .iter()
+            .position(|entry| entry.item.get_name() == name);
+        if entry.is_some_and(|index| self.inventory.items[index].item != item) {
+            return Err(InventoryError::NameTaken(name));
+        }
+        self.check_capacity(item.get_weight() * quantity as f32)?;
+
+        match entry.map(|index| &mut self.inventory.items[index]) {
+            Some(entry) => {
+                let carried = entry.quantity;
+                entry.quantity = carried
+                    .checked_add(quantity)
+                    .ok_or(InventoryError::TooManyCarried { name, carried })?;
+            }
+            None => self.inventory.items.push(InventoryEntry { item, quantity }),
+        }
+        self.refresh_derived_statistics();
+        Ok(())
+    }
+
+    // Returns the removed item.
+    pub fn remove_item(
+        &mut self,
+        name: &str,
+        quantity: u32,
+    ) -> Result<InventoryItem, InventoryError> {
+        if quantity == 0 {
+            return Err(InventoryError::ZeroQuantity);
+        }
+        let index = self
+            .inventory
+            .items
+            .iter()
+            .position(|entry| entry.item.get_name() == name)
+            .ok_or_else(|| InventoryError::NotCarried(String::from(name)))?;
+        let equipped = self.get_equipped_count(name);
+        let entry = &mut self.inventory.items[index];
+        if entry.quantity < quantity {
+            return Err(InventoryError::NotEnoughCarried {
+                name: String::from(name),
+                carried: entry.quantity,
+            });
+        }
+
+        if entry.quantity - quantity < equipped {
+            return Err(InventoryError::Equipped(String::from(name)));
+        }
+
+        entry.quantity -= quantity;
+        let item = entry.item.clone();
+        // Attunement ends when the character no longer has the item.
+        if entry.quantity == 0 {
+            self.inventory.items.remove(index);
+            self.equipment.attuned.retain(|attuned| attuned != name);
+        }
+        self.refresh_derived_statistics();
+        Ok(item)
+    }
+
+    pub fn add_coins(
+        &mut self,
+        denomination: CoinDenomination,
+        coins: u32,
+    ) -> Result<(), InventoryError> {
+        if coins == 0 {
+            return Err(InventoryError::ZeroQuantity);
+        }
+        self.check_capacity(coins as f32 / COINS_PER_POUND)?;
+        let carried = self.inventory.wealth.get_coins(denomination);
+        self.inventory
+            .wealth
+            .set_coins(denomination, carried + coins);
+        self.refresh_derived_statistics();
+        Ok(())
+    }
+
+    pub fn remove_coins(
+        &mut self,
+        denomination: CoinDenomination,
+        coins: u32,
+    ) -> Result<(), InventoryError> {
+        if coins == 0 {
+            return Err(InventoryError::ZeroQuantity);
+        }
+        let carried = self.inventory.wealth.get_coins(denomination);
+        if carried < coins {
+            return Err(InventoryError::NotEnoughCoins {
+                denomination,
+                carried,
+            });
+        }
+        self.inventory
+            .wealth
+            .set_coins(denomination, carried - coins);
+        self.refresh_derived_statistics();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_sample_character;
+    use crate::{load_armor_from_file, load_weapons_from_file};
+
+    fn rope() -> InventoryItem {
+        InventoryItem::Gear(Gear {
+            name: String::from("Rope, hempen (50 feet)"),
+            cost: f32::Coin::new::<coin::gold>(1.0),
+            weight: 10.0,
+            requires_attunement: false,
+        })
+    }
+
+    #[test]
+    fn items_stack_and_are_weighed() {
+        let mut character = create_sample_character();
+        let dagger = load_weapons_from_file("data/weapons.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|weapon| weapon.name == "Dagger")
+            .unwrap();
+        let leather = load_armor_from_file("data/armor.yaml").unwrap().remove(0);
+
+        character
+            .add_item(InventoryItem::Weapon(dagger.clone()), 2)
+            .unwrap();
+        character
+            .add_item(InventoryItem::Weapon(dagger), 1)
+            .unwrap();
+        character
+            .add_item(InventoryItem::Armor(leather), 1)
+            .unwrap();
+        character.add_coins(CoinDenomination::Gold, 100).unwrap();
+        assert_eq!(character.inventory.get_quantity("Dagger"), 3);
+        assert_eq!(character.inventory.items.len(), 2);
+        assert_eq!(character.get_carried_weight(), 3.0 + 8.0 + 2.0);
+
+        assert_eq!(
+            character.add_item(rope(), 0),
+            Err(InventoryError::ZeroQuantity)
+        );
+        assert_eq!(
+            character.remove_item("Dagger", 4),
+            Err(InventoryError::NotEnoughCarried {
+                name: String::from("Dagger"),
+                carried: 3
+            })
+        );
+        assert_eq!(
+            character.remove_item("Rope, hempen (50 feet)", 1),
+            Err(InventoryError::NotCarried(String::from(
+                "Rope, hempen (50 feet)"
+            )))
+        );
+        assert!(character.remove_item("Dagger", 3).is_ok());
+        assert_eq!(character.inventory.items.len(), 1);
+        assert_eq!(
+            character.remove_coins(CoinDenomination::Gold, 150),
+            Err(InventoryError::NotEnoughCoins {
+                denomination: CoinDenomination::Gold,
+                carried: 100
+            })
+        );
+        character.remove_coins(CoinDenomination::Gold, 50).unwrap();
+        assert_eq!(character.get_carried_weight(), 9.0);
+    }
+
+    #[test]
+    fn weightless_stacks_stop_at_the_largest_count() {
+        let mut character = create_sample_character();
+        let feather = InventoryItem::Gear(Gear {
+            name: String::from("Feather"),
+            cost: f32::Coin::new::<coin::copper>(0.0),
+            weight: 0.0,
+            requires_attunement: false,
+        });
+        character.add_item(feather.clone(), u32::MAX - 1).unwrap();
+        assert_eq!(
+            character.add_item(feather.clone(), 2),
+            Err(InventoryError::TooManyCarried {
+                name: String::from("Feather"),
+                carried: u32::MAX - 1
+            })
+        );
+        assert_eq!(character.inventory.get_quantity("Feather"), u32::MAX - 1);
+        character.add_item(feather, 1).unwrap();
+        assert_eq!(character.inventory.get_quantity("Feather"), u32::MAX);
+    }
+
+    #[test]
+    fn carried_items_have_unique_names() {
+        let mut character = create_sample_character();
+        let leather = load_armor_from_file("data/armor.yaml").unwrap().remove(0);
+        let mut magic_leather = leather.clone();
+        magic_leather.magic_bonus = 1;
+        assert_eq!(leather.get_name(), "Leather");
+        assert_eq!(magic_leather.get_name(), "Leather +1");
+
+        character
+            .add_item(InventoryItem::Armor(leather), 1)
+            .unwrap();
+        character
+            .add_item(InventoryItem::Armor(magic_leather), 1)
+            .unwrap();
+        assert_eq!(character.inventory.items.len(), 2);
+        assert_eq!(character.inventory.get_quantity("Leather"), 1);
+
+        let mut heavy_rope = rope();
+        if let InventoryItem::Gear(gear) = &mut heavy_rope {
+            gear.weight = 20.0;
+        }
+        character.add_item(rope(), 1).unwrap();
+        assert_eq!(
+            character.add_item(heavy_rope, 1),
+            Err(InventoryError::NameTaken(String::from(
+                "Rope, hempen (50 feet)"
+            )))
+        );
+        assert_eq!(character.inventory.items.len(), 3);
+    }
+
+    #[test]
+    fn variant_encumbrance_reduces_speed() {
+        let mut character = create_sample_character();
+        let strength = character.ability_scores[Ability::Strength].score as f32;
+        let speed = character.get_speed();
+        assert_eq!(character.get_carrying_capacity(), strength * 15.0);
+
+        let ropes = (strength * 5.0 / 10.0) as u32 + 1;
+        character.add_item(rope(), ropes).unwrap();
+        assert_eq!(character.get_encumbrance(), Encumbrance::Unencumbered);
+
+        character.variant_encumbrance = true;
+        character.refresh_derived_statistics();
+        assert_eq!(character.get_encumbrance(), Encumbrance::Encumbered);
+        assert_eq!(character.speed, speed - 10);
+
+        character.add_item(rope(), ropes).unwrap();
+        assert_eq!(character.get_encumbrance(), Encumbrance::HeavilyEncumbered);
+        assert_eq!(character.speed, speed - 20);
+
+        let remaining = ((strength * 15.0 - character.get_carried_weight()) / 10.0) as u32 + 1;
+        assert!(matches!(
+            character.add_item(rope(), remaining),
+            Err(InventoryError::OverCapacity { .. })
+        ));
+    }
+}
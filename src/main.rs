@@ -7,18 +7,85 @@ extern crate uom;
 
 use uom::fmt::DisplayStyle::Abbreviation;
 
-use std::cmp;
 use std::fmt::Debug;
 use std::fs::OpenOptions;
 use std::io;
 use std::ops::Index;
 use std::path::Path;
+use std::str::FromStr;
 
 use structopt::StructOpt;
 
 use serde::{Deserialize, Serialize};
 
-use rand::distributions::{Distribution, Uniform};
+// The rules model is only partly driven by the CLI so far. The rest of it is
+// exercised by the tests, which still report anything that is never used.
+#[cfg_attr(not(test), allow(dead_code))]
+mod ability_scores;
+#[cfg_attr(not(test), allow(dead_code))]
+mod advancement;
+#[cfg_attr(not(test), allow(dead_code))]
+mod armor_class;
+#[cfg_attr(not(test), allow(dead_code))]
+mod backgrounds;
+#[cfg_attr(not(test), allow(dead_code))]
+mod character_builder;
+#[cfg_attr(not(test), allow(dead_code))]
+mod class_progression;
+#[cfg_attr(not(test), allow(dead_code))]
+mod conditions;
+#[cfg_attr(not(test), allow(dead_code))]
+mod d20;
+#[cfg_attr(not(test), allow(dead_code))]
+mod derived_statistics;
+#[cfg_attr(not(test), allow(dead_code))]
+mod dice;
+#[cfg_attr(not(test), allow(dead_code))]
+mod dying;
+#[cfg_attr(not(test), allow(dead_code))]
+mod equipment;
+#[cfg_attr(not(test), allow(dead_code))]
+mod feats;
+#[cfg_attr(not(test), allow(dead_code))]
+mod hit_points;
+#[cfg_attr(not(test), allow(dead_code))]
+mod inventory;
+#[cfg_attr(not(test), allow(dead_code))]
+mod multiclassing;
+#[cfg_attr(not(test), allow(dead_code))]
+mod probability;
+#[cfg_attr(not(test), allow(dead_code))]
+mod races;
+#[cfg_attr(not(test), allow(dead_code))]
+mod roll_rules;
+#[cfg_attr(not(test), allow(dead_code))]
+mod roller;
+#[cfg_attr(not(test), allow(dead_code))]
+mod skills;
+#[cfg_attr(not(test), allow(dead_code))]
+mod weapon_attacks;
+
+use advancement::Advancement;
+use backgrounds::{Background, CharacterBackground};
+use character_builder::CreationChoice;
+use class_progression::{ClassFeature, ClassLevel, HitPointsGained};
+use conditions::{ActiveCondition, Condition};
+use d20::{
+    get_death_saving_throw_outcome, roll_attack, roll_d20_with_rules, AttackRoll, D20Roll, D20Test,
+    DeathSaveOutcome, RollMode, DEFAULT_CRITICAL_THRESHOLD,
+};
+use dice::{DiceExpression, DiceRoll, DiceTerm, Die, TermValue};
+use dying::DeathSavingThrows;
+use equipment::Equipment;
+use feats::{AbilityScoreImprovement, Feat};
+use hit_points::HitPoints;
+use inventory::Inventory;
+use multiclassing::SkillChoice;
+use probability::ProbabilityDistribution;
+use roll_rules::{DamageSource, RollRule, RollRules};
+use roller::{RandomRoller, Roller, SeededRoller};
+use skills::SkillProficiency;
+use weapon_attacks::{WeaponAttack, WeaponAttackError, WeaponAttackReport, WeaponProficiency};
 
 // https://docs.rs/crate/uom/0.30.0/source/examples/mks.rs
 
@@ -55,7 +122,7 @@ mod f32 {
     Q!(self::mks, f32);
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum RaceType {
     Dwarf,
     HillDwarf,
@@ -70,28 +137,57 @@ enum RaceType {
     Tiefling,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 struct Race {
     race_type: RaceType,
+    #[serde(default)]
+    parent: Option<RaceType>,
     racial_traits: RacialTraits,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 struct RacialTraits {
     age: u64,
     alignment: Alignment,
     size: Size,
     speed: u16,
     languages: Vec<Language>,
+    #[serde(default)]
+    ability_score_increase: Vec<AbilityScoreIncrease>,
+    #[serde(default)]
+    darkvision: Option<u16>,
     traits: Vec<Trait>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct AbilityScoreIncrease {
+    ability: Ability,
+    value: u8,
+}
+
+// A subrace only lists what it adds on top of its parent race.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(not(test), allow(dead_code))]
 struct Subrace {
-    parent: Race,
+    race_type: RaceType,
+    parent: RaceType,
+    #[serde(default)]
+    ability_score_increase: Vec<AbilityScoreIncrease>,
+    #[serde(default)]
+    languages: Vec<Language>,
+    #[serde(default)]
+    traits: Vec<Trait>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(not(test), allow(dead_code))]
+struct RaceCatalogue {
+    races: Vec<Race>,
+    #[serde(default)]
+    subraces: Vec<Subrace>,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum Alignment {
     LawfulGood,
     NeutralGood,
@@ -105,14 +201,14 @@ enum Alignment {
     Unaligned,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum Size {
     Small,
     Medium,
     Large,
     Huge,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum Language {
     Common,
     Dwarvish,
@@ -131,10 +227,8 @@ enum Language {
     Sylvan,
     Undercommmon,
 }
-#[derive(Serialize, Deserialize, Debug)]
-enum Background {}
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum ClassType {
     Barbarian,
     Bard,
@@ -150,18 +244,7 @@ enum ClassType {
     Wizard,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
-struct Die {
-    min: u16,
-    max: u16,
-}
-
-fn roll_die(die: Die) -> u16 {
-    let mut rng = rand::thread_rng();
-    let die_range = Uniform::from(die.min..die.max);
-    die_range.sample(&mut rng)
-}
-
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum DamageType {
     Acid,
     Bludgeoning,
@@ -178,65 +261,115 @@ enum DamageType {
     Thunder,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+impl FromStr for DamageType {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "acid" => Ok(DamageType::Acid),
+            "bludgeoning" => Ok(DamageType::Bludgeoning),
+            "cold" => Ok(DamageType::Cold),
+            "fire" => Ok(DamageType::Fire),
+            "force" => Ok(DamageType::Force),
+            "lightning" => Ok(DamageType::Lightning),
+            "necrotic" => Ok(DamageType::Necrotic),
+            "piercing" => Ok(DamageType::Piercing),
+            "poison" => Ok(DamageType::Poison),
+            "psychic" => Ok(DamageType::Psychic),
+            "radiant" => Ok(DamageType::Radiant),
+            "slashing" => Ok(DamageType::Slashing),
+            "thunder" => Ok(DamageType::Thunder),
+            _ => Err(format!("Unknown damage type: {}", name)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 struct ClassFeatures {
     hit_dice: Die,
     hit_points_starting: u16,
     hit_points_from_level: Die,
     weapon_proficiency_modifiers: Vec<WeaponProficiencyModifier>,
     armor_proficiency_modifiers: Vec<ArmorProficiencyModifier>,
+    #[serde(default)]
+    saving_throw_proficiencies: Vec<Ability>,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 struct Class {
     class_type: ClassType,
+    #[serde(default = "default_class_level")]
+    level: u32,
     features: ClassFeatures,
+    #[serde(default)]
+    levels: Vec<ClassLevel>,
 }
 
-trait Item {}
+fn default_class_level() -> u32 {
+    EFFECTIVE_LEVEL_MIN
+}
+
+// Anything a character can carry in their inventory. Weight is in pounds.
+trait Item {
+    fn get_name(&self) -> String;
+    fn get_weight(&self) -> f32;
+    fn get_cost(&self) -> f32::Coin;
 
+    fn requires_attunement(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 struct Weapon {
-    name: &'static str,
-    cost: u32,
-    damage: DamageRange,
+    name: String,
+    cost: f32::Coin,
+    // Weapons such as the net deal no damage.
+    damage: Option<WeaponDamage>,
     weapon_type: WeaponType,
     category: WeaponCategory,
+    weight: f32,
+    #[serde(default)]
     properties: Vec<WeaponProperty>,
 }
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct WeaponDamage {
+    dice: DiceExpression,
+    damage_type: DamageType,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum WeaponType {
     Melee,
     Ranged,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum WeaponCategory {
     Shields,
     SimpleWeapons,
     MartialWeapons,
 }
 
+// The range applies to weapons with the Ammunition or Thrown property, and the
+// versatile dice to attacks made with two hands.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum WeaponProperty {
     Ammunition,
     Finesse,
     Heavy,
     Light,
     Loading,
-    Range,
+    Range { normal: u32, long: u32 },
     Reach,
     Special,
     Thrown,
     TwoHanded,
-    Versatile,
+    Versatile(DiceExpression),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct DamageRange {
-    min: u32,
-    max: u32,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 struct Armor {
     armor_type: ArmorType,
     category: ArmorCategory,
@@ -245,25 +378,24 @@ struct Armor {
     weight: u32,
     ability_requirement: Option<AbilityScore>,
     has_stealth_disadvantage: bool,
+    #[serde(default)]
+    magic_bonus: i16,
 }
 
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum ArmorCategory {
     LightArmor,
     MediumArmor,
     HeavyArmor,
 }
 
+#[allow(dead_code)]
 enum Action {
     Stabilize,
 }
 
-enum Condition {
-    Stable,
-    Unconscious,
-    Incapacitated,
-}
-
+#[allow(dead_code)]
 enum AreaOfAffect {
     Cone,
     Line,
@@ -272,7 +404,7 @@ enum AreaOfAffect {
     Cube,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum ArmorType {
     Padded,
     Leather,
@@ -288,7 +420,9 @@ enum ArmorType {
     Plate,
 }
 
+#[cfg_attr(not(test), allow(dead_code))]
 const EFFECTIVE_ABILITY_SCORE_MIN: u8 = 0;
+#[cfg_attr(not(test), allow(dead_code))]
 const EFFECTIVE_ABILITY_SCORE_MAX: u8 = 30;
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 enum Ability {
@@ -300,6 +434,15 @@ enum Ability {
     Charisma,
 }
 
+const ABILITIES: [Ability; 6] = [
+    Ability::Strength,
+    Ability::Dexterity,
+    Ability::Constitution,
+    Ability::Intelligence,
+    Ability::Wisdom,
+    Ability::Charisma,
+];
+
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 struct AbilityScore {
     ability: Ability,
@@ -314,8 +457,7 @@ impl Index<Ability> for AbilitySet {
     type Output = AbilityScore;
 
     fn index(&self, index: Ability) -> &Self::Output {
-        &self
-            .0
+        self.0
             .iter()
             .find(|&ability_score| ability_score.ability == index)
             .expect("Ability score not found")
@@ -336,8 +478,7 @@ impl Index<Ability> for SavingThrowSet {
     type Output = SavingThrowScore;
 
     fn index(&self, index: Ability) -> &Self::Output {
-        &self
-            .0
+        self.0
             .iter()
             .find(|&saving_throw_score| saving_throw_score.saving_throw == index)
             .expect("Saving throw score not found")
@@ -366,31 +507,44 @@ enum Skill {
     Persuasion,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 struct SkillScore {
     skill: Skill,
     ability: Ability,
-    score: u8,
-    proficient: bool,
+    proficiency: SkillProficiency,
+    modifier: i8,
     advantage: bool,
     disadvantage: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl SkillScore {
+    fn roll_mode_sources(&self) -> Vec<RollMode> {
+        let mut sources = vec![];
+        if self.advantage {
+            sources.push(RollMode::Advantage);
+        }
+        if self.disadvantage {
+            sources.push(RollMode::Disadvantage);
+        }
+        sources
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 struct SkillSet([SkillScore; 18]);
 
 impl Index<Skill> for SkillSet {
     type Output = SkillScore;
 
     fn index(&self, index: Skill) -> &Self::Output {
-        &self
-            .0
+        self.0
             .iter()
             .find(|&skill_score| skill_score.skill == index)
             .expect("Skill score not found")
     }
 }
 
+#[allow(dead_code)]
 trait Entity {
     fn race(&self) -> Race;
     fn name(&self) -> String;
@@ -404,31 +558,182 @@ trait Entity {
     fn traits(&self) -> Option<Vec<Trait>>;
 }
 
+#[cfg_attr(not(test), allow(dead_code))]
 trait EntityRoll {
-    fn roll_ability_check(&self, ability: Ability) -> u16;
-    fn roll_saving_throw(&self, saving_throw: Ability) -> u16;
-    fn roll_weapon_attack(&self, weapon_attack: Weapon) -> u16;
+    fn roll_ability_check(
+        &self,
+        ability: Ability,
+        roll_mode: RollMode,
+        roller: &mut dyn Roller,
+    ) -> D20Roll;
+    fn roll_saving_throw(
+        &self,
+        saving_throw: Ability,
+        roll_mode: RollMode,
+        roller: &mut dyn Roller,
+    ) -> D20Roll;
+    fn roll_attack(
+        &self,
+        attack_bonus: i16,
+        roll_mode: RollMode,
+        roller: &mut dyn Roller,
+    ) -> AttackRoll;
+    fn roll_damage(
+        &self,
+        damage: &DiceExpression,
+        critical: bool,
+        source: DamageSource,
+        roller: &mut dyn Roller,
+    ) -> DiceRoll;
+    fn roll_death_saving_throw(&self, roller: &mut dyn Roller) -> (D20Roll, DeathSaveOutcome);
+    fn roll_weapon_attack(
+        &self,
+        weapon: &Weapon,
+        attack: WeaponAttack,
+        roller: &mut dyn Roller,
+    ) -> Result<WeaponAttackReport, WeaponAttackError>;
+    #[allow(dead_code)]
     fn roll_passive_check(&self, ability: Ability) -> u16;
 }
 
 impl EntityRoll for Character {
-    fn roll_ability_check(&self, ability: Ability) -> u16 {
-        roll_die(Die { min: 1, max: 20 }) + self.ability_scores[ability].modifier as u16
+    fn roll_ability_check(
+        &self,
+        ability: Ability,
+        roll_mode: RollMode,
+        roller: &mut dyn Roller,
+    ) -> D20Roll {
+        let roll_mode = self.get_roll_mode(D20Test::AbilityCheck(ability), roll_mode);
+        roll_d20_with_rules(
+            self.get_ability_modifier(ability) as i16,
+            roll_mode,
+            &self.get_roll_rules(),
+            roller,
+        )
     }
 
-    fn roll_saving_throw(&self, saving_throw: Ability) -> u16 {
-        roll_die(Die { min: 1, max: 20 }) + self.saving_throws[saving_throw].modifier as u16
+    fn roll_saving_throw(
+        &self,
+        saving_throw: Ability,
+        roll_mode: RollMode,
+        roller: &mut dyn Roller,
+    ) -> D20Roll {
+        let d20_test = D20Test::SavingThrow(saving_throw);
+        let roll_mode = self.get_roll_mode(d20_test, roll_mode);
+        let mut d20 = roll_d20_with_rules(
+            self.get_saving_throw_modifier(saving_throw) as i16,
+            roll_mode,
+            &self.get_roll_rules(),
+            roller,
+        );
+        d20.automatic_failure = self.fails_automatically(d20_test);
+        d20
     }
 
-    fn roll_weapon_attack(&self, weapon_attack: Weapon) -> u16 {
-        0
+    fn roll_attack(
+        &self,
+        attack_bonus: i16,
+        roll_mode: RollMode,
+        roller: &mut dyn Roller,
+    ) -> AttackRoll {
+        let roll_mode = self.get_roll_mode(D20Test::AttackRoll, roll_mode);
+        roll_attack(
+            attack_bonus,
+            roll_mode,
+            self.get_critical_threshold(),
+            &self.get_roll_rules(),
+            roller,
+        )
+    }
+
+    // Extra critical dice such as Brutal Critical only apply to melee attacks.
+    fn roll_damage(
+        &self,
+        damage: &DiceExpression,
+        critical: bool,
+        source: DamageSource,
+        roller: &mut dyn Roller,
+    ) -> DiceRoll {
+        let roll_rules = self.get_roll_rules();
+        if critical {
+            let extra_dice = match source {
+                DamageSource::MeleeAttack { .. } => self.get_critical_extra_dice(),
+                DamageSource::RangedAttack | DamageSource::Other => 0,
+            };
+            damage
+                .critical(extra_dice)
+                .roll_damage(&roll_rules, source, roller)
+        } else {
+            damage.roll_damage(&roll_rules, source, roller)
+        }
+    }
+
+    fn roll_death_saving_throw(&self, roller: &mut dyn Roller) -> (D20Roll, DeathSaveOutcome) {
+        let roll_mode = self.get_roll_mode(D20Test::DeathSavingThrow, RollMode::Normal);
+        let d20 = roll_d20_with_rules(0, roll_mode, &self.get_roll_rules(), roller);
+        let outcome = get_death_saving_throw_outcome(&d20);
+        (d20, outcome)
+    }
+
+    // Attacks at long range are made with disadvantage. Damage is only rolled on
+    // a hit.
+    fn roll_weapon_attack(
+        &self,
+        weapon: &Weapon,
+        attack: WeaponAttack,
+        roller: &mut dyn Roller,
+    ) -> Result<WeaponAttackReport, WeaponAttackError> {
+        if weapon.has_property(&WeaponProperty::TwoHanded) && !attack.two_handed {
+            return Err(WeaponAttackError::RequiresTwoHands(weapon.name.clone()));
+        }
+        let long_range = weapon.check_distance(attack.distance)?;
+        let range_roll_mode = if long_range {
+            RollMode::Disadvantage
+        } else {
+            RollMode::Normal
+        };
+
+        let ability = self.get_weapon_attack_ability(weapon);
+        let attack_bonus = self.get_weapon_attack_bonus(weapon, ability);
+        let roll_mode = self
+            .get_roll_mode_from_sources(D20Test::AttackRoll, [attack.roll_mode, range_roll_mode]);
+        let attack_roll = roll_attack(
+            attack_bonus,
+            roll_mode,
+            self.get_critical_threshold(),
+            &self.get_roll_rules(),
+            roller,
+        );
+        let hit = attack_roll.hits(attack.target_armor_class);
+        let damage = match self.get_weapon_damage(weapon, ability, attack.two_handed) {
+            Some(damage) if hit => Some(self.roll_damage(
+                &damage,
+                attack_roll.critical,
+                weapon.get_damage_source(attack),
+                roller,
+            )),
+            _ => None,
+        };
+
+        Ok(WeaponAttackReport {
+            weapon: weapon.name.clone(),
+            ability,
+            proficient: self.has_weapon_proficiency(weapon),
+            attack_bonus,
+            long_range,
+            attack: attack_roll,
+            hit,
+            damage,
+            damage_type: weapon.damage.as_ref().map(|damage| damage.damage_type),
+        })
     }
 
     fn roll_passive_check(&self, ability: Ability) -> u16 {
-        10 + self.ability_scores[ability].modifier as u16
+        (10 + self.get_ability_modifier(ability) as i16) as u16
     }
 }
 
+#[allow(dead_code)]
 enum EntityType {
     Creature,
     Character,
@@ -453,62 +758,196 @@ struct Character {
     saving_throws: SavingThrowSet,
     traits: Vec<Trait>,
     roll_hit_points: bool,
-}
-
+    #[serde(default)]
+    conditions: Vec<ActiveCondition>,
+    #[serde(default)]
+    creation_choices: Vec<CreationChoice>,
+    #[serde(default)]
+    background: Option<CharacterBackground>,
+    #[serde(default)]
+    class_features: Vec<ClassFeature>,
+    #[serde(default)]
+    hit_points_gained: Vec<HitPointsGained>,
+    #[serde(default)]
+    hit_points: HitPoints,
+    #[serde(default)]
+    death_saving_throws: DeathSavingThrows,
+    #[serde(default)]
+    skills: SkillSet,
+    #[serde(default)]
+    ability_score_improvements: Vec<AbilityScoreImprovement>,
+    #[serde(default)]
+    skill_choices: Vec<SkillChoice>,
+    #[serde(default)]
+    advancement: Advancement,
+    #[serde(default)]
+    inventory: Inventory,
+    #[serde(default)]
+    variant_encumbrance: bool,
+    #[serde(default)]
+    equipment: Equipment,
+}
+
+#[allow(dead_code)]
 const MIN_SPELL_LEVEL: u8 = 0;
+#[allow(dead_code)]
 const MAX_SPELL_LEVEL: u8 = 9;
+#[allow(dead_code)]
 struct Spell {
     level: u8,
 }
 
 impl Character {
+    #[allow(dead_code)]
     fn get_ability_score(&self, ability: Ability) -> AbilityScore {
         self.ability_scores[ability]
     }
 
+    // Gathers every advantage and disadvantage source that applies to a d20 roll,
+    // along with the mode requested by the caller.
+    fn get_roll_mode(&self, d20_test: D20Test, requested_roll_mode: RollMode) -> RollMode {
+        self.get_roll_mode_from_sources(d20_test, std::iter::once(requested_roll_mode))
+    }
+
+    fn get_roll_mode_from_sources<I: IntoIterator<Item = RollMode>>(
+        &self,
+        d20_test: D20Test,
+        requested_roll_modes: I,
+    ) -> RollMode {
+        let trait_sources = self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.roll_mode_modifiers.iter())
+            .filter(|roll_mode_modifier| roll_mode_modifier.value.d20_test == d20_test)
+            .map(|roll_mode_modifier| roll_mode_modifier.value.roll_mode);
+        let condition_sources = self
+            .conditions
+            .iter()
+            .filter_map(|active_condition| active_condition.condition.roll_mode_for(d20_test));
+
+        RollMode::from_sources(
+            requested_roll_modes
+                .into_iter()
+                .chain(trait_sources)
+                .chain(condition_sources),
+        )
+    }
+
+    // Collects the per-die roll rules (rerolls, minimums, exploding dice) granted by traits.
+    fn get_roll_rules(&self) -> RollRules {
+        let mut roll_rules = RollRules::new();
+        for roll_rule_modifier in self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.roll_rule_modifiers.iter())
+        {
+            roll_rules.add(&roll_rule_modifier.name, roll_rule_modifier.value);
+        }
+        roll_rules
+    }
+
+    fn get_trait_critical_modifiers(&self) -> impl Iterator<Item = &CriticalModifier> {
+        self.traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.critical_modifiers.iter())
+    }
+
+    // Critical modifiers listed on the class levels the character has reached,
+    // like Brutal Critical from the ninth Barbarian level.
+    fn get_class_critical_modifiers(&self) -> impl Iterator<Item = &CriticalModifier> {
+        self.class
+            .iter()
+            .flat_map(|class| {
+                class
+                    .levels
+                    .iter()
+                    .filter(move |entry| entry.level <= class.level)
+            })
+            .flat_map(|entry| entry.critical_modifiers.iter())
+    }
+
+    fn get_critical_modifiers(&self) -> impl Iterator<Item = &CriticalModifier> {
+        self.get_trait_critical_modifiers()
+            .chain(self.get_class_critical_modifiers())
+    }
+
+    // Features like Improved Critical let a character score a critical hit on a lower roll.
+    fn get_critical_threshold(&self) -> u16 {
+        self.get_critical_modifiers()
+            .filter_map(|critical_modifier| match critical_modifier.value {
+                CriticalEffect::ExpandedRange(threshold) => Some(threshold),
+                CriticalEffect::ExtraDice(_) => None,
+            })
+            .min()
+            .unwrap_or(DEFAULT_CRITICAL_THRESHOLD)
+    }
+
+    // Brutal Critical grows from 1 to 3 dice as the class levels go up, so only
+    // the largest class grant counts. Traits like Savage Attacks add their dice
+    // on top of it.
+    fn get_critical_extra_dice(&self) -> u16 {
+        let extra_dice = |critical_modifier: &CriticalModifier| match critical_modifier.value {
+            CriticalEffect::ExtraDice(dice) => Some(dice),
+            CriticalEffect::ExpandedRange(_) => None,
+        };
+        let from_class_levels = self
+            .get_class_critical_modifiers()
+            .filter_map(extra_dice)
+            .max()
+            .unwrap_or(0);
+        let from_traits: u16 = self
+            .get_trait_critical_modifiers()
+            .filter_map(extra_dice)
+            .sum();
+        from_class_levels + from_traits
+    }
+
     fn get_current_level(&self) -> u32 {
-        calculate_level_from_experience_points(self.experience_points)
+        match self.advancement {
+            Advancement::ExperiencePoints => {
+                calculate_level_from_experience_points(self.experience_points)
+            }
+            Advancement::Milestone(level) => level,
+        }
     }
 
     fn get_proficiency_bonus(&self) -> u16 {
-        calculate_proficiency_bonus_from_experience_points(self.experience_points)
+        calculate_proficiency_bonus_from_level(self.get_current_level())
     }
 
-    fn gain_level(&mut self) {
-        let target_level = calculate_level_from_experience_points(self.experience_points);
-        match target_level {
-            target_level if self.level < target_level => {
-                while self.level != target_level {
-                    self.roll_hit_points = true; //@TODO: Create map of health rolled at each level
-                    self.add_class_features_for_level();
-                    self.level = self.level + 1;
-                }
-            }
-            target_level if self.level > target_level => {
-                // Handle when someone is overleveled. Return resources?
-            }
-            target_level if self.level == target_level => {
-                // Do nothing if already at level
-            }
-            _ => {
-                // Take no action
+    // Brings the class levels in line with the experience points. Levels above
+    // the experience points, such as after an XP correction, are removed from the
+    // most recent first. Every pending level is spent on the given class; use
+    // level_up_in or multiclass_into to split the levels between classes.
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn gain_level(&mut self, class_type: ClassType, roller: &mut dyn Roller) {
+        while self.get_total_class_levels() > self.get_current_level() {
+            if self.remove_last_class_level().is_err() {
+                break;
             }
         }
-    }
 
-    fn add_class_features_for_level(&self) {
-        todo!();
+        while self.level_up_in(class_type, roller).is_ok() {}
     }
 }
 
+#[allow(dead_code)]
 const EFFECTIVE_SPELL_LEVEL_MIN: u8 = 0;
+#[allow(dead_code)]
 const EFFECTIVE_SPELL_LEVEL_MAX: u8 = 9;
+// The first count is the number of cantrips known, followed by the slots for
+// each spell level from 1st to 9th.
+#[allow(dead_code)]
 struct SpellSlotEntry {
     level: u32,
     spell_level_count: [u8; 10],
 }
 
 #[rustfmt::skip]
+#[allow(dead_code)]
 const WIZARD_SPELL_SLOTS_PER_SPELL_LEVEL: [SpellSlotEntry; 20] = [
     SpellSlotEntry {level: 1,  spell_level_count: [3,2,0,0,0,0,0,0,0,0] },
     SpellSlotEntry {level: 2,  spell_level_count: [3,3,0,0,0,0,0,0,0,0] },
@@ -532,7 +971,8 @@ const WIZARD_SPELL_SLOTS_PER_SPELL_LEVEL: [SpellSlotEntry; 20] = [
     SpellSlotEntry {level: 20, spell_level_count: [5,4,3,3,3,3,2,2,1,1] },
 ];
 
-fn get_number_of_spell_slots_for_spell_level(class: Class, level: u32, spell_level: u8) -> u8 {
+#[allow(dead_code)]
+fn get_number_of_spell_slots_for_spell_level(class: &Class, level: u32, spell_level: u8) -> u8 {
     match class.class_type {
         ClassType::Barbarian => todo!(),
         ClassType::Cleric => todo!(),
@@ -545,27 +985,25 @@ fn get_number_of_spell_slots_for_spell_level(class: Class, level: u32, spell_lev
         ClassType::Bard => todo!(),
         ClassType::Druid => todo!(),
         ClassType::Warlock => todo!(),
-        ClassType::Wizard => {
-            return find_spell_splots_for_spell_level(
-                WIZARD_SPELL_SLOTS_PER_SPELL_LEVEL,
-                level,
-                spell_level,
-            );
-        }
+        ClassType::Wizard => find_spell_splots_for_spell_level(
+            WIZARD_SPELL_SLOTS_PER_SPELL_LEVEL,
+            level,
+            spell_level,
+        ),
     }
 }
 
+#[allow(dead_code)]
 fn find_spell_splots_for_spell_level(
     spell_slots_per_spell_level_table: [SpellSlotEntry; 20],
     level: u32,
     spell_level: u8,
 ) -> u8 {
-    assert!(spell_level >= EFFECTIVE_SPELL_LEVEL_MIN && spell_level <= EFFECTIVE_SPELL_LEVEL_MAX);
+    assert!((EFFECTIVE_SPELL_LEVEL_MIN..=EFFECTIVE_SPELL_LEVEL_MAX).contains(&spell_level));
 
-    let indexed_spell_level = spell_level - 1;
-    for entry in WIZARD_SPELL_SLOTS_PER_SPELL_LEVEL.iter() {
+    for entry in spell_slots_per_spell_level_table.iter() {
         if level == entry.level {
-            return entry.spell_level_count[indexed_spell_level as usize];
+            return entry.spell_level_count[spell_level as usize];
         }
     }
 
@@ -580,11 +1018,12 @@ fn calculate_level_from_experience_points(experience_points: u64) -> u32 {
         }
     }
 
-    assert!(expected_level >= EFFECTIVE_LEVEL_MIN && expected_level <= EFFECTIVE_LEVEL_MAX);
+    assert!((EFFECTIVE_LEVEL_MIN..=EFFECTIVE_LEVEL_MAX).contains(&expected_level));
 
     expected_level
 }
 
+#[allow(dead_code)]
 fn calculate_experience_points_required_for_next_level(experience_points: u64) -> u64 {
     let mut required_experience_points = 0;
     for entry in CHARACTER_ADVANCEMENT_TABLE.iter() {
@@ -597,10 +1036,10 @@ fn calculate_experience_points_required_for_next_level(experience_points: u64) -
     required_experience_points
 }
 
-fn calculate_proficiency_bonus_from_experience_points(experience_points: u64) -> u16 {
+fn calculate_proficiency_bonus_from_level(level: u32) -> u16 {
     let default_proficiency_bonus = 1;
     for entry in CHARACTER_ADVANCEMENT_TABLE.iter() {
-        if experience_points >= entry.required_experience_points {
+        if entry.level == level {
             return entry.proficiency_bonus;
         }
     }
@@ -608,57 +1047,50 @@ fn calculate_proficiency_bonus_from_experience_points(experience_points: u64) ->
     default_proficiency_bonus
 }
 
-fn calculate_base_armor_class_for_character(armor: Armor, character: Character) -> u16 {
-    match armor.category {
-        ArmorCategory::LightArmor => {
-            let dexterity = character.ability_scores[Ability::Dexterity];
-            let armor_class_bonus = dexterity.modifier as u16;
-            return armor.base_armor_class + armor_class_bonus;
-        }
-        ArmorCategory::MediumArmor => {
-            // If you wear medium armor, you add your Dexterity modifier, to a maximum of +2,
-            // to the base number from your armor type to determine your Armor Class.
-            let dexterity = character.ability_scores[Ability::Dexterity];
-            let armor_class_bonus = cmp::max(
-                dexterity.modifier as u16,
-                cmp::min(dexterity.modifier as u16, 2),
-            );
-            return armor.base_armor_class + armor_class_bonus;
-        }
-        ArmorCategory::HeavyArmor => {
-            return armor.base_armor_class;
-        }
-    }
-}
-
-fn has_proficiency_with_armor(character: Character, armor: Armor) -> bool {
-    for character_trait in character.traits {
-        for armor_proficiency_modifiers in character_trait.armor_proficiency_modifiers {
-            if armor_proficiency_modifiers.value == armor.category {
-                return true;
-            }
-        }
-    }
-
-    for racial_trait in character.race.racial_traits.traits {
-        for armor_proficiency_modifiers in racial_trait.armor_proficiency_modifiers {
-            if armor_proficiency_modifiers.value == armor.category {
-                return true;
-            }
-        }
+impl Character {
+    fn has_armor_proficiency(&self, category: ArmorCategory) -> bool {
+        self.traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.armor_proficiency_modifiers.iter())
+            .chain(
+                self.class
+                    .iter()
+                    .flat_map(|class| class.features.armor_proficiency_modifiers.iter()),
+            )
+            .any(|armor_proficiency_modifier| armor_proficiency_modifier.value == category)
     }
-
-    return false;
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
 struct Trait {
     name: String,
     description: String,
+    #[serde(default)]
     weapon_proficiency_modifiers: Vec<WeaponProficiencyModifier>,
+    #[serde(default)]
     armor_proficiency_modifiers: Vec<ArmorProficiencyModifier>,
-}
-
+    #[serde(default)]
+    roll_mode_modifiers: Vec<RollModeModifier>,
+    #[serde(default)]
+    critical_modifiers: Vec<CriticalModifier>,
+    #[serde(default)]
+    roll_rule_modifiers: Vec<RollRuleModifier>,
+    #[serde(default)]
+    hit_point_modifiers: Vec<HitPointModifier>,
+    #[serde(default)]
+    skill_proficiency_modifiers: Vec<SkillProficiencyModifier>,
+    #[serde(default)]
+    armor_class_modifiers: Vec<ArmorClassModifier>,
+    #[serde(default)]
+    speed_modifiers: Vec<SpeedModifier>,
+    #[serde(default)]
+    initiative_modifiers: Vec<InitiativeModifier>,
+    #[serde(default)]
+    armor_modifiers: Vec<ArmorModifier>,
+}
+
+#[allow(dead_code)]
 trait Modifier<T> {
     fn get_name(&self) -> String;
     fn get_value(&self) -> T;
@@ -672,57 +1104,307 @@ fn derive_ability_modifier_from_ability_score(score: u8) -> i8 {
     let ability_modifier: f32 = (score as f32 - INITIAL_ABILITY_SCORE as f32) / 2.0;
     let floored_ability_score = ability_modifier.floor() as i8;
     assert!(
-        floored_ability_score >= MIN_ABILITY_MODIFIER_LEVEL
-            && floored_ability_score <= MAX_ABILITY_MODIFIER_LEVEL
+        (MIN_ABILITY_MODIFIER_LEVEL..=MAX_ABILITY_MODIFIER_LEVEL).contains(&floored_ability_score)
     );
-    return floored_ability_score;
+    floored_ability_score
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct WeaponProficiencyModifier {
+    name: String,
+    value: WeaponProficiency,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct ArmorProficiencyModifier {
+    name: String,
+    value: ArmorCategory,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct RollModeEffect {
+    d20_test: D20Test,
+    roll_mode: RollMode,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct RollModeModifier {
+    name: String,
+    value: RollModeEffect,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+enum CriticalEffect {
+    ExpandedRange(u16),
+    ExtraDice(u16),
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct CriticalModifier {
+    name: String,
+    value: CriticalEffect,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct RollRuleModifier {
+    name: String,
+    value: RollRule,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct HitPointModifier {
+    name: String,
+    value: HitPointEffect,
+}
+
+// A per-level bonus raises the hit point maximum for every character level, like
+// Dwarven Toughness. A hit die minimum is the least a spent hit die heals, as a
+// multiple of the Constitution modifier, like Durable.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+enum HitPointEffect {
+    PerLevel(u16),
+    HitDieMinimum {
+        constitution_multiplier: u16,
+        minimum: u16,
+    },
+}
+
+// Added to the Dexterity modifier when rolling initiative, like Alert.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct InitiativeModifier {
+    name: String,
+    value: i8,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct SkillProficiencyEffect {
+    skill: Skill,
+    proficiency: SkillProficiency,
+    // Limits the effect to checks made in these circumstances, like Stonecunning
+    // only applying to History checks about stonework.
+    #[serde(default)]
+    circumstance: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct SkillProficiencyModifier {
+    name: String,
+    value: SkillProficiencyEffect,
+}
+
+impl Modifier<WeaponProficiency> for WeaponProficiencyModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> WeaponProficiency {
+        self.value.clone()
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::WeaponProficiency
+    }
+}
+
+impl Modifier<ArmorCategory> for ArmorProficiencyModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> ArmorCategory {
+        self.value
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::ArmorProficiency
+    }
+}
+
+impl Modifier<RollModeEffect> for RollModeModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> RollModeEffect {
+        self.value
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::RollMode
+    }
+}
+
+impl Modifier<CriticalEffect> for CriticalModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> CriticalEffect {
+        self.value
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::Critical
+    }
+}
+
+impl Modifier<HitPointEffect> for HitPointModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> HitPointEffect {
+        self.value
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::HitPoints
+    }
+}
+
+impl Modifier<i8> for InitiativeModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> i8 {
+        self.value
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::Initiative
+    }
+}
+
+impl Modifier<SkillProficiencyEffect> for SkillProficiencyModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> SkillProficiencyEffect {
+        self.value.clone()
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::SkillProficiency
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct ArmorClassModifier {
+    name: String,
+    value: ArmorClassEffect,
+}
+
+// A formula replaces the unarmored armor class while no armor is worn, like Mage
+// Armor, and a bonus is added to any armor class, like a ring of protection.
+// Medium Armor Master raises the Dexterity cap of medium armor once the
+// Dexterity score reaches the minimum.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+enum ArmorClassEffect {
+    Formula { base: i16, abilities: Vec<Ability> },
+    Bonus(i16),
+    MediumArmorDexterityMax { maximum: i8, dexterity_minimum: u8 },
+}
+
+impl Modifier<ArmorClassEffect> for ArmorClassModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> ArmorClassEffect {
+        self.value.clone()
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::ArmorClass
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
-struct WeaponProficiencyModifier {
+struct SpeedModifier {
     name: String,
-    value: WeaponType,
+    value: SpeedEffect,
+}
+
+// Speed effects that come from a trait rather than the race's base speed, like
+// the dwarf's speed not being reduced by heavy armor or Mobile adding 10 feet.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+enum SpeedEffect {
+    IgnoreArmorStrengthRequirement,
+    Bonus(i64),
+}
+
+impl Modifier<SpeedEffect> for SpeedModifier {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_value(&self) -> SpeedEffect {
+        self.value
+    }
+
+    fn get_modifier_type(&self) -> ModifierType {
+        ModifierType::Speed
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
-struct ArmorProficiencyModifier {
+struct ArmorModifier {
     name: String,
-    value: ArmorCategory,
+    value: ArmorEffect,
+}
+
+// Lifts a drawback of wearing armor of a category, like Medium Armor Master
+// letting medium armor be worn without disadvantage on Stealth checks.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+enum ArmorEffect {
+    NoStealthDisadvantage(ArmorCategory),
 }
 
-impl Modifier<WeaponType> for WeaponProficiencyModifier {
+impl Modifier<ArmorEffect> for ArmorModifier {
     fn get_name(&self) -> String {
         self.name.clone()
     }
 
-    fn get_value(&self) -> WeaponType {
+    fn get_value(&self) -> ArmorEffect {
         self.value
     }
 
     fn get_modifier_type(&self) -> ModifierType {
-        ModifierType::WeaponProficiency
+        ModifierType::Armor
     }
 }
 
-impl Modifier<ArmorCategory> for ArmorProficiencyModifier {
+impl Modifier<RollRule> for RollRuleModifier {
     fn get_name(&self) -> String {
         self.name.clone()
     }
 
-    fn get_value(&self) -> ArmorCategory {
+    fn get_value(&self) -> RollRule {
         self.value
     }
 
     fn get_modifier_type(&self) -> ModifierType {
-        ModifierType::ArmorProficiency
+        ModifierType::RollRule
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[allow(dead_code)]
 enum ModifierType {
     WeaponProficiency,
     ArmorProficiency,
     Ability,
+    RollMode,
+    Critical,
+    RollRule,
+    HitPoints,
+    SkillProficiency,
+    ArmorClass,
+    Speed,
+    Initiative,
+    Armor,
 }
 
 struct CharacterAdvancementEntry {
@@ -756,6 +1438,7 @@ const CHARACTER_ADVANCEMENT_TABLE: [CharacterAdvancementEntry; 20] = [
 ];
 
 // coin: f32::Coin,
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 struct Wealth {
     copper: f32::Coin,
     silver: f32::Coin,
@@ -764,6 +1447,7 @@ struct Wealth {
     platinum: f32::Coin,
 }
 
+#[allow(dead_code)]
 trait WealthManagement {
     fn add_copper(&mut self, amount: f32);
     fn remove_copper(&mut self, amount: f32);
@@ -778,124 +1462,433 @@ impl WealthManagement for Wealth {
     }
 }
 
+#[allow(dead_code)]
 fn print_type_of<T>(_: &T) {
     println!("{}", std::any::type_name::<T>())
 }
 
-fn load_races_from_file(file_path: &'static str) -> Vec<Race> {
-    let mut races: Vec<Race> = Vec::new();
+// A data file can fail to be read, or hold data that can't be deserialized.
+#[derive(Debug)]
+enum DataError {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+}
 
-    races.push(Race {
-        race_type: RaceType::Dwarf,
-        racial_traits: RacialTraits {
-            age: 80,
-            alignment: Alignment::ChaoticNeutral,
-            size: Size::Medium,
-            speed: 25,
-            languages: vec![Language::Dwarvish],
-            traits: vec![],
-        },
-    });
+impl std::fmt::Display for DataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DataError::Io(error) => write!(f, "{}", error),
+            DataError::Yaml(error) => write!(f, "{}", error),
+        }
+    }
+}
 
-    let file_path = Path::new("serialize/races.yaml");
-    let directory = file_path.parent().unwrap();
+impl From<io::Error> for DataError {
+    fn from(error: io::Error) -> DataError {
+        DataError::Io(error)
+    }
+}
 
-    if !directory.exists() {
-        std::fs::create_dir(directory).unwrap();
+impl From<serde_yaml::Error> for DataError {
+    fn from(error: serde_yaml::Error) -> DataError {
+        DataError::Yaml(error)
     }
+}
 
-    let races_output_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(file_path)
-        .unwrap();
+#[cfg_attr(not(test), allow(dead_code))]
+fn load_races_from_file(file_path: impl AsRef<Path>) -> Result<RaceCatalogue, DataError> {
+    let races = std::fs::read_to_string(file_path)?;
+    Ok(load_races_from_str(&races)?)
+}
 
-    serde_yaml::to_writer(&races_output_file, &races).unwrap();
+#[cfg_attr(not(test), allow(dead_code))]
+fn load_races_from_str(races: &str) -> Result<RaceCatalogue, serde_yaml::Error> {
+    let result: RaceCatalogue = serde_yaml::from_str(races)?;
+
+    if let Some(subrace) = result.find_subrace_without_parent() {
+        return Err(serde::de::Error::custom(format!(
+            "subrace {:?} has no parent race {:?}",
+            subrace.race_type, subrace.parent
+        )));
+    }
 
-    return races;
+    Ok(result)
 }
 
+#[cfg_attr(not(test), allow(dead_code))]
 fn export_characters_to_file(
-    characters: Vec<Character>,
+    mut characters: Vec<Character>,
     file_path: &'static str,
-) -> Result<(), io::Error> {
-    let file_path = Path::new(file_path);
-    let directory = file_path.parent().unwrap();
+) -> Result<(), DataError> {
+    for character in characters.iter_mut() {
+        character.refresh_derived_statistics();
+    }
 
-    if !directory.exists() {
-        std::fs::create_dir(directory).unwrap();
+    let file_path = Path::new(file_path);
+    if let Some(directory) = file_path.parent() {
+        std::fs::create_dir_all(directory)?;
     }
 
     let characters_output_file = OpenOptions::new()
         .write(true)
         .create(true)
-        .open(file_path)
-        .unwrap();
+        .truncate(true)
+        .open(file_path)?;
 
-    let result = serde_yaml::to_writer(&characters_output_file, &characters).unwrap();
+    Ok(serde_yaml::to_writer(&characters_output_file, &characters)?)
+}
 
-    return Ok(result);
+fn load_characters_from_file(file_path: impl AsRef<Path>) -> Result<Vec<Character>, DataError> {
+    let characters = std::fs::read_to_string(file_path)?;
+    Ok(load_characters_from_str(&characters)?)
 }
 
-fn load_characters_from_file(file_path: &'static str) -> Result<Vec<Character>, serde_yaml::Error> {
-    let characters_import_file = OpenOptions::new().read(true).open(file_path).unwrap();
+fn load_characters_from_str(characters: &str) -> Result<Vec<Character>, serde_yaml::Error> {
+    let result: Vec<Character> = serde_yaml::from_str(characters)?;
+
+    for character in result.iter() {
+        if let Err(error) = character.advancement.check_milestone_level() {
+            return Err(serde::de::Error::custom(format!(
+                "{} has an invalid milestone level: {}",
+                character.name, error
+            )));
+        }
 
-    let result = serde_yaml::from_reader(&characters_import_file)
-        .expect("Can't import the characters data by deserializing.");
+        // Stored statistics are only a cache of values derived from the rest of the character.
+        let stale_statistics = character.find_stale_statistics();
+        if !stale_statistics.is_empty() {
+            let mismatches: Vec<String> = stale_statistics
+                .iter()
+                .map(|stale_statistic| stale_statistic.to_string())
+                .collect();
+            return Err(serde::de::Error::custom(format!(
+                "{} has stale statistics: {}",
+                character.name,
+                mismatches.join(", ")
+            )));
+        }
+    }
 
     Ok(result)
 }
 
-fn export_armor_to_file(armors: Vec<Armor>, file_path: &'static str) -> Result<(), io::Error> {
+#[cfg_attr(not(test), allow(dead_code))]
+fn export_armor_to_file(armors: Vec<Armor>, file_path: &'static str) -> Result<(), DataError> {
     let file_path = Path::new(file_path);
-    let directory = file_path.parent().unwrap();
-
-    if !directory.exists() {
-        std::fs::create_dir(directory).unwrap();
+    if let Some(directory) = file_path.parent() {
+        std::fs::create_dir_all(directory)?;
     }
 
     let armors_export_file = OpenOptions::new()
         .write(true)
         .create(true)
-        .open(file_path)
-        .unwrap();
+        .truncate(true)
+        .open(file_path)?;
+
+    Ok(serde_yaml::to_writer(&armors_export_file, &armors)?)
+}
 
-    let result = serde_yaml::to_writer(&armors_export_file, &armors).unwrap();
+#[cfg_attr(not(test), allow(dead_code))]
+fn load_armor_from_file(file_path: impl AsRef<Path>) -> Result<Vec<Armor>, DataError> {
+    let armors_import_file = OpenOptions::new().read(true).open(file_path)?;
 
-    return Ok(result);
+    Ok(serde_yaml::from_reader(&armors_import_file)?)
 }
 
-fn load_armor_from_file(file_path: &'static str) -> Result<Vec<Armor>, serde_yaml::Error> {
-    let armors_import_file = OpenOptions::new().read(true).open(file_path).unwrap();
+#[cfg_attr(not(test), allow(dead_code))]
+fn load_weapons_from_file(file_path: impl AsRef<Path>) -> Result<Vec<Weapon>, DataError> {
+    let weapons_import_file = OpenOptions::new().read(true).open(file_path)?;
 
-    let result = serde_yaml::from_reader(&armors_import_file)
-        .expect("Can't import the armors data by deserializing.");
+    Ok(serde_yaml::from_reader(&weapons_import_file)?)
+}
 
-    Ok(result)
+#[cfg_attr(not(test), allow(dead_code))]
+fn load_classes_from_file(file_path: impl AsRef<Path>) -> Result<Vec<Class>, DataError> {
+    let classes_import_file = OpenOptions::new().read(true).open(file_path)?;
+
+    let classes: Vec<Class> = serde_yaml::from_reader(&classes_import_file)?;
+    for class in classes.iter() {
+        if let Some(problem) = class.find_level_table_problem() {
+            return Err(DataError::Yaml(serde::de::Error::custom(problem)));
+        }
+    }
+
+    Ok(classes)
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+fn load_feats_from_file(file_path: impl AsRef<Path>) -> Result<Vec<Feat>, DataError> {
+    let feats_import_file = OpenOptions::new().read(true).open(file_path)?;
+
+    Ok(serde_yaml::from_reader(&feats_import_file)?)
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+fn load_backgrounds_from_file(file_path: impl AsRef<Path>) -> Result<Vec<Background>, DataError> {
+    let backgrounds_import_file = OpenOptions::new().read(true).open(file_path)?;
+
+    let backgrounds: Vec<Background> = serde_yaml::from_reader(&backgrounds_import_file)?;
+    for background in backgrounds.iter() {
+        if let Some(problem) = background.find_table_problem() {
+            return Err(DataError::Yaml(serde::de::Error::custom(problem)));
+        }
+    }
+
+    Ok(backgrounds)
 }
 
 #[derive(StructOpt)]
-struct Cli {
-    pattern: String,
-    #[structopt(parse(from_os_str))]
-    path: std::path::PathBuf,
+enum Cli {
+    /// Print the lines of a file that contain a pattern
+    Search {
+        pattern: String,
+        #[structopt(parse(from_os_str))]
+        path: std::path::PathBuf,
+    },
+    /// Roll a dice expression, e.g. "4d6kh3" or "1d8 + 1d6 fire"
+    Roll {
+        expression: String,
+        /// Roll with a seeded generator so the same seed repeats the same roll
+        #[structopt(long)]
+        seed: Option<u64>,
+    },
+    /// Show the exact odds of a dice expression, e.g. "2d6+3" or "1d20+5"
+    Odds {
+        expression: String,
+        /// Also print the chance of meeting or beating this difficulty class
+        #[structopt(long)]
+        dc: Option<i32>,
+        /// Roll the d20 with advantage (only for a single d20 plus modifiers)
+        #[structopt(long)]
+        advantage: bool,
+        /// Roll the d20 with disadvantage (only for a single d20 plus modifiers)
+        #[structopt(long)]
+        disadvantage: bool,
+    },
+    /// Print the character sheets stored in a characters file
+    Sheet {
+        #[structopt(parse(from_os_str))]
+        path: std::path::PathBuf,
+    },
+}
+
+fn print_roll(expression: &str, seed: Option<u64>) {
+    let expression: DiceExpression = match expression.parse() {
+        Ok(expression) => expression,
+        Err(error) => {
+            eprintln!("Invalid dice expression: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let mut roller: Box<dyn Roller> = match seed {
+        Some(seed) => Box::new(SeededRoller::new(seed)),
+        None => Box::new(RandomRoller),
+    };
+    let roll = expression.roll(roller.as_mut());
+
+    for term_roll in roll.terms.iter() {
+        // Dropped dice are shown in parentheses.
+        let dice: Vec<String> = term_roll
+            .dice
+            .iter()
+            .map(|die_roll| {
+                if die_roll.kept {
+                    die_roll.value.to_string()
+                } else {
+                    format!("({})", die_roll.value)
+                }
+            })
+            .collect();
+        let term = DiceExpression {
+            terms: vec![term_roll.term],
+            fixed_value: None,
+        };
+        if dice.is_empty() {
+            println!("{:>12}  {:>6}", term.to_string(), term_roll.subtotal);
+        } else {
+            println!(
+                "{:>12}  {:>6}  [{}]",
+                term.to_string(),
+                term_roll.subtotal,
+                dice.join(", ")
+            );
+        }
+    }
+    println!("Total: {}", roll.total);
+}
+
+fn print_odds(expression: &str, dc: Option<i32>, roll_mode: RollMode) {
+    let expression: DiceExpression = match expression.parse() {
+        Ok(expression) => expression,
+        Err(error) => {
+            eprintln!("Invalid dice expression: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let distribution = match roll_mode {
+        RollMode::Normal => ProbabilityDistribution::from_expression(&expression),
+        _ => {
+            // Advantage only makes sense on a lone d20 that is added to the total,
+            // so split off the flat modifiers.
+            let mut dice_terms = expression
+                .terms
+                .iter()
+                .filter(|term| matches!(term.value, TermValue::Dice(_)));
+            match (dice_terms.next(), dice_terms.next()) {
+                (
+                    Some(DiceTerm {
+                        negative: false,
+                        value: TermValue::Dice(group),
+                        ..
+                    }),
+                    None,
+                ) if group.count == 1 && group.die == dice::D20 => {
+                    let modifier = expression.flat_modifier();
+                    Ok(ProbabilityDistribution::d20(modifier as i16, roll_mode))
+                }
+                _ => {
+                    eprintln!("Advantage and disadvantage only apply to a single added d20");
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+
+    let distribution = match distribution {
+        Ok(distribution) => distribution,
+        Err(error) => {
+            eprintln!("Can't compute the odds: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    for (total, probability) in distribution.pmf() {
+        println!("{:>4}  {:>7.3}%", total, probability * 100.0);
+    }
+    println!("Expected value: {:.3}", distribution.expected_value());
+
+    if let Some(dc) = dc {
+        println!(
+            "Chance of {} or higher: {:.3}%",
+            dc,
+            distribution.probability_at_least(dc) * 100.0
+        );
+    }
+}
+
+fn print_sheet(path: &Path) {
+    let characters = match load_characters_from_file(path) {
+        Ok(characters) => characters,
+        Err(error) => {
+            eprintln!("Can't load the characters: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    for character in characters.iter() {
+        let classes: Vec<String> = character
+            .class
+            .iter()
+            .map(|class| format!("{:?} {}", class.class_type, class.level))
+            .collect();
+        println!(
+            "{}, {:?} {}",
+            character.name,
+            character.race.race_type,
+            classes.join(" / ")
+        );
+        println!(
+            "Hit points {}/{}  Armor class {}  Speed {}  Initiative {:+}  Proficiency bonus {:+}",
+            character.get_current_hit_points(),
+            character.get_max_hit_points(),
+            character.get_equipped_armor_class().total,
+            character.get_current_speed(),
+            character.get_initiative_modifier(),
+            character.get_proficiency_bonus()
+        );
+        for &ability in ABILITIES.iter() {
+            println!(
+                "{:<12}  {:>2} ({:+})  save {:+}",
+                format!("{:?}", ability),
+                character.ability_scores[ability].score,
+                character.ability_scores[ability].modifier,
+                character.saving_throws[ability].modifier
+            );
+        }
+        for skill_score in character.skills.0.iter() {
+            println!(
+                "{:<16}  {:+}",
+                format!("{:?}", skill_score.skill),
+                skill_score.modifier
+            );
+        }
+        println!(
+            "Passive Perception {}",
+            character.get_passive_score(Skill::Perception)
+        );
+        for entry in character.inventory.items.iter() {
+            println!(
+                "{} x{}  {} lb.  {:.2}",
+                entry.item.get_name(),
+                entry.quantity,
+                entry.item.get_weight(),
+                entry
+                    .item
+                    .get_cost()
+                    .into_format_args(coin::gold, Abbreviation)
+            );
+        }
+        println!(
+            "Carrying {} lb. of {} lb.",
+            character.get_carried_weight(),
+            character.get_carrying_capacity()
+        );
+        println!();
+    }
 }
 
 // src/main.rs
 fn main() {
-    let args = Cli::from_args();
-    let content = std::fs::read_to_string(&args.path).expect("could not read file");
+    match Cli::from_args() {
+        Cli::Search { pattern, path } => {
+            let content = std::fs::read_to_string(&path).expect("could not read file");
 
-    for line in content.lines() {
-        if line.contains(&args.pattern) {
-            println!("{}", line);
+            for line in content.lines() {
+                if line.contains(&pattern) {
+                    println!("{}", line);
+                }
+            }
         }
+        Cli::Roll { expression, seed } => print_roll(&expression, seed),
+        Cli::Odds {
+            expression,
+            dc,
+            advantage,
+            disadvantage,
+        } => {
+            let mut sources = vec![];
+            if advantage {
+                sources.push(RollMode::Advantage);
+            }
+            if disadvantage {
+                sources.push(RollMode::Disadvantage);
+            }
+            print_odds(&expression, dc, RollMode::from_sources(sources));
+        }
+        Cli::Sheet { path } => print_sheet(&path),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use backgrounds::BackgroundType;
+    use character_builder::CharacterBuilder;
+    use feats::AbilityScoreImprovementChoice;
+    use roll_rules::{RollRuleEffect, RollRuleTarget};
+    use roller::{ScriptedRoller, SeededRoller};
 
     #[test]
     fn verify_ability_score_calculation() {
@@ -949,6 +1942,7 @@ mod tests {
             cost: f32::Coin::new::<coin::gold>(10.0),
             weight: 8,
             has_stealth_disadvantage: false,
+            magic_bonus: 0,
         };
 
         let character = Character {
@@ -957,21 +1951,29 @@ mod tests {
             level: 1,
             race: Race {
                 race_type: RaceType::Dwarf,
+                parent: None,
                 racial_traits: RacialTraits {
                     age: 80,
                     alignment: Alignment::ChaoticNeutral,
                     size: Size::Medium,
                     speed: 25,
                     languages: vec![Language::Dwarvish],
+                    ability_score_increase: vec![],
+                    darkvision: None,
                     traits: vec![],
                 },
             },
             class: vec![Class {
                 class_type: ClassType::Barbarian,
+                level: 1,
+                levels: vec![],
                 features: ClassFeatures {
                     hit_dice: Die { min: 0, max: 6 },
                     hit_points_starting: 0,
                     hit_points_from_level: Die { min: 0, max: 6 },
+                    weapon_proficiency_modifiers: vec![],
+                    armor_proficiency_modifiers: vec![],
+                    saving_throw_proficiencies: vec![],
                 },
             }],
             age: 80,
@@ -1050,12 +2052,26 @@ mod tests {
                     name: String::from("LightArmor"),
                     value: ArmorCategory::LightArmor,
                 }],
-                weapon_proficiency_modifiers: vec![],
+                ..Default::default()
             }],
             roll_hit_points: false,
+            conditions: vec![],
+            creation_choices: vec![],
+            background: None,
+            class_features: vec![],
+            hit_points_gained: vec![],
+            hit_points: HitPoints::default(),
+            death_saving_throws: DeathSavingThrows::default(),
+            skills: SkillSet::default(),
+            ability_score_improvements: vec![],
+            skill_choices: vec![],
+            advancement: Advancement::default(),
+            inventory: Inventory::default(),
+            variant_encumbrance: false,
+            equipment: Equipment::default(),
         };
 
-        assert_eq!(has_proficiency_with_armor(character, armor), true)
+        assert!(character.has_armor_proficiency(armor.category))
     }
 
     // DnD OGL
@@ -1105,6 +2121,7 @@ mod tests {
             cost: f32::Coin::new::<coin::gold>(10.0),
             weight: 8,
             has_stealth_disadvantage: false,
+            magic_bonus: 0,
         };
 
         armors_export.push(armor);
@@ -1124,6 +2141,7 @@ mod tests {
             cost: f32::Coin::new::<coin::gold>(10.0),
             weight: 8,
             has_stealth_disadvantage: false,
+            magic_bonus: 0,
         };
 
         armors.push(armor);
@@ -1135,6 +2153,47 @@ mod tests {
         )
     }
 
+    #[test]
+    fn import_sample_weapons() {
+        let weapons = load_weapons_from_file("./data/weapons.yaml").unwrap();
+        assert_eq!(weapons.len(), 37);
+        assert_eq!(
+            weapons
+                .iter()
+                .filter(|weapon| weapon.category == WeaponCategory::SimpleWeapons)
+                .count(),
+            14
+        );
+
+        let longsword = weapons
+            .iter()
+            .find(|weapon| weapon.name == "Longsword")
+            .unwrap();
+        assert_eq!(
+            longsword.damage,
+            Some(WeaponDamage {
+                dice: "1d8".parse().unwrap(),
+                damage_type: DamageType::Slashing,
+            })
+        );
+        assert_eq!(longsword.cost, f32::Coin::new::<coin::gold>(15.0));
+        assert_eq!(
+            longsword.properties,
+            vec![WeaponProperty::Versatile("1d10".parse().unwrap())]
+        );
+
+        let dart = weapons.iter().find(|weapon| weapon.name == "Dart").unwrap();
+        assert_eq!(dart.weight, 0.25);
+        assert!(dart.properties.contains(&WeaponProperty::Range {
+            normal: 20,
+            long: 60
+        }));
+
+        let net = weapons.last().unwrap();
+        assert_eq!(net.name, "Net");
+        assert_eq!(net.damage, None);
+    }
+
     #[test]
     fn export_sample_characters() {
         let mut characters: Vec<Character> = Vec::new();
@@ -1145,21 +2204,29 @@ mod tests {
             level: 1,
             race: Race {
                 race_type: RaceType::Dwarf,
+                parent: None,
                 racial_traits: RacialTraits {
                     age: 80,
                     alignment: Alignment::ChaoticNeutral,
                     size: Size::Medium,
                     speed: 25,
                     languages: vec![Language::Dwarvish],
+                    ability_score_increase: vec![],
+                    darkvision: None,
                     traits: vec![],
                 },
             },
             class: vec![Class {
                 class_type: ClassType::Barbarian,
+                level: 1,
+                levels: vec![],
                 features: ClassFeatures {
                     hit_dice: Die { min: 0, max: 6 },
                     hit_points_starting: 0,
                     hit_points_from_level: Die { min: 0, max: 6 },
+                    weapon_proficiency_modifiers: vec![],
+                    armor_proficiency_modifiers: vec![],
+                    saving_throw_proficiencies: vec![],
                 },
             }],
             age: 80,
@@ -1202,8 +2269,7 @@ mod tests {
             traits: vec![Trait {
                 name: String::from("test"),
                 description: String::from("Hello"),
-                weapon_proficiency_modifiers: vec![],
-                armor_proficiency_modifiers: vec![],
+                ..Default::default()
             }],
             saving_throws: SavingThrowSet([
                 SavingThrowScore {
@@ -1238,6 +2304,20 @@ mod tests {
                 },
             ]),
             roll_hit_points: false,
+            conditions: vec![],
+            creation_choices: vec![],
+            background: None,
+            class_features: vec![],
+            hit_points_gained: vec![],
+            hit_points: HitPoints::default(),
+            death_saving_throws: DeathSavingThrows::default(),
+            skills: SkillSet::default(),
+            ability_score_improvements: vec![],
+            skill_choices: vec![],
+            advancement: Advancement::default(),
+            inventory: Inventory::default(),
+            variant_encumbrance: false,
+            equipment: Equipment::default(),
         };
 
         characters.push(character);
@@ -1256,21 +2336,29 @@ mod tests {
             level: 1,
             race: Race {
                 race_type: RaceType::Dwarf,
+                parent: None,
                 racial_traits: RacialTraits {
                     age: 80,
                     alignment: Alignment::ChaoticNeutral,
                     size: Size::Medium,
                     speed: 25,
                     languages: vec![Language::Dwarvish],
+                    ability_score_increase: vec![],
+                    darkvision: None,
                     traits: vec![],
                 },
             },
             class: vec![Class {
                 class_type: ClassType::Barbarian,
+                level: 1,
+                levels: vec![],
                 features: ClassFeatures {
                     hit_dice: Die { min: 0, max: 6 },
                     hit_points_starting: 0,
                     hit_points_from_level: Die { min: 0, max: 6 },
+                    weapon_proficiency_modifiers: vec![],
+                    armor_proficiency_modifiers: vec![],
+                    saving_throw_proficiencies: vec![],
                 },
             }],
             age: 80,
@@ -1313,8 +2401,7 @@ mod tests {
             traits: vec![Trait {
                 name: String::from("test"),
                 description: String::from("Hello"),
-                weapon_proficiency_modifiers: vec![],
-                armor_proficiency_modifiers: vec![],
+                ..Default::default()
             }],
             saving_throws: SavingThrowSet([
                 SavingThrowScore {
@@ -1349,6 +2436,20 @@ mod tests {
                 },
             ]),
             roll_hit_points: false,
+            conditions: vec![],
+            creation_choices: vec![],
+            background: None,
+            class_features: vec![],
+            hit_points_gained: vec![],
+            hit_points: HitPoints::default(),
+            death_saving_throws: DeathSavingThrows::default(),
+            skills: SkillSet::default(),
+            ability_score_improvements: vec![],
+            skill_choices: vec![],
+            advancement: Advancement::default(),
+            inventory: Inventory::default(),
+            variant_encumbrance: false,
+            equipment: Equipment::default(),
         };
 
         characters.push(character);
@@ -1361,15 +2462,430 @@ mod tests {
     fn verify_spell_slot_count() {
         let class = Class {
             class_type: ClassType::Wizard,
+            level: 1,
+            levels: vec![],
             features: ClassFeatures {
                 hit_dice: Die { min: 0, max: 6 },
                 hit_points_starting: 0,
                 hit_points_from_level: Die { min: 0, max: 6 },
+                weapon_proficiency_modifiers: vec![],
+                armor_proficiency_modifiers: vec![],
+                saving_throw_proficiencies: vec![],
             },
         };
 
-        assert_eq!(3, get_number_of_spell_slots_for_spell_level(class, 1, 1));
-        assert_eq!(1, get_number_of_spell_slots_for_spell_level(class, 20, 9));
-        assert_eq!(5, get_number_of_spell_slots_for_spell_level(class, 1, 9));
+        assert_eq!(3, get_number_of_spell_slots_for_spell_level(&class, 1, 0));
+        assert_eq!(2, get_number_of_spell_slots_for_spell_level(&class, 1, 1));
+        assert_eq!(1, get_number_of_spell_slots_for_spell_level(&class, 20, 9));
+        assert_eq!(0, get_number_of_spell_slots_for_spell_level(&class, 1, 9));
+        assert_eq!(5, get_number_of_spell_slots_for_spell_level(&class, 20, 0));
+    }
+
+    #[test]
+    fn rolls_are_reproducible_with_injected_rollers() {
+        let character = create_sample_character();
+
+        let mut scripted_roller = ScriptedRoller::new(vec![15, 3]);
+        let ability_check =
+            character.roll_ability_check(Ability::Strength, RollMode::Normal, &mut scripted_roller);
+        assert_eq!(ability_check.total, 15);
+        let saving_throw =
+            character.roll_saving_throw(Ability::Dexterity, RollMode::Normal, &mut scripted_roller);
+        assert_eq!(saving_throw.total, 3);
+
+        let first_roll = character.roll_ability_check(
+            Ability::Wisdom,
+            RollMode::Advantage,
+            &mut SeededRoller::new(11),
+        );
+        let second_roll = character.roll_ability_check(
+            Ability::Wisdom,
+            RollMode::Advantage,
+            &mut SeededRoller::new(11),
+        );
+        assert_eq!(first_roll, second_roll);
+    }
+
+    #[test]
+    fn gather_advantage_sources_from_traits() {
+        let mut character = create_sample_character();
+        character.traits.push(Trait {
+            name: String::from("Danger Sense"),
+            description: String::from(
+                "You have advantage on Dexterity saving throws against effects that you can see.",
+            ),
+            roll_mode_modifiers: vec![RollModeModifier {
+                name: String::from("Danger Sense"),
+                value: RollModeEffect {
+                    d20_test: D20Test::SavingThrow(Ability::Dexterity),
+                    roll_mode: RollMode::Advantage,
+                },
+            }],
+            ..Default::default()
+        });
+
+        let mut roller = ScriptedRoller::new(vec![3, 18, 3, 18, 3]);
+        let dexterity_save =
+            character.roll_saving_throw(Ability::Dexterity, RollMode::Normal, &mut roller);
+        assert_eq!(dexterity_save.mode, RollMode::Advantage);
+        assert_eq!(dexterity_save.natural(), 18);
+
+        let cancelled_save =
+            character.roll_saving_throw(Ability::Dexterity, RollMode::Disadvantage, &mut roller);
+        assert_eq!(cancelled_save.mode, RollMode::Normal);
+        assert_eq!(cancelled_save.rolls, vec![3]);
+
+        let strength_check =
+            character.roll_ability_check(Ability::Strength, RollMode::Disadvantage, &mut roller);
+        assert_eq!(strength_check.rolls, vec![18, 3]);
+        assert_eq!(strength_check.natural(), 3);
+    }
+
+    // Takes a first-level character from the data files through every step of
+    // the character builder. Backgrounds that teach languages get the first ones
+    // the race doesn't already know.
+    pub(crate) fn build_character(
+        race_type: RaceType,
+        class_type: ClassType,
+        scores: &[(Ability, u8)],
+        background_type: BackgroundType,
+    ) -> Character {
+        let race = load_races_from_file("data/races.yaml")
+            .unwrap()
+            .find_race(race_type)
+            .unwrap();
+        let class = load_classes_from_file("data/classes.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|class| class.class_type == class_type)
+            .unwrap();
+        let background = load_backgrounds_from_file("data/backgrounds.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|background| background.background_type == background_type)
+            .unwrap();
+        let languages: Vec<Language> = [Language::Elvish, Language::Dwarvish, Language::Giant]
+            .iter()
+            .copied()
+            .filter(|language| !race.racial_traits.languages.contains(language))
+            .take(background.language_choices as usize)
+            .collect();
+        let personality = background.roll_personality(&mut SeededRoller::new(1));
+
+        let mut builder = CharacterBuilder::new("Tishros", 80, Alignment::ChaoticNeutral);
+        builder.choose_race(race).unwrap();
+        builder.choose_class(class).unwrap();
+        builder.assign_standard_array(scores).unwrap();
+        builder
+            .choose_background(background, languages, personality)
+            .unwrap();
+        builder.choose_equipment(vec![]).unwrap();
+        builder.build().unwrap()
+    }
+
+    pub(crate) fn create_sample_character() -> Character {
+        Character {
+            name: String::from("Tishros"),
+            experience_points: 0,
+            level: 1,
+            race: Race {
+                race_type: RaceType::Dwarf,
+                parent: None,
+                racial_traits: RacialTraits {
+                    age: 80,
+                    alignment: Alignment::ChaoticNeutral,
+                    size: Size::Medium,
+                    speed: 25,
+                    languages: vec![Language::Dwarvish],
+                    ability_score_increase: vec![],
+                    darkvision: None,
+                    traits: vec![],
+                },
+            },
+            class: vec![Class {
+                class_type: ClassType::Barbarian,
+                level: 1,
+                levels: vec![],
+                features: ClassFeatures {
+                    hit_dice: Die { min: 1, max: 12 },
+                    hit_points_starting: 12,
+                    hit_points_from_level: Die { min: 1, max: 12 },
+                    weapon_proficiency_modifiers: vec![],
+                    armor_proficiency_modifiers: vec![],
+                    saving_throw_proficiencies: vec![],
+                },
+            }],
+            age: 80,
+            alignment: Alignment::ChaoticNeutral,
+            size: Size::Medium,
+            speed: 25,
+            languages: vec![Language::Dwarvish],
+            ability_scores: AbilitySet([
+                AbilityScore {
+                    ability: Ability::Strength,
+                    score: 10,
+                    modifier: 0,
+                },
+                AbilityScore {
+                    ability: Ability::Dexterity,
+                    score: 10,
+                    modifier: 0,
+                },
+                AbilityScore {
+                    ability: Ability::Constitution,
+                    score: 10,
+                    modifier: 0,
+                },
+                AbilityScore {
+                    ability: Ability::Wisdom,
+                    score: 10,
+                    modifier: 0,
+                },
+                AbilityScore {
+                    ability: Ability::Intelligence,
+                    score: 10,
+                    modifier: 0,
+                },
+                AbilityScore {
+                    ability: Ability::Charisma,
+                    score: 10,
+                    modifier: 0,
+                },
+            ]),
+            saving_throws: SavingThrowSet([
+                SavingThrowScore {
+                    saving_throw: Ability::Strength,
+                    score: 10,
+                    modifier: 0,
+                },
+                SavingThrowScore {
+                    saving_throw: Ability::Dexterity,
+                    score: 10,
+                    modifier: 0,
+                },
+                SavingThrowScore {
+                    saving_throw: Ability::Constitution,
+                    score: 10,
+                    modifier: 0,
+                },
+                SavingThrowScore {
+                    saving_throw: Ability::Wisdom,
+                    score: 10,
+                    modifier: 0,
+                },
+                SavingThrowScore {
+                    saving_throw: Ability::Intelligence,
+                    score: 10,
+                    modifier: 0,
+                },
+                SavingThrowScore {
+                    saving_throw: Ability::Charisma,
+                    score: 10,
+                    modifier: 0,
+                },
+            ]),
+            traits: vec![],
+            roll_hit_points: false,
+            conditions: vec![],
+            creation_choices: vec![],
+            background: None,
+            class_features: vec![],
+            hit_points_gained: vec![],
+            hit_points: HitPoints::default(),
+            death_saving_throws: DeathSavingThrows::default(),
+            skills: SkillSet::default(),
+            ability_score_improvements: vec![],
+            skill_choices: vec![],
+            advancement: Advancement::default(),
+            inventory: Inventory::default(),
+            variant_encumbrance: false,
+            equipment: Equipment::default(),
+        }
+    }
+
+    #[test]
+    fn critical_hits_use_character_critical_features() {
+        let mut character = create_sample_character();
+        character.traits.push(Trait {
+            name: String::from("Brutal Critical (2 dice)"),
+            description: String::from(
+                "You can roll two additional weapon damage dice when determining the extra damage for a critical hit with a melee attack.",
+            ),
+            critical_modifiers: vec![
+                CriticalModifier {
+                    name: String::from("Brutal Critical"),
+                    value: CriticalEffect::ExtraDice(2),
+                },
+                CriticalModifier {
+                    name: String::from("Improved Critical"),
+                    value: CriticalEffect::ExpandedRange(19),
+                },
+            ],
+            ..Default::default()
+        });
+
+        let mut roller = ScriptedRoller::new(vec![19, 6, 5, 4, 3]);
+        let attack = character.roll_attack(5, RollMode::Normal, &mut roller);
+        assert!(attack.critical);
+
+        let damage: DiceExpression = "1d6 + 3".parse().unwrap();
+        let damage_roll = character.roll_damage(
+            &damage,
+            attack.critical,
+            DamageSource::MeleeAttack { two_handed: false },
+            &mut roller,
+        );
+        assert_eq!(damage_roll.total, 6 + 5 + 4 + 3 + 3);
+        assert_eq!(roller.remaining(), 0);
+
+        let mut roller = ScriptedRoller::new(vec![6, 5]);
+        let damage_roll = character.roll_damage(
+            &damage,
+            attack.critical,
+            DamageSource::RangedAttack,
+            &mut roller,
+        );
+        assert_eq!(damage_roll.total, 6 + 5 + 3);
+        assert_eq!(roller.remaining(), 0);
+    }
+
+    #[test]
+    fn brutal_critical_comes_from_barbarian_levels() {
+        let mut character = create_sample_character();
+        character.class[0] = load_classes_from_file("data/classes.yaml")
+            .unwrap()
+            .remove(0);
+        character.experience_points = 48000;
+        character.gain_level(ClassType::Barbarian, &mut ScriptedRoller::new(vec![]));
+        while !character
+            .get_pending_ability_score_improvements()
+            .is_empty()
+        {
+            character
+                .resolve_ability_score_improvement(AbilityScoreImprovementChoice::IncreaseOne(
+                    Ability::Constitution,
+                ))
+                .unwrap();
+            character.gain_level(ClassType::Barbarian, &mut ScriptedRoller::new(vec![]));
+        }
+        assert_eq!(character.get_class_level(ClassType::Barbarian), 9);
+        assert!(character.has_class_feature("Brutal Critical (1 die)"));
+        assert_eq!(character.get_critical_extra_dice(), 1);
+
+        let damage: DiceExpression = "1d12 + 3".parse().unwrap();
+        let mut roller = ScriptedRoller::new(vec![12, 11, 10]);
+        let damage_roll = character.roll_damage(
+            &damage,
+            true,
+            DamageSource::MeleeAttack { two_handed: true },
+            &mut roller,
+        );
+        assert_eq!(damage_roll.total, 12 + 11 + 10 + 3);
+        assert_eq!(roller.remaining(), 0);
+
+        character.race = load_races_from_file("data/races.yaml")
+            .unwrap()
+            .find_race(RaceType::HalfOrc)
+            .unwrap();
+        assert_eq!(character.get_critical_extra_dice(), 2);
+    }
+
+    #[test]
+    fn character_traits_apply_roll_rules() {
+        let mut character = create_sample_character();
+        character.race.racial_traits.traits.push(Trait {
+            name: String::from("Lucky"),
+            description: String::from(
+                "When you roll a 1 on an attack roll, ability check, or saving throw, you can reroll the die and must use the new roll.",
+            ),
+            roll_rule_modifiers: vec![RollRuleModifier {
+                name: String::from("Lucky"),
+                value: RollRule {
+                    target: RollRuleTarget::D20,
+                    effect: RollRuleEffect::RerollAtOrBelow(1),
+                },
+            }],
+            ..Default::default()
+        });
+        character.traits.push(Trait {
+            name: String::from("Great Weapon Fighting"),
+            description: String::from(
+                "When you roll a 1 or 2 on a damage die for an attack you make with a melee weapon that you are wielding with two hands, you can reroll the die and must use the new roll.",
+            ),
+            roll_rule_modifiers: vec![RollRuleModifier {
+                name: String::from("Great Weapon Fighting"),
+                value: RollRule {
+                    target: RollRuleTarget::TwoHandedMeleeDamage,
+                    effect: RollRuleEffect::RerollAtOrBelow(2),
+                },
+            }],
+            ..Default::default()
+        });
+
+        let mut roller = ScriptedRoller::new(vec![1, 14, 2, 5, 3]);
+        let ability_check =
+            character.roll_ability_check(Ability::Strength, RollMode::Normal, &mut roller);
+        assert_eq!(ability_check.natural(), 14);
+        assert_eq!(ability_check.adjustments[0].1.original, 1);
+
+        let damage: DiceExpression = "2d6".parse().unwrap();
+        let damage_roll = character.roll_damage(
+            &damage,
+            false,
+            DamageSource::MeleeAttack { two_handed: true },
+            &mut roller,
+        );
+        let dice = &damage_roll.terms[0].dice;
+        assert_eq!(dice[0].value, 5);
+        assert_eq!(dice[0].adjustments[0].original, 2);
+        assert_eq!(dice[0].adjustments[0].rule, "Great Weapon Fighting");
+        assert_eq!(dice[1].value, 3);
+        assert!(dice[1].adjustments.is_empty());
+        assert_eq!(damage_roll.total, 8);
+
+        // Great Weapon Fighting doesn't touch one-handed, ranged or spell damage.
+        for source in [
+            DamageSource::MeleeAttack { two_handed: false },
+            DamageSource::RangedAttack,
+            DamageSource::Other,
+        ]
+        .iter()
+        {
+            let mut roller = ScriptedRoller::new(vec![1, 2]);
+            let damage_roll = character.roll_damage(&damage, false, *source, &mut roller);
+            assert_eq!(damage_roll.total, 3);
+            assert_eq!(roller.remaining(), 0);
+        }
+    }
+
+    #[test]
+    fn loading_stale_characters_reports_the_mismatch() {
+        let mut character = create_sample_character();
+        character.speed = 30;
+
+        let serialized = serde_yaml::to_string(&vec![character]).unwrap();
+        let error = load_characters_from_str(&serialized).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Tishros has stale statistics: Speed is stored as 30 but should be 25"
+        );
+    }
+
+    #[test]
+    fn loading_characters_checks_the_milestone_level() {
+        for level in [0, 25] {
+            let mut character = create_sample_character();
+            character.advancement = Advancement::Milestone(level);
+
+            let serialized = serde_yaml::to_string(&vec![character]).unwrap();
+            let error = load_characters_from_str(&serialized).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "Tishros has an invalid milestone level: level {} is outside of 1 to 20",
+                    level
+                )
+            );
+        }
     }
-}
\ No newline at end of file
+}
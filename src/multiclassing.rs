@@ -0,0 +1,656 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::roller::Roller;
+use crate::skills::SkillProficiency;
+use crate::weapon_attacks::WeaponProficiency;
+use crate::{
+    find_spell_splots_for_spell_level, Ability, AbilitySet, ArmorCategory, Character, Class,
+    ClassType, Skill, WeaponCategory, EFFECTIVE_LEVEL_MIN, WIZARD_SPELL_SLOTS_PER_SPELL_LEVEL,
+};
+
+const MULTICLASS_ABILITY_SCORE_MIN: u8 = 13;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MulticlassPrerequisite {
+    AllOf(&'static [Ability]),
+    AnyOf(&'static [Ability]),
+}
+
+impl MulticlassPrerequisite {
+    pub fn for_class(class_type: ClassType) -> MulticlassPrerequisite {
+        match class_type {
+            ClassType::Barbarian => MulticlassPrerequisite::AllOf(&[Ability::Strength]),
+            ClassType::Bard => MulticlassPrerequisite::AllOf(&[Ability::Charisma]),
+            ClassType::Cleric => MulticlassPrerequisite::AllOf(&[Ability::Wisdom]),
+            ClassType::Druid => MulticlassPrerequisite::AllOf(&[Ability::Wisdom]),
+            ClassType::Fighter => {
+                MulticlassPrerequisite::AnyOf(&[Ability::Strength, Ability::Dexterity])
+            }
+            ClassType::Monk => {
+                MulticlassPrerequisite::AllOf(&[Ability::Dexterity, Ability::Wisdom])
+            }
+            ClassType::Paladin => {
+                MulticlassPrerequisite::AllOf(&[Ability::Strength, Ability::Charisma])
+            }
+            ClassType::Ranger => {
+                MulticlassPrerequisite::AllOf(&[Ability::Dexterity, Ability::Wisdom])
+            }
+            ClassType::Rogue => MulticlassPrerequisite::AllOf(&[Ability::Dexterity]),
+            ClassType::Sorceror => MulticlassPrerequisite::AllOf(&[Ability::Charisma]),
+            ClassType::Warlock => MulticlassPrerequisite::AllOf(&[Ability::Charisma]),
+            ClassType::Wizard => MulticlassPrerequisite::AllOf(&[Ability::Intelligence]),
+        }
+    }
+
+    pub fn is_met_by(&self, ability_scores: &AbilitySet) -> bool {
+        let meets_minimum =
+            |ability: &Ability| ability_scores[*ability].score >= MULTICLASS_ABILITY_SCORE_MIN;
+        match self {
+            MulticlassPrerequisite::AllOf(abilities) => abilities.iter().all(meets_minimum),
+            MulticlassPrerequisite::AnyOf(abilities) => abilities.iter().any(meets_minimum),
+        }
+    }
+}
+
+impl fmt::Display for MulticlassPrerequisite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (abilities, separator) = match self {
+            MulticlassPrerequisite::AllOf(abilities) => (abilities, " and "),
+            MulticlassPrerequisite::AnyOf(abilities) => (abilities, " or "),
+        };
+        let requirements: Vec<String> = abilities
+            .iter()
+            .map(|ability| format!("{:?} {}", ability, MULTICLASS_ABILITY_SCORE_MIN))
+            .collect();
+        write!(f, "{}", requirements.join(separator))
+    }
+}
+
+// The subset of a class's proficiencies that is granted when it isn't the
+// character's first class. Saving throw proficiencies are never granted.
+#[derive(Debug, PartialEq)]
+pub struct MulticlassProficiencies {
+    pub armor: &'static [ArmorCategory],
+    pub weapons: &'static [WeaponCategory],
+    pub named_weapons: &'static [&'static str],
+    pub skill_choices: u8,
+}
+
+impl MulticlassProficiencies {
+    pub fn for_class(class_type: ClassType) -> MulticlassProficiencies {
+        use ArmorCategory::{LightArmor, MediumArmor};
+        use WeaponCategory::{MartialWeapons, Shields, SimpleWeapons};
+
+        let (armor, weapons, skill_choices): (&[ArmorCategory], &[WeaponCategory], u8) =
+            match class_type {
+                ClassType::Barbarian => (&[], &[Shields, SimpleWeapons, MartialWeapons], 0),
+                ClassType::Bard => (&[LightArmor], &[], 1),
+                ClassType::Cleric => (&[LightArmor, MediumArmor], &[Shields], 0),
+                ClassType::Druid => (&[LightArmor, MediumArmor], &[Shields], 0),
+                ClassType::Fighter => (
+                    &[LightArmor, MediumArmor],
+                    &[Shields, SimpleWeapons, MartialWeapons],
+                    0,
+                ),
+                ClassType::Monk => (&[], &[SimpleWeapons], 0),
+                ClassType::Paladin => (
+                    &[LightArmor, MediumArmor],
+                    &[Shields, SimpleWeapons, MartialWeapons],
+                    0,
+                ),
+                ClassType::Ranger => (
+                    &[LightArmor, MediumArmor],
+                    &[Shields, SimpleWeapons, MartialWeapons],
+                    1,
+                ),
+                ClassType::Rogue => (&[LightArmor], &[], 1),
+                ClassType::Sorceror => (&[], &[], 0),
+                ClassType::Warlock => (&[LightArmor], &[SimpleWeapons], 0),
+                ClassType::Wizard => (&[], &[], 0),
+            };
+
+        // The monk is the only class that passes on a single weapon.
+        let named_weapons: &[&str] = match class_type {
+            ClassType::Monk => &["Shortsword"],
+            _ => &[],
+        };
+
+        MulticlassProficiencies {
+            armor,
+            weapons,
+            named_weapons,
+            skill_choices,
+        }
+    }
+
+    // Proficiencies with single weapons, such as the bard's rapier, are only
+    // granted when they are listed, like the monk's shortsword.
+    fn restrict(&self, class: &mut Class) {
+        let features = &mut class.features;
+        features
+            .armor_proficiency_modifiers
+            .retain(|modifier| self.armor.contains(&modifier.value));
+        features
+            .weapon_proficiency_modifiers
+            .retain(|modifier| match &modifier.value {
+                WeaponProficiency::Category(category) => self.weapons.contains(category),
+                WeaponProficiency::Weapon(name) => self.named_weapons.contains(&name.as_str()),
+            });
+        features.saving_throw_proficiencies.clear();
+    }
+}
+
+// Records which class level granted a skill proficiency, so that losing the
+// level can take the skill away again.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SkillChoice {
+    pub class_type: ClassType,
+    pub level: u32,
+    pub skill: Skill,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SpellcastingProgression {
+    None,
+    Full,
+    Half,
+    PactMagic,
+}
+
+impl SpellcastingProgression {
+    pub fn for_class(class_type: ClassType) -> SpellcastingProgression {
+        match class_type {
+            ClassType::Bard
+            | ClassType::Cleric
+            | ClassType::Druid
+            | ClassType::Sorceror
+            | ClassType::Wizard => SpellcastingProgression::Full,
+            ClassType::Paladin | ClassType::Ranger => SpellcastingProgression::Half,
+            ClassType::Warlock => SpellcastingProgression::PactMagic,
+            ClassType::Barbarian | ClassType::Fighter | ClassType::Monk | ClassType::Rogue => {
+                SpellcastingProgression::None
+            }
+        }
+    }
+
+    // Pact Magic slots are tracked separately and don't count towards the
+    // multiclass spellcaster table.
+    fn caster_levels(&self, class_level: u32) -> u32 {
+        match self {
+            SpellcastingProgression::Full => class_level,
+            SpellcastingProgression::Half => class_level / 2,
+            SpellcastingProgression::None | SpellcastingProgression::PactMagic => 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MulticlassError {
+    NoLevelAvailable {
+        level: u32,
+        class_levels: u32,
+    },
+    AbilityScoreImprovementPending {
+        class_type: ClassType,
+        level: u32,
+    },
+    MissingClass(ClassType),
+    AlreadyHasClass(ClassType),
+    PrerequisiteNotMet {
+        class_type: ClassType,
+        prerequisite: MulticlassPrerequisite,
+    },
+    SkillChoices {
+        expected: u8,
+        chosen: usize,
+    },
+    KnownSkill(Skill),
+}
+
+impl fmt::Display for MulticlassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MulticlassError::NoLevelAvailable {
+                level,
+                class_levels,
+            } => write!(
+                f,
+                "a level {} character has already spent {} class levels",
+                level, class_levels
+            ),
+            MulticlassError::AbilityScoreImprovementPending { class_type, level } => write!(
+                f,
+                "the Ability Score Improvement from {:?} level {} hasn't been resolved",
+                class_type, level
+            ),
+            MulticlassError::MissingClass(class_type) => {
+                write!(f, "the character has no levels in {:?}", class_type)
+            }
+            MulticlassError::AlreadyHasClass(class_type) => {
+                write!(f, "the character already has levels in {:?}", class_type)
+            }
+            MulticlassError::PrerequisiteNotMet {
+                class_type,
+                prerequisite,
+            } => write!(
+                f,
+                "multiclassing with {:?} requires {}",
+                class_type, prerequisite
+            ),
+            MulticlassError::SkillChoices { expected, chosen } => write!(
+                f,
+                "the class grants {} skills when multiclassing, but {} were chosen",
+                expected, chosen
+            ),
+            MulticlassError::KnownSkill(skill) => {
+                write!(f, "the character is already proficient in {:?}", skill)
+            }
+        }
+    }
+}
+
+impl Character {
+    pub fn get_class_level(&self, class_type: ClassType) -> u32 {
+        self.class
+            .iter()
+            .find(|class| class.class_type == class_type)
+            .map_or(0, |class| class.level)
+    }
+
+    pub fn get_total_class_levels(&self) -> u32 {
+        self.class.iter().map(|class| class.level).sum()
+    }
+
+    // A new level is only available once every Ability Score Improvement from
+    // earlier levels has been resolved.
+    fn check_level_available(&self) -> Result<(), MulticlassError> {
+        if let Some(pending) = self.get_pending_ability_score_improvements().first() {
+            return Err(MulticlassError::AbilityScoreImprovementPending {
+                class_type: pending.class_type,
+                level: pending.level,
+            });
+        }
+
+        let level = self.get_current_level();
+        let class_levels = self.get_total_class_levels();
+        if class_levels >= level {
+            return Err(MulticlassError::NoLevelAvailable {
+                level,
+                class_levels,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Spends a level earned through experience on a class the character already
+    // has, and returns the new level in that class.
+    pub fn level_up_in(
+        &mut self,
+        class_type: ClassType,
+        roller: &mut dyn Roller,
+    ) -> Result<u32, MulticlassError> {
+        self.check_level_available()?;
+        let class = self
+            .class
+            .iter_mut()
+            .find(|class| class.class_type == class_type)
+            .ok_or(MulticlassError::MissingClass(class_type))?;
+        class.level += 1;
+        let class_level = class.level;
+        self.advance_class_level(class_type, roller);
+
+        Ok(class_level)
+    }
+
+    // Spends a level earned through experience on a new class. The character has
+    // to meet the prerequisites of the new class and of every class it already
+    // has, and only gains the new class's multiclass proficiencies. Skills are
+    // chosen for the classes that grant one, and have to be new to the character.
+    pub fn multiclass_into(
+        &mut self,
+        mut class: Class,
+        skills: Vec<Skill>,
+        roller: &mut dyn Roller,
+    ) -> Result<(), MulticlassError> {
+        self.check_level_available()?;
+        if self.get_class_level(class.class_type) > 0 {
+            return Err(MulticlassError::AlreadyHasClass(class.class_type));
+        }
+
+        let class_types = self.class.iter().map(|class| class.class_type);
+        for class_type in class_types.chain(std::iter::once(class.class_type)) {
+            let prerequisite = MulticlassPrerequisite::for_class(class_type);
+            if !prerequisite.is_met_by(&self.ability_scores) {
+                return Err(MulticlassError::PrerequisiteNotMet {
+                    class_type,
+                    prerequisite,
+                });
+            }
+        }
+
+        let proficiencies = MulticlassProficiencies::for_class(class.class_type);
+        if skills.len() != proficiencies.skill_choices as usize {
+            return Err(MulticlassError::SkillChoices {
+                expected: proficiencies.skill_choices,
+                chosen: skills.len(),
+            });
+        }
+        for (index, &skill) in skills.iter().enumerate() {
+            if skills[..index].contains(&skill)
+                || self.get_skill_proficiency(skill, None) >= SkillProficiency::Proficient
+            {
+                return Err(MulticlassError::KnownSkill(skill));
+            }
+        }
+
+        proficiencies.restrict(&mut class);
+        class.level = EFFECTIVE_LEVEL_MIN;
+        let class_type = class.class_type;
+        self.class.push(class);
+        self.advance_class_level(class_type, roller);
+        for &skill in skills.iter() {
+            self.set_skill_proficiency(skill, SkillProficiency::Proficient);
+            self.skill_choices.push(SkillChoice {
+                class_type,
+                level: EFFECTIVE_LEVEL_MIN,
+                skill,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn get_caster_level(&self) -> u32 {
+        self.class
+            .iter()
+            .map(|class| {
+                SpellcastingProgression::for_class(class.class_type).caster_levels(class.level)
+            })
+            .sum()
+    }
+
+    // Spell slots from the multiclass spellcaster table, which matches the
+    // wizard's slots at the combined caster level. Cantrips are known per class,
+    // so there are no slots for spell level 0.
+    pub fn get_multiclass_spell_slots(&self, spell_level: u8) -> u8 {
+        if spell_level == 0 {
+            return 0;
+        }
+
+        find_spell_splots_for_spell_level(
+            WIZARD_SPELL_SLOTS_PER_SPELL_LEVEL,
+            self.get_caster_level(),
+            spell_level,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backgrounds::BackgroundType;
+    use crate::dice::{Die, D6};
+    use crate::feats::AbilityScoreImprovementChoice;
+    use crate::roller::ScriptedRoller;
+    use crate::tests::build_character;
+    use crate::{
+        load_classes_from_file, load_weapons_from_file, ArmorProficiencyModifier, ClassFeatures,
+        RaceType,
+    };
+
+    fn armor_proficiency(category: ArmorCategory) -> ArmorProficiencyModifier {
+        ArmorProficiencyModifier {
+            name: format!("{:?}", category),
+            value: category,
+        }
+    }
+
+    fn class(class_type: ClassType, hit_dice: Die) -> Class {
+        Class {
+            class_type,
+            level: 1,
+            levels: vec![],
+            features: ClassFeatures {
+                hit_dice,
+                hit_points_starting: hit_dice.max,
+                hit_points_from_level: hit_dice,
+                weapon_proficiency_modifiers: vec![],
+                armor_proficiency_modifiers: vec![
+                    armor_proficiency(ArmorCategory::LightArmor),
+                    armor_proficiency(ArmorCategory::MediumArmor),
+                    armor_proficiency(ArmorCategory::HeavyArmor),
+                ],
+                saving_throw_proficiencies: vec![Ability::Strength, Ability::Charisma],
+            },
+        }
+    }
+
+    fn load_class(class_type: ClassType) -> Class {
+        load_classes_from_file("data/classes.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|class| class.class_type == class_type)
+            .unwrap()
+    }
+
+    fn build_paladin(experience_points: u64) -> Character {
+        let mut character = build_character(
+            RaceType::Human,
+            ClassType::Paladin,
+            &[
+                (Ability::Strength, 15),
+                (Ability::Dexterity, 8),
+                (Ability::Constitution, 13),
+                (Ability::Intelligence, 12),
+                (Ability::Wisdom, 10),
+                (Ability::Charisma, 14),
+            ],
+            BackgroundType::Soldier,
+        );
+        character.experience_points = experience_points;
+        character
+    }
+
+    #[test]
+    fn multiclassing_checks_every_prerequisite() {
+        let mut character = build_paladin(300);
+        let mut roller = ScriptedRoller::new(vec![]);
+
+        let error = character
+            .multiclass_into(class(ClassType::Monk, D6), vec![], &mut roller)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "multiclassing with Monk requires Dexterity 13 and Wisdom 13"
+        );
+
+        let charisma = character
+            .ability_scores
+            .0
+            .iter_mut()
+            .find(|ability_score| ability_score.ability == Ability::Charisma)
+            .unwrap();
+        charisma.score = 12;
+        let error = character
+            .multiclass_into(class(ClassType::Wizard, D6), vec![], &mut roller)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            MulticlassError::PrerequisiteNotMet {
+                class_type: ClassType::Paladin,
+                prerequisite: MulticlassPrerequisite::for_class(ClassType::Paladin),
+            }
+        );
+        assert_eq!(character.class.len(), 1);
+    }
+
+    #[test]
+    fn level_up_picks_the_class_and_limits_proficiencies() {
+        let mut character = build_paladin(900);
+        let mut roller = ScriptedRoller::new(vec![]);
+        assert_eq!(
+            character.level_up_in(ClassType::Paladin, &mut roller),
+            Ok(2)
+        );
+        assert_eq!(
+            character.level_up_in(ClassType::Wizard, &mut roller),
+            Err(MulticlassError::MissingClass(ClassType::Wizard))
+        );
+
+        character
+            .multiclass_into(class(ClassType::Wizard, D6), vec![], &mut roller)
+            .unwrap();
+        assert_eq!(
+            character.multiclass_into(class(ClassType::Wizard, D6), vec![], &mut roller),
+            Err(MulticlassError::NoLevelAvailable {
+                level: 3,
+                class_levels: 3
+            })
+        );
+        assert_eq!(character.get_class_level(ClassType::Paladin), 2);
+        assert_eq!(character.get_class_level(ClassType::Wizard), 1);
+
+        let wizard = &character.class[1];
+        assert!(wizard.features.armor_proficiency_modifiers.is_empty());
+        assert!(wizard.features.saving_throw_proficiencies.is_empty());
+        assert!(!character.is_proficient_in_saving_throw(Ability::Intelligence));
+    }
+
+    #[test]
+    fn caster_level_combines_full_and_half_casters() {
+        let mut character = build_paladin(355000);
+        let mut roller = ScriptedRoller::new(vec![]);
+        for _ in 0..3 {
+            character
+                .level_up_in(ClassType::Paladin, &mut roller)
+                .unwrap();
+        }
+        character
+            .resolve_ability_score_improvement(AbilityScoreImprovementChoice::IncreaseTwo(
+                Ability::Strength,
+                Ability::Charisma,
+            ))
+            .unwrap();
+        character
+            .level_up_in(ClassType::Paladin, &mut roller)
+            .unwrap();
+        character
+            .multiclass_into(class(ClassType::Wizard, D6), vec![], &mut roller)
+            .unwrap();
+        character
+            .level_up_in(ClassType::Wizard, &mut roller)
+            .unwrap();
+        character
+            .level_up_in(ClassType::Wizard, &mut roller)
+            .unwrap();
+
+        assert_eq!(character.get_caster_level(), 5);
+        assert_eq!(character.get_multiclass_spell_slots(0), 0);
+        assert_eq!(character.get_multiclass_spell_slots(1), 4);
+        assert_eq!(character.get_multiclass_spell_slots(3), 2);
+        assert_eq!(character.get_multiclass_spell_slots(4), 0);
+    }
+
+    #[test]
+    fn multiclassing_grants_shields_and_skills() {
+        let mut character = build_character(
+            RaceType::Human,
+            ClassType::Wizard,
+            &[
+                (Ability::Strength, 15),
+                (Ability::Dexterity, 8),
+                (Ability::Constitution, 13),
+                (Ability::Intelligence, 12),
+                (Ability::Wisdom, 10),
+                (Ability::Charisma, 14),
+            ],
+            BackgroundType::Soldier,
+        );
+        character.experience_points = 900;
+        let mut roller = ScriptedRoller::new(vec![]);
+        assert!(!character.has_shield_proficiency());
+
+        assert_eq!(
+            character.multiclass_into(load_class(ClassType::Bard), vec![], &mut roller),
+            Err(MulticlassError::SkillChoices {
+                expected: 1,
+                chosen: 0
+            })
+        );
+        assert_eq!(
+            character.multiclass_into(
+                load_class(ClassType::Bard),
+                vec![Skill::Athletics],
+                &mut roller
+            ),
+            Err(MulticlassError::KnownSkill(Skill::Athletics))
+        );
+        character
+            .multiclass_into(
+                load_class(ClassType::Bard),
+                vec![Skill::Performance],
+                &mut roller,
+            )
+            .unwrap();
+        assert_eq!(
+            character.get_skill_proficiency(Skill::Performance, None),
+            SkillProficiency::Proficient
+        );
+        assert!(character.class[1]
+            .features
+            .weapon_proficiency_modifiers
+            .is_empty());
+
+        let wisdom = character
+            .ability_scores
+            .0
+            .iter_mut()
+            .find(|ability_score| ability_score.ability == Ability::Wisdom)
+            .unwrap();
+        wisdom.score = 13;
+        character
+            .multiclass_into(load_class(ClassType::Cleric), vec![], &mut roller)
+            .unwrap();
+        let cleric = &character.class[2];
+        assert_eq!(cleric.features.weapon_proficiency_modifiers.len(), 1);
+        assert_eq!(cleric.features.armor_proficiency_modifiers.len(), 2);
+        assert!(character.has_shield_proficiency());
+    }
+
+    #[test]
+    fn multiclassing_into_monk_keeps_the_shortsword() {
+        let mut character = build_character(
+            RaceType::Human,
+            ClassType::Wizard,
+            &[
+                (Ability::Strength, 8),
+                (Ability::Dexterity, 14),
+                (Ability::Constitution, 13),
+                (Ability::Intelligence, 15),
+                (Ability::Wisdom, 12),
+                (Ability::Charisma, 10),
+            ],
+            BackgroundType::Sage,
+        );
+        character.experience_points = 300;
+        let weapons = load_weapons_from_file("data/weapons.yaml").unwrap();
+        let find_weapon = |name: &str| weapons.iter().find(|weapon| weapon.name == name).unwrap();
+        assert!(!character.has_weapon_proficiency(find_weapon("Shortsword")));
+
+        character
+            .multiclass_into(
+                load_class(ClassType::Monk),
+                vec![],
+                &mut ScriptedRoller::new(vec![]),
+            )
+            .unwrap();
+        let monk = &character.class[1];
+        assert_eq!(monk.features.weapon_proficiency_modifiers.len(), 2);
+        assert!(character.has_weapon_proficiency(find_weapon("Shortsword")));
+        assert!(character.has_weapon_proficiency(find_weapon("Handaxe")));
+        assert!(!character.has_weapon_proficiency(find_weapon("Longsword")));
+    }
+}
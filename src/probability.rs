@@ -0,0 +1,439 @@
+use std::fmt;
+
+use crate::d20::{RollMode, DEFAULT_CRITICAL_THRESHOLD};
+use crate::dice::{DiceExpression, DiceGroup, Die, KeepRule, TermValue, D20};
+
+// Every distribution is built by spreading each probability on one side over
+// the other, so the number of those steps bounds how long the odds take.
+const MAX_DISTRIBUTION_STEPS: u64 = 100_000_000;
+
+#[derive(Debug, PartialEq)]
+pub enum DistributionError {
+    TooManySteps(u64),
+}
+
+impl fmt::Display for DistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DistributionError::TooManySteps(steps) => write!(
+                f,
+                "{} steps is too many to compute exactly (limit is {})",
+                steps, MAX_DISTRIBUTION_STEPS
+            ),
+        }
+    }
+}
+
+// Counts the steps spent on one expression so that oversized expressions are
+// refused up front, before any of the work is done.
+struct StepBudget {
+    steps: u64,
+}
+
+impl StepBudget {
+    fn new() -> StepBudget {
+        StepBudget { steps: 0 }
+    }
+
+    fn spend(&mut self, steps: u64) -> Result<(), DistributionError> {
+        self.steps = self.steps.saturating_add(steps);
+        if self.steps > MAX_DISTRIBUTION_STEPS {
+            return Err(DistributionError::TooManySteps(self.steps));
+        }
+        Ok(())
+    }
+}
+
+// An exact probability mass function over integer totals, starting at `min`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbabilityDistribution {
+    min: i32,
+    probabilities: Vec<f64>,
+}
+
+impl ProbabilityDistribution {
+    pub fn constant(value: i32) -> ProbabilityDistribution {
+        ProbabilityDistribution {
+            min: value,
+            probabilities: vec![1.0],
+        }
+    }
+
+    pub fn from_die(die: Die) -> ProbabilityDistribution {
+        let sides = die.sides() as usize;
+        ProbabilityDistribution {
+            min: die.min as i32,
+            probabilities: vec![1.0 / sides as f64; sides],
+        }
+    }
+
+    pub fn from_expression(
+        expression: &DiceExpression,
+    ) -> Result<ProbabilityDistribution, DistributionError> {
+        let mut budget = StepBudget::new();
+        let mut distribution = ProbabilityDistribution::constant(0);
+        for term in expression.terms.iter() {
+            let term_distribution = match term.value {
+                TermValue::Flat(value) => ProbabilityDistribution::constant(value),
+                TermValue::Dice(group) => dice_group_distribution(group, &mut budget)?,
+            };
+            budget.spend(
+                distribution.probabilities.len() as u64
+                    * term_distribution.probabilities.len() as u64,
+            )?;
+            distribution = if term.negative {
+                distribution.add(&term_distribution.negate())
+            } else {
+                distribution.add(&term_distribution)
+            };
+        }
+
+        Ok(distribution)
+    }
+
+    pub fn d20(modifier: i16, roll_mode: RollMode) -> ProbabilityDistribution {
+        let natural = match roll_mode {
+            RollMode::Normal => ProbabilityDistribution::from_die(D20),
+            RollMode::Advantage => from_kept_dice(2, D20, KeepRule::KeepHighest(1)),
+            RollMode::Disadvantage => from_kept_dice(2, D20, KeepRule::KeepLowest(1)),
+        };
+
+        natural.add(&ProbabilityDistribution::constant(modifier as i32))
+    }
+
+    pub fn min(&self) -> i32 {
+        self.min
+    }
+
+    pub fn max(&self) -> i32 {
+        self.min + self.probabilities.len() as i32 - 1
+    }
+
+    pub fn probability_of(&self, total: i32) -> f64 {
+        if total < self.min || total > self.max() {
+            return 0.0;
+        }
+        self.probabilities[(total - self.min) as usize]
+    }
+
+    pub fn probability_at_least(&self, difficulty_class: i32) -> f64 {
+        self.pmf()
+            .filter(|&(total, _)| total >= difficulty_class)
+            .map(|(_, probability)| probability)
+            .sum()
+    }
+
+    pub fn expected_value(&self) -> f64 {
+        self.pmf()
+            .map(|(total, probability)| total as f64 * probability)
+            .sum()
+    }
+
+    pub fn pmf(&self) -> impl Iterator<Item = (i32, f64)> + '_ {
+        self.probabilities
+            .iter()
+            .enumerate()
+            .map(move |(index, &probability)| (self.min + index as i32, probability))
+    }
+
+    pub fn add(&self, other: &ProbabilityDistribution) -> ProbabilityDistribution {
+        let mut probabilities = vec![0.0; self.probabilities.len() + other.probabilities.len() - 1];
+        for (i, left) in self.probabilities.iter().enumerate() {
+            for (j, right) in other.probabilities.iter().enumerate() {
+                probabilities[i + j] += left * right;
+            }
+        }
+
+        ProbabilityDistribution {
+            min: self.min + other.min,
+            probabilities,
+        }
+    }
+
+    pub fn negate(&self) -> ProbabilityDistribution {
+        ProbabilityDistribution {
+            min: -self.max(),
+            probabilities: self.probabilities.iter().rev().copied().collect(),
+        }
+    }
+}
+
+fn dice_group_distribution(
+    group: DiceGroup,
+    budget: &mut StepBudget,
+) -> Result<ProbabilityDistribution, DistributionError> {
+    let count = group.count as u64;
+    let sides = group.die.sides() as u64;
+    match group.keep {
+        None => {
+            // Adding the i-th die spreads i * (sides - 1) + 1 totals over every face.
+            budget.spend(sides.saturating_mul(count.saturating_add(
+                (sides - 1).saturating_mul(count * count.saturating_sub(1) / 2),
+            )))?;
+            Ok(
+                (0..group.count).fold(ProbabilityDistribution::constant(0), |sum, _| {
+                    sum.add(&ProbabilityDistribution::from_die(group.die))
+                }),
+            )
+        }
+        Some(keep) => {
+            // For every face, each (dice placed, kept sum) state can place any
+            // number of the remaining dice on it.
+            let sums = (group.kept_count() as u64).saturating_mul(sides - 1) + 1;
+            budget.spend(
+                sides
+                    .saturating_mul((count + 1) * (count + 2) / 2)
+                    .saturating_mul(sums),
+            )?;
+            Ok(from_kept_dice(group.count, group.die, keep))
+        }
+    }
+}
+
+// Works through the faces from the kept end, deciding how many of the remaining
+// dice land on each one. The first dice placed are the ones that are kept, so
+// only the number of dice placed so far and the kept sum need to be tracked.
+fn from_kept_dice(count: u16, die: Die, keep: KeepRule) -> ProbabilityDistribution {
+    let count = count as usize;
+    let kept_count = DiceGroup {
+        count: count as u16,
+        die,
+        keep: Some(keep),
+    }
+    .kept_count() as usize;
+    let sides = die.sides() as usize;
+    let faces: Vec<usize> = match keep {
+        KeepRule::KeepHighest(_) | KeepRule::DropLowest(_) => (0..sides).rev().collect(),
+        KeepRule::KeepLowest(_) | KeepRule::DropHighest(_) => (0..sides).collect(),
+    };
+
+    let mut log_factorials = vec![0.0; count + 1];
+    for n in 1..=count {
+        log_factorials[n] = log_factorials[n - 1] + (n as f64).ln();
+    }
+
+    // states[placed][kept sum above the minimum face]
+    let sums = kept_count * (sides - 1) + 1;
+    let mut states = vec![vec![0.0; sums]; count + 1];
+    states[0][0] = 1.0;
+
+    for (index, &face) in faces.iter().enumerate() {
+        // Every die that hasn't been placed yet lands on this face with the same
+        // chance as on any of the faces still to come.
+        let faces_left = sides - index;
+        let mut next = vec![vec![0.0; sums]; count + 1];
+        for placed in 0..=count {
+            let remaining = count - placed;
+            let kept_left = kept_count.saturating_sub(placed);
+            for sum in 0..sums {
+                let probability = states[placed][sum];
+                if probability == 0.0 {
+                    continue;
+                }
+                for here in 0..=remaining {
+                    let chance = if faces_left == 1 {
+                        if here < remaining {
+                            continue;
+                        }
+                        1.0
+                    } else {
+                        let p = 1.0 / faces_left as f64;
+                        (log_factorials[remaining]
+                            - log_factorials[here]
+                            - log_factorials[remaining - here]
+                            + here as f64 * p.ln()
+                            + (remaining - here) as f64 * (1.0 - p).ln())
+                        .exp()
+                    };
+                    next[placed + here][sum + here.min(kept_left) * face] += probability * chance;
+                }
+            }
+        }
+        states = next;
+    }
+
+    ProbabilityDistribution {
+        min: kept_count as i32 * die.min as i32,
+        probabilities: states
+            .pop()
+            .expect("There is always a state for every die placed"),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DamagePerRoundReport {
+    pub hit_probability: f64,
+    pub critical_probability: f64,
+    pub expected_damage_on_hit: f64,
+    pub expected_damage_on_critical: f64,
+    pub expected_damage_per_round: f64,
+}
+
+pub struct AttackProfile<'a> {
+    pub attack_bonus: i16,
+    pub roll_mode: RollMode,
+    pub critical_threshold: u16,
+    pub critical_extra_dice: u16,
+    pub damage: &'a DiceExpression,
+}
+
+impl<'a> AttackProfile<'a> {
+    pub fn new(attack_bonus: i16, damage: &'a DiceExpression) -> AttackProfile<'a> {
+        AttackProfile {
+            attack_bonus,
+            roll_mode: RollMode::Normal,
+            critical_threshold: DEFAULT_CRITICAL_THRESHOLD,
+            critical_extra_dice: 0,
+            damage,
+        }
+    }
+}
+
+// A natural 1 always misses and a natural roll in the critical range always hits
+// for the critical damage; anything else hits when it meets the target's AC.
+pub fn calculate_damage_per_round(
+    attack: &AttackProfile,
+    target_armor_class: i32,
+) -> Result<DamagePerRoundReport, DistributionError> {
+    let natural = ProbabilityDistribution::d20(0, attack.roll_mode);
+    let mut hit_probability = 0.0;
+    let mut critical_probability = 0.0;
+    for (roll, probability) in natural.pmf() {
+        if roll >= attack.critical_threshold as i32 {
+            critical_probability += probability;
+        } else if roll > 1 && roll + attack.attack_bonus as i32 >= target_armor_class {
+            hit_probability += probability;
+        }
+    }
+
+    let expected_damage_on_hit =
+        ProbabilityDistribution::from_expression(attack.damage)?.expected_value();
+    let expected_damage_on_critical = ProbabilityDistribution::from_expression(
+        &attack.damage.critical(attack.critical_extra_dice),
+    )?
+    .expected_value();
+
+    Ok(DamagePerRoundReport {
+        hit_probability: hit_probability + critical_probability,
+        critical_probability,
+        expected_damage_on_hit,
+        expected_damage_on_critical,
+        expected_damage_per_round: hit_probability * expected_damage_on_hit
+            + critical_probability * expected_damage_on_critical,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{} != {}", left, right);
+    }
+
+    #[test]
+    fn distribution_of_summed_dice() {
+        let distribution =
+            ProbabilityDistribution::from_expression(&"2d6 + 3".parse().unwrap()).unwrap();
+
+        assert_eq!(distribution.min(), 5);
+        assert_eq!(distribution.max(), 15);
+        assert_close(distribution.probability_of(10), 6.0 / 36.0);
+        assert_close(distribution.expected_value(), 10.0);
+        assert_close(
+            distribution.pmf().map(|(_, probability)| probability).sum(),
+            1.0,
+        );
+    }
+
+    #[test]
+    fn distribution_of_kept_dice() {
+        let distribution =
+            ProbabilityDistribution::from_expression(&"4d6kh3".parse().unwrap()).unwrap();
+
+        assert_eq!(distribution.min(), 3);
+        assert_close(distribution.probability_of(18), 21.0 / 1296.0);
+        assert_close(distribution.expected_value(), 15869.0 / 1296.0);
+    }
+
+    #[test]
+    fn d20_odds_with_advantage() {
+        let normal = ProbabilityDistribution::d20(5, RollMode::Normal);
+        assert_close(normal.probability_at_least(15), 0.55);
+
+        // P(at least one of two d20s is 10 or more) = 1 - (9/20)^2
+        let advantage = ProbabilityDistribution::d20(5, RollMode::Advantage);
+        assert_close(advantage.probability_at_least(15), 1.0 - 0.45 * 0.45);
+
+        let disadvantage = ProbabilityDistribution::d20(5, RollMode::Disadvantage);
+        assert_close(disadvantage.probability_at_least(15), 0.55 * 0.55);
+    }
+
+    #[test]
+    fn damage_per_round() {
+        let damage: DiceExpression = "1d8 + 3".parse().unwrap();
+        let report = calculate_damage_per_round(&AttackProfile::new(5, &damage), 15).unwrap();
+
+        assert_close(report.hit_probability, 0.55);
+        assert_close(report.critical_probability, 0.05);
+        assert_close(report.expected_damage_on_hit, 7.5);
+        assert_close(report.expected_damage_on_critical, 12.0);
+        assert_close(report.expected_damage_per_round, 0.50 * 7.5 + 0.05 * 12.0);
+
+        // A natural 1 still misses even when the bonus alone beats the AC.
+        let report = calculate_damage_per_round(&AttackProfile::new(20, &damage), 5).unwrap();
+        assert_close(report.hit_probability, 0.95);
+
+        let brutal_champion = AttackProfile {
+            critical_threshold: 19,
+            critical_extra_dice: 1,
+            ..AttackProfile::new(5, &damage)
+        };
+        let report = calculate_damage_per_round(&brutal_champion, 15).unwrap();
+        assert_close(report.critical_probability, 0.10);
+        assert_close(report.expected_damage_on_critical, 16.5);
+    }
+
+    #[test]
+    fn distribution_of_many_kept_dice() {
+        let distribution =
+            ProbabilityDistribution::from_expression(&"9d6kh3".parse().unwrap()).unwrap();
+
+        // 18 needs at least three sixes among the nine dice.
+        let at_most_two_sixes: f64 = (0..3)
+            .map(|sixes| {
+                let ways = [1.0, 9.0, 36.0][sixes];
+                ways * (1.0_f64 / 6.0).powi(sixes as i32) * (5.0_f64 / 6.0).powi(9 - sixes as i32)
+            })
+            .sum();
+        assert_eq!(distribution.min(), 3);
+        assert_eq!(distribution.max(), 18);
+        assert_close(distribution.probability_of(18), 1.0 - at_most_two_sixes);
+        assert_close(distribution.probability_of(3), (1.0_f64 / 6.0).powi(9));
+        assert_close(
+            distribution.pmf().map(|(_, probability)| probability).sum(),
+            1.0,
+        );
+
+        let dropped = ProbabilityDistribution::from_expression(&"4d6dl1".parse().unwrap()).unwrap();
+        let kept = ProbabilityDistribution::from_expression(&"4d6kh3".parse().unwrap()).unwrap();
+        for ((total, left), (_, right)) in dropped.pmf().zip(kept.pmf()) {
+            assert_close(left, right);
+            assert_eq!(dropped.probability_of(total), left);
+        }
+
+        let lowest = ProbabilityDistribution::from_expression(&"3d4kl1".parse().unwrap()).unwrap();
+        assert_close(lowest.probability_of(4), 1.0 / 64.0);
+        assert_close(lowest.probability_of(1), 1.0 - 27.0 / 64.0);
+    }
+
+    #[test]
+    fn refuse_oversized_expressions() {
+        for expression in ["1000d1000", "60000d60000", "1000d100kh500"] {
+            assert!(matches!(
+                ProbabilityDistribution::from_expression(&expression.parse().unwrap()),
+                Err(DistributionError::TooManySteps(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{Race, RaceCatalogue, RaceType, Subrace};
+
+impl Subrace {
+    // The parent's traits come first, followed by everything the subrace adds.
+    pub fn resolve(&self, parent: &Race) -> Race {
+        let mut racial_traits = parent.racial_traits.clone();
+        racial_traits
+            .ability_score_increase
+            .extend(self.ability_score_increase.iter().copied());
+        for &language in self.languages.iter() {
+            if !racial_traits.languages.contains(&language) {
+                racial_traits.languages.push(language);
+            }
+        }
+        racial_traits.traits.extend(self.traits.iter().cloned());
+
+        Race {
+            race_type: self.race_type,
+            parent: Some(parent.race_type),
+            racial_traits,
+        }
+    }
+}
+
+impl RaceCatalogue {
+    // Finds a race or subrace, with subraces resolved against their parent.
+    pub fn find_race(&self, race_type: RaceType) -> Option<Race> {
+        if let Some(race) = self.races.iter().find(|race| race.race_type == race_type) {
+            return Some(race.clone());
+        }
+
+        let subrace = self
+            .subraces
+            .iter()
+            .find(|subrace| subrace.race_type == race_type)?;
+        let parent = self
+            .races
+            .iter()
+            .find(|race| race.race_type == subrace.parent)?;
+        Some(subrace.resolve(parent))
+    }
+
+    pub fn list_subraces(&self, parent: RaceType) -> Vec<RaceType> {
+        self.subraces
+            .iter()
+            .filter(|subrace| subrace.parent == parent)
+            .map(|subrace| subrace.race_type)
+            .collect()
+    }
+
+    pub fn find_subrace_without_parent(&self) -> Option<&Subrace> {
+        self.subraces.iter().find(|subrace| {
+            !self
+                .races
+                .iter()
+                .any(|race| race.race_type == subrace.parent)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_races_from_file, load_races_from_str, Ability, AbilityScoreIncrease};
+
+    #[test]
+    fn subraces_layer_their_traits_on_the_parent() {
+        let catalogue = load_races_from_file("data/races.yaml").unwrap();
+        let hill_dwarf = catalogue.find_race(RaceType::HillDwarf).unwrap();
+
+        assert_eq!(hill_dwarf.parent, Some(RaceType::Dwarf));
+        assert_eq!(hill_dwarf.racial_traits.speed, 25);
+        assert_eq!(
+            hill_dwarf.racial_traits.ability_score_increase,
+            vec![
+                AbilityScoreIncrease {
+                    ability: Ability::Constitution,
+                    value: 2
+                },
+                AbilityScoreIncrease {
+                    ability: Ability::Wisdom,
+                    value: 1
+                },
+            ]
+        );
+        let trait_names: Vec<&str> = hill_dwarf
+            .racial_traits
+            .traits
+            .iter()
+            .map(|racial_trait| racial_trait.name.as_str())
+            .collect();
+        assert!(trait_names.contains(&"Dwarven Resilience"));
+        assert_eq!(trait_names.last(), Some(&"Dwarven Toughness"));
+
+        assert_eq!(
+            catalogue.list_subraces(RaceType::Elf),
+            vec![RaceType::HighElf]
+        );
+        assert!(catalogue.list_subraces(RaceType::Human).is_empty());
+    }
+
+    #[test]
+    fn reject_subraces_with_a_missing_parent() {
+        let mut catalogue = load_races_from_file("data/races.yaml").unwrap();
+        catalogue
+            .races
+            .retain(|race| race.race_type != RaceType::Elf);
+        let serialized = serde_yaml::to_string(&catalogue).unwrap();
+
+        let error = load_races_from_str(&serialized).unwrap_err();
+        assert_eq!(error.to_string(), "subrace HighElf has no parent race Elf");
+    }
+}
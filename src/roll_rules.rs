@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dice::{roll_die, Die};
+use crate::roller::Roller;
+use crate::DamageType;
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum RollRuleTarget {
+    D20,
+    Damage,
+    DamageOfType(DamageType),
+    // Damage from a melee attack with a weapon held in two hands (Great Weapon
+    // Fighting).
+    TwoHandedMeleeDamage,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum RollRuleEffect {
+    // Reroll the die once when it shows this value or lower, and use the new roll
+    // (Halfling Lucky, Great Weapon Fighting).
+    RerollAtOrBelow(u16),
+    // Treat any result lower than this value as this value (Elemental Adept).
+    Minimum(u16),
+    // Roll the die again and add it whenever it shows its highest face.
+    Explode,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RollRule {
+    pub target: RollRuleTarget,
+    pub effect: RollRuleEffect,
+}
+
+// The attack that damage comes from, for features that only apply to some attacks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DamageSource {
+    MeleeAttack { two_handed: bool },
+    RangedAttack,
+    Other,
+}
+
+// What kind of die is being rolled, so that rules only touch the dice they apply to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RollTarget {
+    D20,
+    Damage(Option<DamageType>, DamageSource),
+    Other,
+}
+
+impl RollRuleTarget {
+    fn applies_to(&self, roll_target: RollTarget) -> bool {
+        match (self, roll_target) {
+            (RollRuleTarget::D20, RollTarget::D20) => true,
+            (RollRuleTarget::Damage, RollTarget::Damage(..)) => true,
+            (RollRuleTarget::DamageOfType(damage_type), RollTarget::Damage(Some(rolled), _)) => {
+                *damage_type == rolled
+            }
+            (
+                RollRuleTarget::TwoHandedMeleeDamage,
+                RollTarget::Damage(_, DamageSource::MeleeAttack { two_handed }),
+            ) => two_handed,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DieAdjustment {
+    pub rule: String,
+    pub original: u16,
+    pub replacement: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct RollRules(Vec<(String, RollRule)>);
+
+// Exploding dice stop after this many extra rolls so a scripted or degenerate die can't loop forever.
+// The total saturates at the largest die value rather than overflowing on huge dice.
+const MAX_EXPLOSIONS: u16 = 100;
+
+impl RollRules {
+    pub fn new() -> RollRules {
+        RollRules(vec![])
+    }
+
+    pub fn add(&mut self, name: &str, rule: RollRule) {
+        self.0.push((String::from(name), rule));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Rolls a single die and runs it through every applicable rule, in order.
+    pub fn roll(
+        &self,
+        die: Die,
+        roll_target: RollTarget,
+        roller: &mut dyn Roller,
+    ) -> (u16, Vec<DieAdjustment>) {
+        let mut value = roll_die(die, roller);
+        let mut adjustments = vec![];
+
+        for (name, rule) in self.0.iter() {
+            if !rule.target.applies_to(roll_target) {
+                continue;
+            }
+
+            let replacement = match rule.effect {
+                RollRuleEffect::RerollAtOrBelow(threshold) if value <= threshold => {
+                    roll_die(die, roller)
+                }
+                RollRuleEffect::Minimum(minimum) if value < minimum => minimum,
+                RollRuleEffect::Explode if value == die.max && die.max > die.min => {
+                    let mut total = value;
+                    let mut last = value;
+                    let mut explosions = 0;
+                    while last == die.max && explosions < MAX_EXPLOSIONS {
+                        last = roll_die(die, roller);
+                        total = total.saturating_add(last);
+                        explosions += 1;
+                    }
+                    total
+                }
+                _ => continue,
+            };
+
+            adjustments.push(DieAdjustment {
+                rule: name.clone(),
+                original: value,
+                replacement,
+            });
+            value = replacement;
+        }
+
+        (value, adjustments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::{Die, D20, D6};
+    use crate::roller::ScriptedRoller;
+
+    fn lucky() -> RollRule {
+        RollRule {
+            target: RollRuleTarget::D20,
+            effect: RollRuleEffect::RerollAtOrBelow(1),
+        }
+    }
+
+    #[test]
+    fn reroll_uses_the_new_roll() {
+        let mut rules = RollRules::new();
+        rules.add("Lucky", lucky());
+
+        let mut roller = ScriptedRoller::new(vec![1, 1, 1]);
+        let (value, adjustments) = rules.roll(D20, RollTarget::D20, &mut roller);
+        assert_eq!(value, 1);
+        assert_eq!(
+            adjustments,
+            vec![DieAdjustment {
+                rule: String::from("Lucky"),
+                original: 1,
+                replacement: 1,
+            }]
+        );
+
+        // Lucky doesn't touch damage dice.
+        let (value, adjustments) = rules.roll(
+            D6,
+            RollTarget::Damage(None, DamageSource::Other),
+            &mut roller,
+        );
+        assert_eq!(value, 1);
+        assert!(adjustments.is_empty());
+        assert_eq!(roller.remaining(), 0);
+    }
+
+    #[test]
+    fn minimum_only_applies_to_matching_damage_type() {
+        let mut rules = RollRules::new();
+        rules.add(
+            "Elemental Adept (Fire)",
+            RollRule {
+                target: RollRuleTarget::DamageOfType(DamageType::Fire),
+                effect: RollRuleEffect::Minimum(2),
+            },
+        );
+
+        let mut roller = ScriptedRoller::new(vec![1, 1]);
+        let (fire, _) = rules.roll(
+            D6,
+            RollTarget::Damage(Some(DamageType::Fire), DamageSource::Other),
+            &mut roller,
+        );
+        let (cold, _) = rules.roll(
+            D6,
+            RollTarget::Damage(Some(DamageType::Cold), DamageSource::Other),
+            &mut roller,
+        );
+        assert_eq!(fire, 2);
+        assert_eq!(cold, 1);
+    }
+
+    #[test]
+    fn exploding_dice_keep_rolling_on_the_highest_face() {
+        let mut rules = RollRules::new();
+        rules.add(
+            "Exploding",
+            RollRule {
+                target: RollRuleTarget::Damage,
+                effect: RollRuleEffect::Explode,
+            },
+        );
+
+        let mut roller = ScriptedRoller::new(vec![6, 6, 2]);
+        let (value, adjustments) = rules.roll(
+            D6,
+            RollTarget::Damage(None, DamageSource::Other),
+            &mut roller,
+        );
+        assert_eq!(value, 14);
+        assert_eq!(adjustments[0].original, 6);
+        assert_eq!(adjustments[0].replacement, 14);
+    }
+
+    #[test]
+    fn exploding_huge_dice_saturate() {
+        let mut rules = RollRules::new();
+        rules.add(
+            "Exploding",
+            RollRule {
+                target: RollRuleTarget::Damage,
+                effect: RollRuleEffect::Explode,
+            },
+        );
+
+        let die = Die { min: 1, max: 40000 };
+        let mut roller = ScriptedRoller::new(vec![40000, 40000, 3]);
+        let (value, _) = rules.roll(
+            die,
+            RollTarget::Damage(None, DamageSource::Other),
+            &mut roller,
+        );
+        assert_eq!(value, u16::MAX);
+        assert_eq!(roller.remaining(), 0);
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::dice::Die;
+
+// Every roll in the emulator goes through a Roller so that tests and session
+// replays can reproduce the exact same results.
+pub trait Roller {
+    fn roll(&mut self, die: Die) -> u16;
+}
+
+pub struct RandomRoller;
+
+impl Roller for RandomRoller {
+    fn roll(&mut self, die: Die) -> u16 {
+        let mut rng = rand::thread_rng();
+        Uniform::from(die.min..=die.max).sample(&mut rng)
+    }
+}
+
+pub struct SeededRoller {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SeededRoller {
+    pub fn new(seed: u64) -> SeededRoller {
+        SeededRoller {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Roller for SeededRoller {
+    fn roll(&mut self, die: Die) -> u16 {
+        Uniform::from(die.min..=die.max).sample(&mut self.rng)
+    }
+}
+
+// Hands out a fixed sequence of results, in order, regardless of the die.
+pub struct ScriptedRoller {
+    results: VecDeque<u16>,
+}
+
+impl ScriptedRoller {
+    pub fn new(results: Vec<u16>) -> ScriptedRoller {
+        ScriptedRoller {
+            results: results.into(),
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.results.len()
+    }
+}
+
+impl Roller for ScriptedRoller {
+    fn roll(&mut self, die: Die) -> u16 {
+        let result = self
+            .results
+            .pop_front()
+            .expect("Scripted roller ran out of results");
+        assert!(
+            result >= die.min && result <= die.max,
+            "Scripted result {} can't be rolled on a {}",
+            result,
+            die
+        );
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::D20;
+
+    #[test]
+    fn seeded_rollers_repeat_the_same_rolls() {
+        let mut first = SeededRoller::new(42);
+        let mut second = SeededRoller::new(42);
+
+        let first_rolls: Vec<u16> = (0..50).map(|_| first.roll(D20)).collect();
+        let second_rolls: Vec<u16> = (0..50).map(|_| second.roll(D20)).collect();
+
+        assert_eq!(first_rolls, second_rolls);
+        assert_eq!(first.seed(), 42);
+    }
+
+    #[test]
+    fn scripted_roller_returns_results_in_order() {
+        let mut roller = ScriptedRoller::new(vec![20, 1, 7]);
+
+        assert_eq!(roller.roll(D20), 20);
+        assert_eq!(roller.roll(D20), 1);
+        assert_eq!(roller.remaining(), 1);
+        assert_eq!(roller.roll(D20), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be rolled")]
+    fn scripted_roller_rejects_impossible_results() {
+        let mut roller = ScriptedRoller::new(vec![21]);
+        roller.roll(D20);
+    }
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+
+use crate::d20::{roll_d20_with_rules, D20Roll, D20Test, RollMode};
+use crate::roller::Roller;
+use crate::{Ability, Character, Skill, SkillScore, SkillSet};
+
+pub const SKILLS: [Skill; 18] = [
+    Skill::Athletics,
+    Skill::Acrobatics,
+    Skill::SleightOfHand,
+    Skill::Stealth,
+    Skill::Arcana,
+    Skill::History,
+    Skill::Investigation,
+    Skill::Nature,
+    Skill::Religion,
+    Skill::AnimalHandling,
+    Skill::Insight,
+    Skill::Medicine,
+    Skill::Perception,
+    Skill::Survival,
+    Skill::Deception,
+    Skill::Intimidation,
+    Skill::Performance,
+    Skill::Persuasion,
+];
+
+// Advantage on a check adds 5 to the passive score, and disadvantage subtracts 5.
+const PASSIVE_SCORE_BASE: i16 = 10;
+const PASSIVE_SCORE_ROLL_MODE_ADJUSTMENT: i16 = 5;
+
+const JACK_OF_ALL_TRADES: &str = "Jack of All Trades";
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, PartialOrd, Eq, Ord, Default)]
+pub enum SkillProficiency {
+    #[default]
+    None,
+    Half,
+    Proficient,
+    Expertise,
+}
+
+impl SkillProficiency {
+    // Half proficiency rounds down.
+    pub fn apply_to(&self, proficiency_bonus: u16) -> i8 {
+        let bonus = match self {
+            SkillProficiency::None => 0,
+            SkillProficiency::Half => proficiency_bonus / 2,
+            SkillProficiency::Proficient => proficiency_bonus,
+            SkillProficiency::Expertise => proficiency_bonus * 2,
+        };
+        bonus as i8
+    }
+}
+
+impl Skill {
+    pub fn ability(&self) -> Ability {
+        match self {
+            Skill::Athletics => Ability::Strength,
+            Skill::Acrobatics | Skill::SleightOfHand | Skill::Stealth => Ability::Dexterity,
+            Skill::Arcana
+            | Skill::History
+            | Skill::Investigation
+            | Skill::Nature
+            | Skill::Religion => Ability::Intelligence,
+            Skill::AnimalHandling
+            | Skill::Insight
+            | Skill::Medicine
+            | Skill::Perception
+            | Skill::Survival => Ability::Wisdom,
+            Skill::Deception | Skill::Intimidation | Skill::Performance | Skill::Persuasion => {
+                Ability::Charisma
+            }
+        }
+    }
+}
+
+impl SkillScore {
+    fn new(skill: Skill, proficiency: SkillProficiency) -> SkillScore {
+        SkillScore {
+            skill,
+            ability: skill.ability(),
+            proficiency,
+            modifier: 0,
+            advantage: false,
+            disadvantage: false,
+        }
+    }
+}
+
+impl Default for SkillSet {
+    fn default() -> SkillSet {
+        SkillSet(SKILLS.map(|skill| SkillScore::new(skill, SkillProficiency::None)))
+    }
+}
+
+impl SkillSet {
+    pub fn from_proficiencies(proficiencies: &[Skill]) -> SkillSet {
+        SkillSet(SKILLS.map(|skill| {
+            let proficiency = if proficiencies.contains(&skill) {
+                SkillProficiency::Proficient
+            } else {
+                SkillProficiency::None
+            };
+            SkillScore::new(skill, proficiency)
+        }))
+    }
+
+    fn get_mut(&mut self, skill: Skill) -> &mut SkillScore {
+        self.0
+            .iter_mut()
+            .find(|skill_score| skill_score.skill == skill)
+            .expect("Skill score not found")
+    }
+}
+
+impl Character {
+    // Combines the proficiency the character chose with any granted by traits,
+    // keeping the best. A circumstance, such as "stonework", unlocks traits that
+    // only apply to some checks.
+    pub fn get_skill_proficiency(
+        &self,
+        skill: Skill,
+        circumstance: Option<&str>,
+    ) -> SkillProficiency {
+        let from_traits = self
+            .traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.skill_proficiency_modifiers.iter())
+            .filter(|modifier| modifier.value.skill == skill)
+            .filter(|modifier| match &modifier.value.circumstance {
+                Some(required) => circumstance == Some(required.as_str()),
+                None => true,
+            })
+            .map(|modifier| modifier.value.proficiency);
+        let from_class_features = if self.has_class_feature(JACK_OF_ALL_TRADES) {
+            SkillProficiency::Half
+        } else {
+            SkillProficiency::None
+        };
+
+        from_traits
+            .chain(std::iter::once(from_class_features))
+            .fold(self.skills[skill].proficiency, SkillProficiency::max)
+    }
+
+    // The skills the character is at least proficient in, whether from their
+    // background, their class or their traits.
+    pub fn get_skill_proficiencies(&self) -> Vec<Skill> {
+        self.skills
+            .0
+            .iter()
+            .map(|skill_score| skill_score.skill)
+            .filter(|&skill| {
+                self.get_skill_proficiency(skill, None) >= SkillProficiency::Proficient
+            })
+            .collect()
+    }
+
+    pub fn set_skill_proficiency(&mut self, skill: Skill, proficiency: SkillProficiency) {
+        self.skills.get_mut(skill).proficiency = proficiency;
+        self.refresh_skill_modifiers();
+    }
+
+    pub fn get_skill_modifier(&self, skill: Skill, circumstance: Option<&str>) -> i8 {
+        self.get_ability_modifier(skill.ability())
+            + self
+                .get_skill_proficiency(skill, circumstance)
+                .apply_to(self.get_proficiency_bonus())
+    }
+
+    pub(crate) fn refresh_skill_modifiers(&mut self) {
+        let modifiers = SKILLS.map(|skill| self.get_skill_modifier(skill, None));
+        for (skill_score, modifier) in self.skills.0.iter_mut().zip(modifiers) {
+            skill_score.modifier = modifier;
+        }
+    }
+
+    // Some armor, like plate, imposes disadvantage on Stealth checks while worn.
+    fn get_skill_roll_mode(&self, skill: Skill, roll_mode: RollMode) -> RollMode {
+        let armor_roll_mode = if skill == Skill::Stealth && self.has_armor_stealth_disadvantage() {
+            RollMode::Disadvantage
+        } else {
+            RollMode::Normal
+        };
+        self.get_roll_mode_from_sources(
+            D20Test::AbilityCheck(skill.ability()),
+            [roll_mode, armor_roll_mode]
+                .iter()
+                .copied()
+                .chain(self.skills[skill].roll_mode_sources()),
+        )
+    }
+
+    pub fn roll_skill_check(
+        &self,
+        skill: Skill,
+        circumstance: Option<&str>,
+        roll_mode: RollMode,
+        roller: &mut dyn Roller,
+    ) -> D20Roll {
+        roll_d20_with_rules(
+            self.get_skill_modifier(skill, circumstance) as i16,
+            self.get_skill_roll_mode(skill, roll_mode),
+            &self.get_roll_rules(),
+            roller,
+        )
+    }
+
+    pub fn get_passive_score(&self, skill: Skill) -> i16 {
+        let adjustment = match self.get_skill_roll_mode(skill, RollMode::Normal) {
+            RollMode::Advantage => PASSIVE_SCORE_ROLL_MODE_ADJUSTMENT,
+            RollMode::Disadvantage => -PASSIVE_SCORE_ROLL_MODE_ADJUSTMENT,
+            RollMode::Normal => 0,
+        };
+        PASSIVE_SCORE_BASE + self.get_skill_modifier(skill, None) as i16 + adjustment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::{Condition, ConditionDuration};
+    use crate::roller::ScriptedRoller;
+    use crate::tests::create_sample_character;
+    use crate::{load_races_from_file, RaceType};
+
+    #[test]
+    fn proficiency_levels_scale_the_bonus() {
+        let mut character = create_sample_character();
+        let wisdom_modifier = character.get_ability_modifier(Ability::Wisdom);
+        assert_eq!(
+            character.get_skill_modifier(Skill::Insight, None),
+            wisdom_modifier
+        );
+
+        character.set_skill_proficiency(Skill::Insight, SkillProficiency::Proficient);
+        assert_eq!(
+            character.get_skill_modifier(Skill::Insight, None),
+            wisdom_modifier + 2
+        );
+        assert!(character
+            .get_skill_proficiencies()
+            .contains(&Skill::Insight));
+        character.set_skill_proficiency(Skill::Insight, SkillProficiency::Expertise);
+        assert_eq!(
+            character.skills[Skill::Insight].modifier,
+            wisdom_modifier + 4
+        );
+        character.set_skill_proficiency(Skill::Insight, SkillProficiency::Half);
+        assert!(!character
+            .get_skill_proficiencies()
+            .contains(&Skill::Insight));
+        assert_eq!(
+            character.get_passive_score(Skill::Insight),
+            11 + wisdom_modifier as i16
+        );
+
+        character
+            .add_condition(
+                Condition::Poisoned,
+                "Poison",
+                ConditionDuration::UntilRemoved,
+            )
+            .unwrap();
+        assert_eq!(
+            character.get_passive_score(Skill::Insight),
+            6 + wisdom_modifier as i16
+        );
+        let check = character.roll_skill_check(
+            Skill::Insight,
+            None,
+            RollMode::Normal,
+            &mut ScriptedRoller::new(vec![14, 6]),
+        );
+        assert_eq!(check.mode, RollMode::Disadvantage);
+        assert_eq!(check.total, 6 + 1 + wisdom_modifier as i16);
+    }
+
+    #[test]
+    fn stonecunning_grants_expertise_for_stonework() {
+        let mut character = create_sample_character();
+        character.race = load_races_from_file("data/races.yaml")
+            .unwrap()
+            .find_race(RaceType::Dwarf)
+            .unwrap();
+
+        assert_eq!(
+            character.get_skill_proficiency(Skill::History, None),
+            SkillProficiency::None
+        );
+        assert_eq!(
+            character.get_skill_proficiency(Skill::History, Some("stonework")),
+            SkillProficiency::Expertise
+        );
+        let intelligence_modifier = character.get_ability_modifier(Ability::Intelligence);
+        assert_eq!(
+            character.get_skill_modifier(Skill::History, Some("stonework")),
+            intelligence_modifier + 4
+        );
+    }
+}
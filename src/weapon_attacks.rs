@@ -0,0 +1,424 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::d20::{AttackRoll, RollMode};
+use crate::dice::{DiceExpression, DiceRoll, DiceTerm, TermValue};
+use crate::roll_rules::DamageSource;
+use crate::{Ability, Character, DamageType, Weapon, WeaponCategory, WeaponProperty, WeaponType};
+
+const MELEE_REACH: u32 = 5;
+const REACH_PROPERTY_BONUS: u32 = 5;
+
+// Proficiency covers a whole category, such as martial weapons, or a single
+// weapon from the catalogue, such as the longsword.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum WeaponProficiency {
+    Category(WeaponCategory),
+    Weapon(String),
+}
+
+impl WeaponProficiency {
+    pub fn covers(&self, weapon: &Weapon) -> bool {
+        match self {
+            WeaponProficiency::Category(category) => weapon.category == *category,
+            WeaponProficiency::Weapon(name) => weapon.name == *name,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WeaponAttack {
+    pub target_armor_class: i16,
+    // Feet between the attacker and the target.
+    pub distance: u32,
+    pub two_handed: bool,
+    pub roll_mode: RollMode,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeaponAttackReport {
+    pub weapon: String,
+    pub ability: Ability,
+    pub proficient: bool,
+    pub attack_bonus: i16,
+    pub long_range: bool,
+    pub attack: AttackRoll,
+    pub hit: bool,
+    // Only rolled on a hit with a weapon that deals damage.
+    pub damage: Option<DiceRoll>,
+    pub damage_type: Option<DamageType>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WeaponAttackError {
+    OutOfRange { distance: u32, range: u32 },
+    RequiresTwoHands(String),
+}
+
+impl fmt::Display for WeaponAttackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeaponAttackError::OutOfRange { distance, range } => write!(
+                f,
+                "a target {} feet away is beyond the range of {} feet",
+                distance, range
+            ),
+            WeaponAttackError::RequiresTwoHands(name) => {
+                write!(f, "the {} has to be wielded with two hands", name)
+            }
+        }
+    }
+}
+
+impl Weapon {
+    pub fn has_property(&self, property: &WeaponProperty) -> bool {
+        self.properties.contains(property)
+    }
+
+    pub fn get_range(&self) -> Option<(u32, u32)> {
+        self.properties.iter().find_map(|property| match property {
+            WeaponProperty::Range { normal, long } => Some((*normal, *long)),
+            _ => None,
+        })
+    }
+
+    pub fn get_reach(&self) -> u32 {
+        if self.has_property(&WeaponProperty::Reach) {
+            MELEE_REACH + REACH_PROPERTY_BONUS
+        } else {
+            MELEE_REACH
+        }
+    }
+
+    // Returns whether the target is at long range. Melee weapons with the Thrown
+    // property can be thrown at targets beyond their reach.
+    pub fn check_distance(&self, distance: u32) -> Result<bool, WeaponAttackError> {
+        let is_melee = self.weapon_type == WeaponType::Melee;
+        if is_melee && distance <= self.get_reach() {
+            return Ok(false);
+        }
+
+        match self.get_range() {
+            Some((normal, long)) if !is_melee || self.has_property(&WeaponProperty::Thrown) => {
+                if distance <= normal {
+                    Ok(false)
+                } else if distance <= long {
+                    Ok(true)
+                } else {
+                    Err(WeaponAttackError::OutOfRange {
+                        distance,
+                        range: long,
+                    })
+                }
+            }
+            _ => Err(WeaponAttackError::OutOfRange {
+                distance,
+                range: self.get_reach(),
+            }),
+        }
+    }
+
+    pub fn is_versatile(&self) -> bool {
+        self.properties
+            .iter()
+            .any(|property| matches!(property, WeaponProperty::Versatile(_)))
+    }
+
+    // Throwing a melee weapon at a target beyond its reach is a ranged attack.
+    // Only two-handed and versatile weapons count as wielded with two hands, so
+    // holding a dagger in both hands doesn't trigger Great Weapon Fighting.
+    pub fn get_damage_source(&self, attack: WeaponAttack) -> DamageSource {
+        if self.weapon_type == WeaponType::Melee && attack.distance <= self.get_reach() {
+            DamageSource::MeleeAttack {
+                two_handed: attack.two_handed
+                    && (self.has_property(&WeaponProperty::TwoHanded) || self.is_versatile()),
+            }
+        } else {
+            DamageSource::RangedAttack
+        }
+    }
+
+    // Versatile weapons use their larger dice when wielded with two hands.
+    pub fn get_damage_dice(&self, two_handed: bool) -> Option<&DiceExpression> {
+        let damage = self.damage.as_ref()?;
+        if two_handed {
+            let versatile = self.properties.iter().find_map(|property| match property {
+                WeaponProperty::Versatile(dice) => Some(dice),
+                _ => None,
+            });
+            if let Some(dice) = versatile {
+                return Some(dice);
+            }
+        }
+        Some(&damage.dice)
+    }
+}
+
+impl Character {
+    pub fn get_weapon_proficiencies(&self) -> impl Iterator<Item = &WeaponProficiency> {
+        self.traits
+            .iter()
+            .chain(self.race.racial_traits.traits.iter())
+            .flat_map(|character_trait| character_trait.weapon_proficiency_modifiers.iter())
+            .chain(
+                self.class
+                    .iter()
+                    .flat_map(|class| class.features.weapon_proficiency_modifiers.iter()),
+            )
+            .map(|weapon_proficiency_modifier| &weapon_proficiency_modifier.value)
+    }
+
+    pub fn has_weapon_proficiency(&self, weapon: &Weapon) -> bool {
+        self.get_weapon_proficiencies()
+            .any(|weapon_proficiency| weapon_proficiency.covers(weapon))
+    }
+
+    // Melee weapons use Strength and ranged weapons Dexterity. Finesse weapons
+    // use whichever is better.
+    pub fn get_weapon_attack_ability(&self, weapon: &Weapon) -> Ability {
+        if weapon.has_property(&WeaponProperty::Finesse) {
+            if self.get_ability_modifier(Ability::Dexterity)
+                > self.get_ability_modifier(Ability::Strength)
+            {
+                Ability::Dexterity
+            } else {
+                Ability::Strength
+            }
+        } else {
+            match weapon.weapon_type {
+                WeaponType::Melee => Ability::Strength,
+                WeaponType::Ranged => Ability::Dexterity,
+            }
+        }
+    }
+
+    // The proficiency bonus is only added with a weapon the character is
+    // proficient with.
+    pub fn get_weapon_attack_bonus(&self, weapon: &Weapon, ability: Ability) -> i16 {
+        let proficiency_bonus = if self.has_weapon_proficiency(weapon) {
+            self.get_proficiency_bonus() as i16
+        } else {
+            0
+        };
+        self.get_ability_modifier(ability) as i16 + proficiency_bonus
+    }
+
+    // Every term deals the weapon's damage type, and the ability modifier is
+    // added as a flat term so that critical hits don't double it.
+    pub fn get_weapon_damage(
+        &self,
+        weapon: &Weapon,
+        ability: Ability,
+        two_handed: bool,
+    ) -> Option<DiceExpression> {
+        let damage_type = weapon.damage.as_ref()?.damage_type;
+        let mut terms: Vec<DiceTerm> = weapon
+            .get_damage_dice(two_handed)?
+            .terms
+            .iter()
+            .map(|term| DiceTerm {
+                damage_type: Some(damage_type),
+                ..*term
+            })
+            .collect();
+        let ability_modifier = self.get_ability_modifier(ability) as i32;
+        if ability_modifier != 0 {
+            terms.push(DiceTerm {
+                negative: ability_modifier < 0,
+                value: TermValue::Flat(ability_modifier.abs()),
+                damage_type: Some(damage_type),
+            });
+        }
+
+        Some(DiceExpression {
+            terms,
+            fixed_value: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roll_rules::{RollRule, RollRuleEffect, RollRuleTarget};
+    use crate::roller::ScriptedRoller;
+    use crate::tests::create_sample_character;
+    use crate::{
+        load_weapons_from_file, EntityRoll, RollRuleModifier, Trait, WeaponProficiencyModifier,
+    };
+
+    fn find_weapon(name: &str) -> Weapon {
+        load_weapons_from_file("data/weapons.yaml")
+            .unwrap()
+            .into_iter()
+            .find(|weapon| weapon.name == name)
+            .unwrap()
+    }
+
+    fn attack(distance: u32, two_handed: bool) -> WeaponAttack {
+        WeaponAttack {
+            target_armor_class: 15,
+            distance,
+            two_handed,
+            roll_mode: RollMode::Normal,
+        }
+    }
+
+    #[test]
+    fn versatile_weapons_hit_harder_with_two_hands() {
+        let mut character = create_sample_character();
+        let longsword = find_weapon("Longsword");
+        let strength_modifier = character.get_ability_modifier(Ability::Strength);
+        assert_eq!(
+            character.get_weapon_attack_ability(&longsword),
+            Ability::Strength
+        );
+        assert!(!character.has_weapon_proficiency(&longsword));
+
+        let report = character
+            .roll_weapon_attack(
+                &longsword,
+                attack(5, true),
+                &mut ScriptedRoller::new(vec![14, 10]),
+            )
+            .unwrap();
+        assert!(!report.proficient);
+        assert_eq!(report.attack_bonus, strength_modifier as i16);
+        assert_eq!(report.hit, 14 + strength_modifier as i16 >= 15);
+
+        character.class[0]
+            .features
+            .weapon_proficiency_modifiers
+            .push(WeaponProficiencyModifier {
+                name: String::from("Martial Weapons"),
+                value: WeaponProficiency::Category(WeaponCategory::MartialWeapons),
+            });
+        let report = character
+            .roll_weapon_attack(
+                &longsword,
+                attack(5, true),
+                &mut ScriptedRoller::new(vec![20, 10, 7]),
+            )
+            .unwrap();
+        assert!(report.proficient);
+        assert_eq!(report.attack_bonus, strength_modifier as i16 + 2);
+        assert!(report.attack.critical && report.hit);
+        assert_eq!(report.damage_type, Some(DamageType::Slashing));
+        let damage = report.damage.unwrap();
+        assert_eq!(damage.total, 17 + strength_modifier as i32);
+        assert_eq!(
+            damage.total_for_damage_type(DamageType::Slashing),
+            damage.total
+        );
+
+        assert_eq!(
+            character.roll_weapon_attack(
+                &find_weapon("Greataxe"),
+                attack(5, false),
+                &mut ScriptedRoller::new(vec![])
+            ),
+            Err(WeaponAttackError::RequiresTwoHands(String::from(
+                "Greataxe"
+            )))
+        );
+    }
+
+    #[test]
+    fn ranged_attacks_use_dexterity_and_range_increments() {
+        let character = create_sample_character();
+        let dexterity_modifier = character.get_ability_modifier(Ability::Dexterity);
+        let longbow = find_weapon("Longbow");
+        assert_eq!(
+            character.get_weapon_attack_ability(&longbow),
+            Ability::Dexterity
+        );
+
+        let mut roller = ScriptedRoller::new(vec![18, 3, 4]);
+        let report = character
+            .roll_weapon_attack(&longbow, attack(300, true), &mut roller)
+            .unwrap();
+        assert!(report.long_range);
+        assert_eq!(report.attack.d20.mode, RollMode::Disadvantage);
+        assert!(!report.hit);
+        assert_eq!(report.damage, None);
+        assert_eq!(roller.remaining(), 1);
+
+        assert_eq!(
+            longbow.check_distance(700),
+            Err(WeaponAttackError::OutOfRange {
+                distance: 700,
+                range: 600
+            })
+        );
+        let handaxe = find_weapon("Handaxe");
+        assert_eq!(handaxe.check_distance(5), Ok(false));
+        assert_eq!(handaxe.check_distance(40), Ok(true));
+        assert_eq!(
+            handaxe.get_damage_source(attack(5, false)),
+            DamageSource::MeleeAttack { two_handed: false }
+        );
+        assert_eq!(
+            handaxe.get_damage_source(attack(20, false)),
+            DamageSource::RangedAttack
+        );
+        assert_eq!(
+            find_weapon("Glaive").check_distance(15),
+            Err(WeaponAttackError::OutOfRange {
+                distance: 15,
+                range: 10
+            })
+        );
+        let dagger_damage = character
+            .get_weapon_damage(&find_weapon("Dagger"), Ability::Dexterity, false)
+            .unwrap();
+        assert_eq!(dagger_damage.max(), 4 + dexterity_modifier as i32);
+    }
+
+    #[test]
+    fn great_weapon_fighting_needs_a_two_handed_or_versatile_weapon() {
+        let mut character = create_sample_character();
+        character.traits.push(Trait {
+            name: String::from("Great Weapon Fighting"),
+            roll_rule_modifiers: vec![RollRuleModifier {
+                name: String::from("Great Weapon Fighting"),
+                value: RollRule {
+                    target: RollRuleTarget::TwoHandedMeleeDamage,
+                    effect: RollRuleEffect::RerollAtOrBelow(2),
+                },
+            }],
+            ..Default::default()
+        });
+
+        let dagger = find_weapon("Dagger");
+        assert_eq!(
+            dagger.get_damage_source(attack(5, true)),
+            DamageSource::MeleeAttack { two_handed: false }
+        );
+        let mut roller = ScriptedRoller::new(vec![18, 1]);
+        let report = character
+            .roll_weapon_attack(&dagger, attack(5, true), &mut roller)
+            .unwrap();
+        let damage = report.damage.unwrap();
+        assert_eq!(damage.terms[0].dice[0].value, 1);
+        assert!(damage.terms[0].dice[0].adjustments.is_empty());
+        assert_eq!(roller.remaining(), 0);
+
+        let longsword = find_weapon("Longsword");
+        assert_eq!(
+            longsword.get_damage_source(attack(5, true)),
+            DamageSource::MeleeAttack { two_handed: true }
+        );
+        assert_eq!(
+            longsword.get_damage_source(attack(5, false)),
+            DamageSource::MeleeAttack { two_handed: false }
+        );
+        let mut roller = ScriptedRoller::new(vec![18, 1, 7]);
+        let report = character
+            .roll_weapon_attack(&longsword, attack(5, true), &mut roller)
+            .unwrap();
+        let damage = report.damage.unwrap();
+        assert_eq!(damage.terms[0].dice[0].value, 7);
+        assert_eq!(damage.terms[0].dice[0].adjustments[0].original, 1);
+    }
+}